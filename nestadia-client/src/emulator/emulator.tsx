@@ -41,6 +41,12 @@ class Emulator extends React.Component<{setAppState: Function, mode: EmulatorMod
 
     wsAddEventListener(ws: WebSocket) {
         ws.addEventListener("message", (event) => {
+            // Text messages are errors, like a ROM that couldn't be loaded
+            if (typeof event.data === "string") {
+                alert(event.data);
+                return;
+            }
+
             let frameEncoded: Uint8Array = new Uint8Array(event.data);
             let frame = gzip.unzip(frameEncoded);
 
@@ -81,10 +81,10 @@ impl Application for NestadiaIced {
             Message::Disassemble => {
                 self.disassembly = self
                     .emulation_state
-                    .read()
+                    .write()
                     .unwrap()
                     .emulator
-                    .disassemble(0, 0)
+                    .disassemble(0, 0xFFFF)
             }
         }
         Command::none()
@@ -102,7 +102,8 @@ impl Application for NestadiaIced {
             });
         }
 
-        let cpu = self.emulation_state.read().unwrap().emulator.cpu().clone();
+        let info = self.emulation_state.read().unwrap().emulator.debug_info();
+        let cpu = &info.cpu;
 
         // Filter the disassembly to show only part of it
         let mut disassembly = Vec::new();
@@ -145,7 +146,18 @@ impl Application for NestadiaIced {
                 .push(Text::new(&format!("y: {:#x}", cpu.y)))
                 .push(Text::new(&format!("st: {:#x}", cpu.st)))
                 .push(Text::new(&format!("pc: {:#x}", cpu.pc)))
-                .push(Text::new(&format!("status: {:#x}", cpu.status_register))),
+                .push(Text::new(&format!("status: {:#x}", cpu.status_register)))
+                .push(Text::new(&format!(
+                    "scanline: {}, cycle: {}",
+                    info.scanline, info.cycle
+                )))
+                .push(Text::new(&format!(
+                    "scroll: {}, {}",
+                    info.scroll.0, info.scroll.1
+                )))
+                .push(Text::new(&format!("prg banks: {:?}", info.prg_banks)))
+                .push(Text::new(&format!("chr banks: {:?}", info.chr_banks)))
+                .push(Text::new(&format!("frame: {}", info.frame_count))),
         );
 
         debugger_window.into()
@@ -1,55 +1,35 @@
 #[macro_use]
 extern crate libretro_backend;
 
-extern crate bitflags;
 extern crate nestadia;
 
-use bitflags::bitflags;
 use libretro_backend::{
     AudioVideoInfo, Core, CoreInfo, GameData, JoypadButton, LoadGameResult, PixelFormat, Region,
     RuntimeHandle,
 };
-use nestadia::Emulator;
+use nestadia::{Buttons, ControllerPort, Emulator};
 
 // NES outputs a 256 x 240 pixel image
 const NUM_PIXELS: usize = 256 * 240;
 
-bitflags! {
-    #[derive(Default)]
-    struct ControllerState: u8 {
-        const NONE = 0x00;
-        const A = 0x80;
-        const B = 0x40;
-        const SELECT = 0x20;
-        const START = 0x10;
-        const UP = 0x08;
-        const DOWN = 0x04;
-        const LEFT = 0x02;
-        const RIGHT = 0x01;
-    }
-}
-
-impl From<JoypadButton> for ControllerState {
-    fn from(button: JoypadButton) -> Self {
-        match button {
-            JoypadButton::A => Self::A,
-            JoypadButton::B => Self::B,
-            JoypadButton::Start => Self::START,
-            JoypadButton::Select => Self::SELECT,
-            JoypadButton::Down => Self::DOWN,
-            JoypadButton::Left => Self::LEFT,
-            JoypadButton::Right => Self::RIGHT,
-            JoypadButton::Up => Self::UP,
-            _ => Self::NONE,
-        }
+// This maps the libretro joypad to a controller input
+fn joypad_to_button(button: JoypadButton) -> Option<Buttons> {
+    match button {
+        JoypadButton::A => Some(Buttons::A),
+        JoypadButton::B => Some(Buttons::B),
+        JoypadButton::Start => Some(Buttons::START),
+        JoypadButton::Select => Some(Buttons::SELECT),
+        JoypadButton::Down => Some(Buttons::DOWN),
+        JoypadButton::Left => Some(Buttons::LEFT),
+        JoypadButton::Right => Some(Buttons::RIGHT),
+        JoypadButton::Up => Some(Buttons::UP),
+        _ => None,
     }
 }
 
 pub struct State {
     emulator: Option<Emulator>,
     game_data: Option<GameData>,
-    controller1: ControllerState,
-    controller2: ControllerState,
 }
 
 impl State {
@@ -63,8 +43,6 @@ impl State {
         State {
             emulator: None,
             game_data: None,
-            controller1: ControllerState::NONE,
-            controller2: ControllerState::NONE,
         }
     }
 }
@@ -134,6 +112,7 @@ impl Core for State {
 
     fn on_run(&mut self, handle: &mut RuntimeHandle) {
         let mask_reg;
+        let palette;
 
         let emulator = match &mut self.emulator {
             None => {
@@ -141,18 +120,15 @@ impl Core for State {
             }
             Some(emulator) => {
                 mask_reg = emulator.get_ppu_mask_reg();
+                palette = *emulator.palette();
                 emulator
             }
         };
 
-        let frame = loop {
-            if let Some(frame) = emulator.clock() {
-                break frame;
-            }
-        };
+        let frame = emulator.run_frame();
 
         let mut current_frame = [0u8; NUM_PIXELS * 4];
-        nestadia::frame_to_argb(mask_reg, &frame, &mut current_frame);
+        nestadia::frame_to_argb_with_palette(&palette, mask_reg, &frame, &mut current_frame);
 
         handle.upload_video_frame(&current_frame);
 
@@ -173,37 +149,28 @@ impl Core for State {
         handle.upload_audio_frame(&audio_buffer[..]);
 
         // Reading controller inputs
-        macro_rules! update_controllers {
-            ( $( $button:ident ),+ ) => (
-                $(
-                    let controller_state: ControllerState = match ControllerState::from(JoypadButton::$button) {
-                        ControllerState::NONE => { return; },
-                        state => state
-                    };
-
-                    // Setting controller 1 button state
-                    if handle.is_joypad_button_pressed(0, JoypadButton::$button) {
-                        self.controller1 |= controller_state;
-
-                    } else if self.controller1.contains(controller_state) {
-                        self.controller1 &= !controller_state;
+        for &button in &[
+            JoypadButton::A,
+            JoypadButton::B,
+            JoypadButton::Up,
+            JoypadButton::Down,
+            JoypadButton::Left,
+            JoypadButton::Right,
+            JoypadButton::Select,
+            JoypadButton::Start,
+        ] {
+            if let Some(nes_button) = joypad_to_button(button) {
+                for (index, &port) in [ControllerPort::One, ControllerPort::Two]
+                    .iter()
+                    .enumerate()
+                {
+                    let pressed = handle.is_joypad_button_pressed(index as u32, button);
+                    if emulator.controller(port).contains(nes_button) != pressed {
+                        emulator.set_button(port, nes_button, pressed);
                     }
-
-                    // Setting controller 2 button state
-                    if handle.is_joypad_button_pressed(1, JoypadButton::$button) {
-                        self.controller2 |= controller_state;
-
-                    } else if self.controller2.contains(controller_state) {
-                        self.controller2 &= !controller_state;
-                    }
-                )+
-            )
+                }
+            }
         }
-
-        update_controllers!(A, B, Up, Down, Left, Right, Select, Start);
-
-        emulator.set_controller1(self.controller1.bits());
-        emulator.set_controller2(self.controller2.bits());
     }
 
     fn on_reset(&mut self) {
@@ -35,6 +35,8 @@ async fn emulator_start_param(req: HttpRequest, stream: web::Payload) -> impl Re
         heartbeat: Instant::now(),
         custom_rom: vec![],
         custom_rom_len: 0,
+        custom_patch: None,
+        custom_patch_len: 0,
     };
 
     ws::start(websocket, &req, stream)
@@ -46,6 +48,22 @@ async fn custom_emulator(req: HttpRequest, stream: web::Payload) -> impl Respond
         heartbeat: Instant::now(),
         custom_rom: vec![],
         custom_rom_len: 0,
+        custom_patch: None,
+        custom_patch_len: 0,
+    };
+
+    ws::start(websocket, &req, stream)
+}
+
+// Same as `custom_emulator`, but an IPS or BPS patch is uploaded after the ROM, the same way
+async fn custom_patched_emulator(req: HttpRequest, stream: web::Payload) -> impl Responder {
+    let websocket = NestadiaWs {
+        state: EmulationState::Waiting,
+        heartbeat: Instant::now(),
+        custom_rom: vec![],
+        custom_rom_len: 0,
+        custom_patch: Some(vec![]),
+        custom_patch_len: 0,
     };
 
     ws::start(websocket, &req, stream)
@@ -63,6 +81,10 @@ pub async fn actix_main(bind_addr: String, port: u16) -> std::io::Result<()> {
             .service(
                 web::scope("/api")
                     .route("/emulator/custom", web::get().to(custom_emulator))
+                    .route(
+                        "/emulator/custom_patched",
+                        web::get().to(custom_patched_emulator),
+                    )
                     .route("/emulator/{rom_name}", web::get().to(emulator_start_param))
                     .route("/list", web::get().to(rom_list)),
             )
@@ -27,7 +27,7 @@ pub struct EmulationError(RomParserError);
 
 impl core::fmt::Display for EmulationError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        write!(f, "{:?}", &self)
+        write!(f, "Could not load the ROM: {}", self.0)
     }
 }
 
@@ -44,6 +44,8 @@ pub struct NestadiaWs {
     pub heartbeat: Instant,
     pub custom_rom: Vec<u8>,
     pub custom_rom_len: usize,
+    pub custom_patch: Option<Vec<u8>>, // `Some` when an IPS or BPS patch is uploaded after the ROM
+    pub custom_patch_len: usize,
 }
 
 struct FrameStream {
@@ -85,7 +87,7 @@ impl Actor for NestadiaWs {
     fn started(&mut self, ctx: &mut Self::Context) {
         if let EmulationState::Ready { rom } = &self.state {
             // At this point, ROMs are hardcoded, so this shouldn't fail
-            let sender = start_emulation(ctx, rom).unwrap();
+            let sender = start_emulation(ctx, rom, None).unwrap();
             self.state = EmulationState::Started(sender);
         }
 
@@ -121,22 +123,35 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for NestadiaWs {
             Ok(ws::Message::Binary(bin)) => {
                 match &mut self.state {
                     EmulationState::Waiting => {
-                        // Received chunk of ROM
-                        if self.custom_rom.is_empty() {
-                            // First 4 bytes are used to specify the ROM's size
-                            self.custom_rom_len =
-                                u32::from_le_bytes(bin[0..4].try_into().unwrap()) as usize;
-                            self.custom_rom = Vec::with_capacity(self.custom_rom_len);
-
-                            self.custom_rom.extend_from_slice(&bin[4..]);
-                        } else {
-                            self.custom_rom.extend_from_slice(&bin);
+                        // Received chunk of ROM, or of the patch once the ROM is complete
+                        if !is_upload_complete(&self.custom_rom, self.custom_rom_len) {
+                            receive_chunk(&mut self.custom_rom, &mut self.custom_rom_len, &bin);
+                        } else if let Some(patch) = &mut self.custom_patch {
+                            receive_chunk(patch, &mut self.custom_patch_len, &bin);
                         }
 
-                        if self.custom_rom.len() == self.custom_rom_len {
-                            // If there's an error, just ignore it and wait for a valid ROM
-                            if let Ok(sender) = start_emulation(ctx, &self.custom_rom) {
-                                self.state = EmulationState::Started(sender);
+                        let patch_complete = match &self.custom_patch {
+                            Some(patch) => is_upload_complete(patch, self.custom_patch_len),
+                            None => true,
+                        };
+
+                        if is_upload_complete(&self.custom_rom, self.custom_rom_len)
+                            && patch_complete
+                        {
+                            match start_emulation(
+                                ctx,
+                                &self.custom_rom,
+                                self.custom_patch.as_deref(),
+                            ) {
+                                Ok(sender) => self.state = EmulationState::Started(sender),
+                                Err(e) => {
+                                    // Tell the user what's wrong, and wait for a valid ROM
+                                    ctx.text(e.to_string());
+                                    self.custom_rom.clear();
+                                    if let Some(patch) = &mut self.custom_patch {
+                                        patch.clear();
+                                    }
+                                }
                             }
                         }
                     }
@@ -175,12 +190,37 @@ impl Handler<Frame> for NestadiaWs {
     }
 }
 
+// Files are uploaded in chunks, the first 4 bytes specifying the size of the file
+fn receive_chunk(data: &mut Vec<u8>, len: &mut usize, chunk: &[u8]) {
+    if data.is_empty() {
+        if chunk.len() < 4 {
+            return;
+        }
+
+        *len = u32::from_le_bytes(chunk[0..4].try_into().unwrap()) as usize;
+        *data = Vec::with_capacity(*len);
+        data.extend_from_slice(&chunk[4..]);
+    } else {
+        data.extend_from_slice(chunk);
+    }
+}
+
+fn is_upload_complete(data: &[u8], len: usize) -> bool {
+    !data.is_empty() && data.len() == len
+}
+
 fn start_emulation(
     ctx: &mut ws::WebsocketContext<NestadiaWs>,
     rom: &[u8],
+    patch: Option<&[u8]>,
 ) -> Result<Sender<EmulatorInput>, Box<dyn std::error::Error>> {
-    // Read save file
-    let rom_hash = blake3::hash(rom).to_hex().to_string();
+    // Read save file. A patched game gets its own, since the hack may not use the same save layout
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(rom);
+    if let Some(patch) = patch {
+        hasher.update(patch);
+    }
+    let rom_hash = hasher.finalize().to_hex().to_string();
     let save_path = "saves/".to_string() + &rom_hash + ".save";
     let mut buf = Vec::new();
 
@@ -191,7 +231,11 @@ fn start_emulation(
         None
     };
 
-    let mut emulator = Emulator::new(rom, save_data).map_err(EmulationError)?;
+    let emulator = match patch {
+        Some(patch) => Emulator::new_with_patch(rom, patch, save_data),
+        None => Emulator::new(rom, save_data),
+    };
+    let mut emulator = emulator.map_err(EmulationError)?;
 
     let (input_sender, input_receiver) = channel();
     let (frame_sender, frame_receiver) = channel();
@@ -211,13 +255,7 @@ fn start_emulation(
                 }
             };
 
-            // Loop until we get a frame
-            let frame = loop {
-                if let Some(frame) = emulator.clock() {
-                    break frame;
-                }
-            }
-            .to_vec();
+            let frame = emulator.run_frame().to_vec();
 
             if Instant::now() < next_frame_time {
                 std::thread::sleep(next_frame_time.duration_since(Instant::now()));
@@ -244,7 +282,10 @@ fn start_emulation(
             log::warn!("Couldn't create save folder: {}", e)
         };
 
-        if let Some(save_data) = emulator.get_save_data() {
+        if let Some(save_data) = emulator
+            .get_save_data()
+            .filter(|_| emulator.is_save_dirty())
+        {
             if let Ok(mut f) = OpenOptions::new()
                 .read(true)
                 .write(true)
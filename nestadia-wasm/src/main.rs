@@ -1,7 +1,4 @@
-#[macro_use]
-extern crate bitflags;
-
-use nestadia::Emulator;
+use nestadia::{Buttons, ControllerPort, Emulator};
 use wasm_bindgen::{Clamped, JsCast};
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
 use yew::{
@@ -10,17 +7,18 @@ use yew::{
 };
 use yew::{virtual_dom::VNode, ChangeData};
 
-bitflags! {
-    #[derive(Default)]
-    struct ControllerState: u8 {
-        const A = 0x80;
-        const B = 0x40;
-        const SELECT = 0x20;
-        const START = 0x10;
-        const UP = 0x08;
-        const DOWN = 0x04;
-        const LEFT = 0x02;
-        const RIGHT = 0x01;
+// This maps the keyboard input to a controller input
+fn key_to_button(code: &str) -> Option<Buttons> {
+    match code {
+        "KeyX" => Some(Buttons::A),
+        "KeyZ" => Some(Buttons::B),
+        "KeyA" => Some(Buttons::SELECT),
+        "KeyS" => Some(Buttons::START),
+        "ArrowDown" => Some(Buttons::DOWN),
+        "ArrowLeft" => Some(Buttons::LEFT),
+        "ArrowRight" => Some(Buttons::RIGHT),
+        "ArrowUp" => Some(Buttons::UP),
+        _ => None,
     }
 }
 
@@ -67,10 +65,12 @@ impl Component for MainComponent {
                 false
             }
 
-            // When the ROM is loaded, store it in the component
+            // When the ROM is loaded, store it in the component, or show why it can't be run
             MainMsg::LoadedRom(f) => {
-                self.emulator_component =
-                    html! {<EmulatorComponent rom=f.content></EmulatorComponent>};
+                self.emulator_component = match Emulator::new(&f.content, None) {
+                    Ok(_) => html! {<EmulatorComponent rom=f.content></EmulatorComponent>},
+                    Err(e) => html! {<p>{ format!("Could not load the ROM: {}", e) }</p>},
+                };
                 true
             }
             _ => false,
@@ -96,7 +96,6 @@ struct EmulatorComponent {
     _link: ComponentLink<Self>,
     emulator: Emulator,
     canvas_ref: NodeRef,
-    controller1_state: ControllerState,
 
     _interval_handle: yew::services::interval::IntervalTask,
     _keyup_handle: yew::services::keyboard::KeyListenerHandle,
@@ -119,7 +118,7 @@ impl Component for EmulatorComponent {
     type Properties = RomProps;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let emulator = Emulator::new(&props.rom, None).unwrap();
+        let emulator = Emulator::new(&props.rom, None).expect("ROM is checked when it's loaded");
 
         // Render a frame every 1/60th of a second
         let _interval_handle = yew::services::IntervalService::spawn(
@@ -143,7 +142,6 @@ impl Component for EmulatorComponent {
             _link: link,
             emulator,
             canvas_ref: Default::default(),
-            controller1_state: Default::default(),
 
             _interval_handle,
             _keyup_handle,
@@ -153,15 +151,11 @@ impl Component for EmulatorComponent {
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         let mask_reg = self.emulator.get_ppu_mask_reg();
+        let palette = *self.emulator.palette();
 
         match msg {
             EmulatorMsg::RenderFrame => {
-                // Run until there's a frame
-                let frame = loop {
-                    if let Some(frame) = self.emulator.clock() {
-                        break frame;
-                    }
-                };
+                let frame = self.emulator.run_frame();
 
                 // Get canvas 2d context
                 let context = self
@@ -177,7 +171,7 @@ impl Component for EmulatorComponent {
                 // Convert to RGBA
                 let mut rgba_frame = [0u8; 256 * 240 * 4];
 
-                nestadia::frame_to_rgba(mask_reg, &frame, &mut rgba_frame);
+                nestadia::frame_to_rgba_with_palette(&palette, mask_reg, &frame, &mut rgba_frame);
 
                 // Draw image data to the canvas
                 let image_data =
@@ -190,45 +184,17 @@ impl Component for EmulatorComponent {
             }
             // Remove the button from the controller state
             EmulatorMsg::KeyUp(e) => {
-                let input = match e.key_code() {
-                    0x58 => Some(ControllerState::A),
-                    0x5a => Some(ControllerState::B),
-                    0x41 => Some(ControllerState::SELECT),
-                    0x53 => Some(ControllerState::START),
-                    0x28 => Some(ControllerState::DOWN),
-                    0x25 => Some(ControllerState::LEFT),
-                    0x27 => Some(ControllerState::RIGHT),
-                    0x26 => Some(ControllerState::UP),
-                    _ => None,
-                };
-
-                if let Some(f) = input {
-                    self.controller1_state.remove(f);
-
-                    self.emulator.set_controller1(self.controller1_state.bits());
-                };
+                if let Some(button) = key_to_button(&e.code()) {
+                    self.emulator.set_button(ControllerPort::One, button, false);
+                }
 
                 false
             }
             // Add the button from the controller state
             EmulatorMsg::KeyDown(e) => {
-                let input = match e.key_code() {
-                    0x58 => Some(ControllerState::A),
-                    0x5a => Some(ControllerState::B),
-                    0x41 => Some(ControllerState::SELECT),
-                    0x53 => Some(ControllerState::START),
-                    0x28 => Some(ControllerState::DOWN),
-                    0x25 => Some(ControllerState::LEFT),
-                    0x27 => Some(ControllerState::RIGHT),
-                    0x26 => Some(ControllerState::UP),
-                    _ => None,
-                };
-
-                if let Some(f) = input {
-                    self.controller1_state.insert(f);
-
-                    self.emulator.set_controller1(self.controller1_state.bits());
-                };
+                if let Some(button) = key_to_button(&e.code()) {
+                    self.emulator.set_button(ControllerPort::One, button, true);
+                }
 
                 false
             }
@@ -237,7 +203,7 @@ impl Component for EmulatorComponent {
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         // If the rom changes, reload the emulator.
-        self.emulator = Emulator::new(&props.rom, None).unwrap();
+        self.emulator = Emulator::new(&props.rom, None).expect("ROM is checked when it's loaded");
         false
     }
 
@@ -95,7 +95,7 @@ impl State {
             Ok(clap) => {
                 let opt = DebuggerOpt::from_clap(&clap);
                 match opt {
-                    DebuggerOpt::Continue => self.paused = false,
+                    DebuggerOpt::Continue => self.debugging = false,
                     DebuggerOpt::Break { addr } => self.add_breakpoint(addr),
                     DebuggerOpt::Delete { index } => self.remove_breakpoint(index),
                     DebuggerOpt::Step => self.step(&mut frame),
@@ -117,7 +117,7 @@ impl State {
     }
 
     fn add_breakpoint(&mut self, addr: u16) {
-        let disassembly = self.emulator.disassemble(0, 0);
+        let disassembly = self.emulator.disassemble(0x4020, 0xFFFF);
         let closest_addr = disassembly
             .iter()
             .min_by_key(|&(_, x, _)| (x.wrapping_sub(addr)))
@@ -181,21 +181,21 @@ impl State {
         }
     }
 
-    fn disassemble(&self, search_addr: Option<u16>) {
-        let cpu = self.emulator.cpu();
-        let disassembly = self.emulator.disassemble(0, 0);
+    fn disassemble(&mut self, search_addr: Option<u16>) {
+        let pc = self.emulator.cpu().pc;
+        let disassembly = self.emulator.disassemble(0x4020, 0xFFFF);
 
         let center_addr = if let Some(search_addr) = search_addr {
             search_addr
         } else {
-            cpu.pc
+            pc
         };
 
         for (prg_bank, addr, disas) in &disassembly {
             if (*addr as usize) > (center_addr as usize) - 20
                 && (*addr as usize) < (center_addr as usize) + 20
             {
-                let prefix = if (*addr as usize) == (cpu.pc as usize) {
+                let prefix = if (*addr as usize) == (pc as usize) {
                     ">"
                 } else {
                     " "
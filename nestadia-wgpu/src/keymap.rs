@@ -0,0 +1,199 @@
+//! Keyboard bindings of the controllers.
+//! They can be loaded from a JSON file that maps the buttons of each controller to a key, like:
+//! `{ "controller1": { "A": "X", "B": "Z", "Start": "S" }, "controller2": { "A": "K" } }`
+//! The key names are the ones of winit's `VirtualKeyCode`. Unlisted buttons are left unbound.
+//! The hotkeys of the emulator can't be bound.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use nestadia::{Buttons, ControllerPort};
+use serde::Deserialize;
+use winit::event::VirtualKeyCode;
+
+#[derive(Debug)]
+pub enum KeyMapError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    UnknownButton(String),
+    /// The same key is bound to two buttons
+    DuplicateKey(VirtualKeyCode),
+    /// The key is one of the hotkeys of the emulator
+    ReservedKey(VirtualKeyCode),
+}
+
+/// Keys handled by the emulator itself: quit, pause, frame advance, debugger, fast-forward,
+/// screenshot and the audio channel toggles
+pub const RESERVED_KEYS: [VirtualKeyCode; 11] = [
+    VirtualKeyCode::Escape,
+    VirtualKeyCode::P,
+    VirtualKeyCode::N,
+    VirtualKeyCode::D,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+];
+
+impl fmt::Display for KeyMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyMapError::Io(e) => write!(f, "{}", e),
+            KeyMapError::Parse(e) => write!(f, "{}", e),
+            KeyMapError::UnknownButton(name) => write!(
+                f,
+                "unknown button \"{}\", expected one of A, B, Select, Start, Up, Down, Left or Right",
+                name
+            ),
+            KeyMapError::DuplicateKey(key) => {
+                write!(f, "the {:?} key is bound to more than one button", key)
+            }
+            KeyMapError::ReservedKey(key) => {
+                write!(f, "the {:?} key is a hotkey of the emulator", key)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyMapConfig {
+    #[serde(default)]
+    controller1: HashMap<String, VirtualKeyCode>,
+    #[serde(default)]
+    controller2: HashMap<String, VirtualKeyCode>,
+}
+
+pub struct KeyMap {
+    bindings: HashMap<VirtualKeyCode, (ControllerPort, Buttons)>,
+}
+
+// X/Z for A/B, A/S for Select/Start and the arrows, all on the first controller
+impl Default for KeyMap {
+    fn default() -> Self {
+        let bindings = [
+            (VirtualKeyCode::X, Buttons::A),
+            (VirtualKeyCode::Z, Buttons::B),
+            (VirtualKeyCode::A, Buttons::SELECT),
+            (VirtualKeyCode::S, Buttons::START),
+            (VirtualKeyCode::Up, Buttons::UP),
+            (VirtualKeyCode::Down, Buttons::DOWN),
+            (VirtualKeyCode::Left, Buttons::LEFT),
+            (VirtualKeyCode::Right, Buttons::RIGHT),
+        ]
+        .iter()
+        .map(|&(key, button)| (key, (ControllerPort::One, button)))
+        .collect();
+
+        Self { bindings }
+    }
+}
+
+impl KeyMap {
+    pub fn load(path: &Path) -> Result<Self, KeyMapError> {
+        let data = std::fs::read(path).map_err(KeyMapError::Io)?;
+        Self::from_json(&data)
+    }
+
+    pub fn from_json(data: &[u8]) -> Result<Self, KeyMapError> {
+        let config: KeyMapConfig = serde_json::from_slice(data).map_err(KeyMapError::Parse)?;
+
+        let mut bindings = HashMap::new();
+        for (port, buttons) in [
+            (ControllerPort::One, config.controller1),
+            (ControllerPort::Two, config.controller2),
+        ]
+        .iter()
+        {
+            for (name, &key) in buttons {
+                let button =
+                    button_by_name(name).ok_or_else(|| KeyMapError::UnknownButton(name.clone()))?;
+                if RESERVED_KEYS.contains(&key) {
+                    return Err(KeyMapError::ReservedKey(key));
+                }
+                if bindings.insert(key, (*port, button)).is_some() {
+                    return Err(KeyMapError::DuplicateKey(key));
+                }
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// Controller and button bound to a key
+    pub fn get(&self, key: &VirtualKeyCode) -> Option<(ControllerPort, Buttons)> {
+        self.bindings.get(key).copied()
+    }
+}
+
+fn button_by_name(name: &str) -> Option<Buttons> {
+    match name.to_ascii_lowercase().as_str() {
+        "a" => Some(Buttons::A),
+        "b" => Some(Buttons::B),
+        "select" => Some(Buttons::SELECT),
+        "start" => Some(Buttons::START),
+        "up" => Some(Buttons::UP),
+        "down" => Some(Buttons::DOWN),
+        "left" => Some(Buttons::LEFT),
+        "right" => Some(Buttons::RIGHT),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_bindings() {
+        let keymap = KeyMap::from_json(
+            br#"{ "controller1": { "A": "K", "start": "Return" }, "controller2": { "Up": "W" } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            keymap.get(&VirtualKeyCode::K),
+            Some((ControllerPort::One, Buttons::A))
+        );
+        assert_eq!(
+            keymap.get(&VirtualKeyCode::Return),
+            Some((ControllerPort::One, Buttons::START))
+        );
+        assert_eq!(
+            keymap.get(&VirtualKeyCode::W),
+            Some((ControllerPort::Two, Buttons::UP))
+        );
+        // Unlisted buttons are left unbound
+        assert_eq!(keymap.get(&VirtualKeyCode::X), None);
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let result =
+            KeyMap::from_json(br#"{ "controller1": { "A": "K" }, "controller2": { "B": "K" } }"#);
+        assert!(matches!(
+            result,
+            Err(KeyMapError::DuplicateKey(VirtualKeyCode::K))
+        ));
+    }
+
+    #[test]
+    fn unknown_key() {
+        let result = KeyMap::from_json(br#"{ "controller1": { "A": "NotAKey" } }"#);
+        assert!(matches!(result, Err(KeyMapError::Parse(_))));
+
+        let result = KeyMap::from_json(br#"{ "controller1": { "Turbo": "K" } }"#);
+        assert!(matches!(result, Err(KeyMapError::UnknownButton(name)) if name == "Turbo"));
+    }
+
+    #[test]
+    fn hotkey_conflict() {
+        for &key in &RESERVED_KEYS {
+            let json = format!(r#"{{ "controller2": {{ "B": "{:?}" }} }}"#, key);
+            let result = KeyMap::from_json(json.as_bytes());
+            assert!(matches!(result, Err(KeyMapError::ReservedKey(k)) if k == key));
+        }
+    }
+}
@@ -1,16 +1,20 @@
 use futures::executor::block_on;
-use nestadia::Emulator;
+use nestadia::{ApuChannel, Emulator, Movie, PpuFrame};
 use wgpu::util::DeviceExt;
 
 use std::{
-    convert::TryFrom,
+    collections::VecDeque,
     fs::OpenOptions,
     io::{Read, Write},
     path::Path,
-    time::{Duration, Instant},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use rodio::{buffer::SamplesBuffer, OutputStream, OutputStreamHandle, Sink};
+use rodio::{
+    cpal::traits::{DeviceTrait, HostTrait},
+    OutputStream, OutputStreamHandle, Sink, Source,
+};
 
 use winit::{
     event::*,
@@ -22,8 +26,6 @@ use winit::{
 #[cfg(target_os = "windows")]
 use winit::platform::windows::WindowBuilderExtWindows;
 
-use bitflags::bitflags;
-
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -35,48 +37,38 @@ struct Opt {
     #[structopt(parse(from_os_str))]
     rom: Option<PathBuf>,
 
+    /// Start paused, to step through the first frames with N
     #[structopt(short = "p", long)]
     start_paused: bool,
-}
 
-mod debugger;
+    /// IPS or BPS patch to apply to the ROM, like a ROM hack or a translation
+    #[structopt(long, parse(from_os_str))]
+    patch: Option<PathBuf>,
 
-bitflags! {
-    #[derive(Default)]
-    struct ControllerState: u8 {
-        const A = 0x80;
-        const B = 0x40;
-        const SELECT = 0x20;
-        const START = 0x10;
-        const UP = 0x08;
-        const DOWN = 0x04;
-        const LEFT = 0x02;
-        const RIGHT = 0x01;
-    }
-}
+    /// Record the controller inputs to a movie file
+    #[structopt(long, parse(from_os_str), conflicts_with = "replay")]
+    record: Option<PathBuf>,
 
-// This maps the keyboard input to a controller input
-impl TryFrom<&VirtualKeyCode> for ControllerState {
-    type Error = ();
-
-    fn try_from(keycode: &VirtualKeyCode) -> Result<Self, ()> {
-        match keycode {
-            VirtualKeyCode::X => Ok(ControllerState::A),
-            VirtualKeyCode::Z => Ok(ControllerState::B),
-            VirtualKeyCode::S => Ok(ControllerState::START),
-            VirtualKeyCode::A => Ok(ControllerState::SELECT),
-            VirtualKeyCode::Down => Ok(ControllerState::DOWN),
-            VirtualKeyCode::Left => Ok(ControllerState::LEFT),
-            VirtualKeyCode::Right => Ok(ControllerState::RIGHT),
-            VirtualKeyCode::Up => Ok(ControllerState::UP),
-            _ => Err(()),
-        }
-    }
+    /// Play back a movie file recorded with --record
+    #[structopt(long, parse(from_os_str))]
+    replay: Option<PathBuf>,
+
+    /// JSON file that binds the buttons of both controllers to keys
+    #[structopt(long, parse(from_os_str))]
+    keymap: Option<PathBuf>,
 }
 
+mod debugger;
+mod keymap;
+
+use keymap::KeyMap;
+
 // Target for NTSC is ~60 FPS
 const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
+// Frames emulated per rendered frame while fast-forwarding
+const FAST_FORWARD_SPEED: u32 = 4;
+
 // Desired sample rate is 44100 Hz
 const SAMPLE_RATE: f32 = 44100.0;
 
@@ -112,41 +104,121 @@ impl Vertex {
     }
 }
 
+// Samples are dropped past this latency, if the emulator runs faster than the audio device
+const MAX_AUDIO_LATENCY: Duration = Duration::from_millis(100);
+
+/// Samples produced by the emulator, waiting to be played by the audio thread
+type SampleQueue = Arc<Mutex<VecDeque<i16>>>;
+
+/// Endless source that plays the queued samples.
+/// When the emulator falls behind, the last sample is repeated instead of popping back to silence.
+struct EmulatorSource {
+    queue: SampleQueue,
+    last_sample: i16,
+    sample_rate: u32,
+}
+
+impl Iterator for EmulatorSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if let Some(sample) = self.queue.lock().unwrap().pop_front() {
+            self.last_sample = sample;
+        }
+        Some(self.last_sample)
+    }
+}
+
+impl Source for EmulatorSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
 struct AudioHandler {
     _stream: OutputStream,
     _stream_handle: OutputStreamHandle,
-    sink: Sink,
+    _sink: Sink,
+    queue: SampleQueue,
+    sample_rate: u32,
 }
 
 impl AudioHandler {
     pub fn try_new() -> Option<Self> {
+        // Produce the samples at the rate of the device, so they don't need to be resampled again
+        let sample_rate = rodio::cpal::default_host()
+            .default_output_device()
+            .and_then(|device| device.default_output_config().ok())
+            .map_or(SAMPLE_RATE as u32, |config| config.sample_rate().0);
+
         match OutputStream::try_default() {
             Ok((stream, stream_handle)) => {
                 let sink = Sink::try_new(&stream_handle).unwrap();
+                let queue = SampleQueue::default();
+                sink.append(EmulatorSource {
+                    queue: queue.clone(),
+                    last_sample: 0,
+                    sample_rate,
+                });
+
                 Some(Self {
                     _stream: stream,
                     _stream_handle: stream_handle,
-                    sink,
+                    _sink: sink,
+                    queue,
+                    sample_rate,
                 })
             }
             Err(_) => None,
         }
     }
 
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
     pub fn queue_samples(&mut self, samples: Vec<i16>) {
-        let buffer = SamplesBuffer::new(1, SAMPLE_RATE as u32, samples);
-        self.sink.append(buffer);
+        let max_samples = (self.sample_rate as f32 * MAX_AUDIO_LATENCY.as_secs_f32()) as usize;
+
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        if queue.len() > max_samples {
+            let excess = queue.len() - max_samples;
+            queue.drain(..excess);
+        }
     }
 }
 
 struct State {
     emulator: Emulator,
-    controller1: ControllerState,
+    keymap: KeyMap,
     last_frame_time: Instant,
 
+    // Waiting for commands in the debugger prompt
+    debugging: bool,
     paused: bool,
+    // Emulate a single frame on the next update while paused
+    step_frame: bool,
+    fast_forward: bool,
+    // Last frame shown, kept for the screenshots
+    last_frame: Box<PpuFrame>,
     breakpoints: Vec<u16>,
 
+    // Last frame of the movie being played back
+    replay_end: Option<u64>,
+
     surface: wgpu::Surface,
     device: wgpu::Device,
     queue: wgpu::Queue,
@@ -165,7 +237,12 @@ struct State {
 
 impl State {
     /// Create a new state and initialize the rendering pipeline.
-    async fn new(window: &Window, audio_handler: Option<AudioHandler>, emulator: Emulator) -> Self {
+    async fn new(
+        window: &Window,
+        audio_handler: Option<AudioHandler>,
+        emulator: Emulator,
+        keymap: KeyMap,
+    ) -> Self {
         let size = window.inner_size();
 
         // Used prefered graphic API
@@ -379,12 +456,18 @@ impl State {
 
         Self {
             emulator,
-            controller1: Default::default(),
+            keymap,
             last_frame_time: Instant::now(),
 
+            debugging: false,
             paused: false,
+            step_frame: false,
+            fast_forward: false,
+            last_frame: Box::new([0u8; NUM_PIXELS]),
             breakpoints: Vec::new(),
 
+            replay_end: None,
+
             surface,
             device,
             queue,
@@ -412,6 +495,11 @@ impl State {
 
     /// This is where we handle controller inputs
     fn input(&mut self, event: &WindowEvent) -> bool {
+        // The controller inputs come from the movie during a replay
+        if self.replay_end.is_some() {
+            return false;
+        }
+
         match event {
             WindowEvent::KeyboardInput { input, .. } => match input {
                 // Handle controller inputs
@@ -420,10 +508,8 @@ impl State {
                     virtual_keycode: Some(key_code),
                     ..
                 } => {
-                    if let Ok(f) = ControllerState::try_from(key_code) {
-                        self.controller1.insert(f);
-
-                        self.emulator.set_controller1(self.controller1.bits());
+                    if let Some((port, button)) = self.keymap.get(key_code) {
+                        self.emulator.set_button(port, button, true);
                         true
                     } else {
                         false
@@ -435,10 +521,8 @@ impl State {
                     virtual_keycode: Some(key_code),
                     ..
                 } => {
-                    if let Ok(f) = ControllerState::try_from(key_code) {
-                        self.controller1.remove(f);
-
-                        self.emulator.set_controller1(self.controller1.bits());
+                    if let Some((port, button)) = self.keymap.get(key_code) {
+                        self.emulator.set_button(port, button, false);
                         true
                     } else {
                         false
@@ -452,75 +536,45 @@ impl State {
 
     /// Update the game state
     fn update(&mut self) {
-        let mask_reg = self.emulator.get_ppu_mask_reg();
-
-        if self.paused {
-            let frame = self.debugger_prompt();
-
-            if let Some(frame) = frame {
-                let mut current_frame = [0u8; NUM_PIXELS * 4];
-                nestadia::frame_to_rgba(mask_reg, &frame, &mut current_frame);
-
-                // Update texture
-                let texture_size = wgpu::Extent3d {
-                    width: 256,
-                    height: 240,
-                    depth_or_array_layers: 1,
-                };
-
-                self.queue.write_texture(
-                    wgpu::ImageCopyTexture {
-                        texture: &self.screen_texture,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::ZERO,
-                    },
-                    &current_frame,
-                    wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: std::num::NonZeroU32::new(4 * 256),
-                        rows_per_image: std::num::NonZeroU32::new(240),
-                    },
-                    texture_size,
-                );
+        if self.debugging {
+            if let Some(frame) = self.debugger_prompt() {
+                self.show_frame(&frame);
             }
         } else {
-            // Clock until a frame is ready
-            let frame = loop {
-                if self.breakpoints.contains(&self.emulator.cpu().pc) {
-                    println!("Reached breakpoint at {:#06x}", self.emulator.cpu().pc);
-                    self.paused = true;
-                    break None;
-                }
-                if let Some(frame) = self.emulator.clock() {
-                    break Some(frame);
-                }
+            // Emulate several frames per rendered frame while fast-forwarding, but only show the last one.
+            // While paused, a frame is only emulated when advancing to the next one.
+            let frames = if self.paused {
+                std::mem::take(&mut self.step_frame) as u32
+            } else if self.fast_forward {
+                FAST_FORWARD_SPEED
+            } else {
+                1
             };
 
-            if let Some(frame) = frame {
-                let mut current_frame = [0u8; NUM_PIXELS * 4];
-                nestadia::frame_to_rgba(mask_reg, &frame, &mut current_frame);
-
-                // Update texture
-                let texture_size = wgpu::Extent3d {
-                    width: 256,
-                    height: 240,
-                    depth_or_array_layers: 1,
+            let mut frame = None;
+            for _ in 0..frames {
+                // The audio of the skipped frames is dropped, so it still plays at the normal pitch
+                self.emulator.take_audio_samples();
+
+                // Clock until a frame is ready
+                frame = loop {
+                    if self.breakpoints.contains(&self.emulator.cpu().pc) {
+                        println!("Reached breakpoint at {:#06x}", self.emulator.cpu().pc);
+                        self.debugging = true;
+                        break None;
+                    }
+                    if let Some(frame) = self.emulator.clock() {
+                        break Some(*frame);
+                    }
                 };
 
-                self.queue.write_texture(
-                    wgpu::ImageCopyTexture {
-                        texture: &self.screen_texture,
-                        mip_level: 0,
-                        origin: wgpu::Origin3d::ZERO,
-                    },
-                    &current_frame,
-                    wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: std::num::NonZeroU32::new(4 * 256),
-                        rows_per_image: std::num::NonZeroU32::new(240),
-                    },
-                    texture_size,
-                );
+                if frame.is_none() {
+                    break;
+                }
+            }
+
+            if let Some(frame) = frame {
+                self.show_frame(&frame);
             }
         }
 
@@ -529,6 +583,39 @@ impl State {
         }
     }
 
+    /// Upload a frame to the screen texture
+    fn show_frame(&mut self, frame: &PpuFrame) {
+        *self.last_frame = *frame;
+
+        let mask_reg = self.emulator.get_ppu_mask_reg();
+        let palette = *self.emulator.palette();
+
+        let mut current_frame = [0u8; NUM_PIXELS * 4];
+        nestadia::frame_to_rgba_with_palette(&palette, mask_reg, frame, &mut current_frame);
+
+        // Update texture
+        let texture_size = wgpu::Extent3d {
+            width: 256,
+            height: 240,
+            depth_or_array_layers: 1,
+        };
+
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.screen_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &current_frame,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * 256),
+                rows_per_image: std::num::NonZeroU32::new(240),
+            },
+            texture_size,
+        );
+    }
+
     /// Render the screen
     fn render(&mut self) -> Result<(), wgpu::SwapChainError> {
         let frame = self.swap_chain.get_current_frame()?.output;
@@ -571,6 +658,10 @@ impl State {
     }
 
     fn save_data(&self, save_path: &Path) {
+        if !self.emulator.is_save_dirty() {
+            return;
+        }
+
         if let Some(save_data) = self.emulator.get_save_data() {
             if let Ok(mut f) = OpenOptions::new()
                 .read(true)
@@ -583,9 +674,88 @@ impl State {
         }
     }
 
-    fn pause(&mut self) {
-        self.paused = true;
-        println!("Emulator is paused");
+    fn save_movie(&mut self, movie_path: &Path) {
+        if let Some(movie) = self.emulator.stop_recording() {
+            if let Err(e) = std::fs::write(movie_path, movie.to_bytes()) {
+                eprintln!("Could not write the movie file: {}", e);
+            }
+        }
+    }
+
+    /// Returns true when the replay just ended
+    fn check_replay_end(&mut self) -> bool {
+        match self.replay_end {
+            Some(end) if self.emulator.frame_count() > end => {
+                self.replay_end = None;
+                println!("Movie playback ended");
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Save the last frame as a PNG in the working directory.
+    /// This is the frame of the emulator, so it's always 256x240 whatever the size of the window.
+    fn screenshot(&mut self) {
+        let mask_reg = self.emulator.get_ppu_mask_reg();
+        let mut rgba = [0u8; NUM_PIXELS * 4];
+        nestadia::frame_to_rgba_with_palette(
+            self.emulator.palette(),
+            mask_reg,
+            &self.last_frame,
+            &mut rgba,
+        );
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis());
+        let path = format!("nestadia-{}.png", timestamp);
+
+        match image::save_buffer(&path, &rgba, 256, 240, image::ColorType::Rgba8) {
+            Ok(()) => println!("Saved screenshot to {}", path),
+            Err(e) => log::error!("Could not save the screenshot to {}: {}", path, e),
+        }
+    }
+
+    fn break_into_debugger(&mut self) {
+        self.debugging = true;
+        println!("Emulator is paused in the debugger");
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_frame = false;
+        if self.paused {
+            println!("Emulator is paused, press N to advance one frame");
+        } else {
+            println!("Emulator is resumed");
+        }
+    }
+
+    fn advance_frame(&mut self) {
+        if self.paused {
+            self.step_frame = true;
+        }
+    }
+
+    // Mute or unmute an audio channel with the number keys
+    fn toggle_channel(&mut self, key_code: VirtualKeyCode) {
+        let channel = match key_code {
+            VirtualKeyCode::Key1 => ApuChannel::Pulse1,
+            VirtualKeyCode::Key2 => ApuChannel::Pulse2,
+            VirtualKeyCode::Key3 => ApuChannel::Triangle,
+            VirtualKeyCode::Key4 => ApuChannel::Noise,
+            VirtualKeyCode::Key5 => ApuChannel::Dmc,
+            _ => return,
+        };
+
+        let enabled = !self.emulator.is_channel_enabled(channel);
+        self.emulator.set_channel_enabled(channel, enabled);
+        println!(
+            "{:?} channel is {}",
+            channel,
+            if enabled { "unmuted" } else { "muted" }
+        );
     }
 }
 
@@ -599,6 +769,18 @@ fn main() {
         .start()
         .unwrap();
 
+    // Load the key bindings first, so a bad file is reported before opening the window
+    let keymap = match &opt.keymap {
+        Some(keymap_path) => match KeyMap::load(keymap_path) {
+            Ok(keymap) => keymap,
+            Err(e) => {
+                eprintln!("Could not load the key map: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => KeyMap::default(),
+    };
+
     // Find ROM path
     let path = if let Some(p) = opt.rom {
         p
@@ -610,7 +792,8 @@ fn main() {
             .expect("No rom passed!")
     };
 
-    let mut save_path = path.clone();
+    // A patched game gets its own save file, since the hack may not use the same save layout
+    let mut save_path = opt.patch.clone().unwrap_or_else(|| path.clone());
     save_path.set_extension("sav");
 
     // Create the audio device
@@ -634,6 +817,10 @@ fn main() {
 
     // Read the ROM
     let rom = std::fs::read(path).expect("Could not read the ROM file");
+    let patch = opt
+        .patch
+        .as_ref()
+        .map(|patch_path| std::fs::read(patch_path).expect("Could not read the patch file"));
 
     // Read the save file
     let mut save_buf = Vec::new();
@@ -645,19 +832,52 @@ fn main() {
     };
 
     // Create the emulator
-    let mut emulator = Emulator::new(&rom, save_file).expect("Rom parsing failed");
-    emulator.set_sample_rate(SAMPLE_RATE);
+    let emulator = match &patch {
+        Some(patch) => Emulator::new_with_patch(&rom, patch, save_file),
+        None => Emulator::new(&rom, save_file),
+    };
+    let mut emulator = match emulator {
+        Ok(emulator) => emulator,
+        Err(e) => {
+            eprintln!("Could not load the ROM: {}", e);
+            std::process::exit(1);
+        }
+    };
+    emulator.set_sample_rate(audio_handler.as_ref().map_or(SAMPLE_RATE, |audio_handler| {
+        audio_handler.sample_rate() as f32
+    }));
+
+    // Movies start from the save state they were recorded from, which includes the save data of the game
+    let mut replay_end = None;
+    if let Some(replay_path) = &opt.replay {
+        let movie = std::fs::read(replay_path).expect("Could not read the movie file");
+        let movie = Movie::from_bytes(&movie).expect("Movie parsing failed");
+        emulator
+            .play_movie(&movie)
+            .unwrap_or_else(|e| panic!("The movie can't be played: {}", e));
+
+        replay_end = Some(emulator.frame_count() + movie.last_frame());
+        window.set_title("Nestadia [PLAY]");
+    } else if opt.record.is_some() {
+        emulator.start_recording();
+        window.set_title("Nestadia [REC]");
+    }
+    let record_path = opt.record;
 
     // Wait until WGPU is ready
-    let mut state = block_on(State::new(&window, audio_handler, emulator));
+    let mut state = block_on(State::new(&window, audio_handler, emulator, keymap));
+    state.replay_end = replay_end;
     if opt.start_paused {
-        state.pause();
+        state.toggle_pause();
     }
 
     // Handle window events
     event_loop.run(move |event, _, control_flow| match event {
         Event::RedrawRequested(_) => {
             state.update();
+            if state.check_replay_end() {
+                window.set_title("Nestadia");
+            }
             match state.render() {
                 Ok(_) => {}
                 Err(wgpu::SwapChainError::Lost) => state.resize(state.size),
@@ -685,6 +905,9 @@ fn main() {
                     // Exit if X button is clicked
                     WindowEvent::CloseRequested => {
                         state.save_data(&save_path);
+                        if let Some(record_path) = &record_path {
+                            state.save_movie(record_path);
+                        }
 
                         *control_flow = ControlFlow::Exit
                     }
@@ -706,6 +929,9 @@ fn main() {
                         ..
                     } => {
                         state.save_data(&save_path);
+                        if let Some(record_path) = &record_path {
+                            state.save_movie(record_path);
+                        }
 
                         *control_flow = ControlFlow::Exit
                     }
@@ -719,7 +945,68 @@ fn main() {
                             },
                         ..
                     } => {
-                        state.pause();
+                        state.toggle_pause();
+                    }
+
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::N),
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.advance_frame();
+                    }
+
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F12),
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.screenshot();
+                    }
+
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::D),
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.break_into_debugger();
+                    }
+
+                    // Fast-forward while Space is held
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: key_state,
+                                virtual_keycode: Some(VirtualKeyCode::Space),
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.fast_forward = *key_state == ElementState::Pressed;
+                    }
+
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(key_code),
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.toggle_channel(*key_code);
                     }
                     _ => {}
                 }
@@ -2,6 +2,7 @@ use bitfield::bitfield;
 
 bitfield! {
     #[derive(Clone, Copy, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct EnvelopeRegister(u8);
     impl Debug;
 
@@ -12,6 +13,7 @@ bitfield! {
 }
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Envelope {
     register: EnvelopeRegister,
     start_flag: bool,
@@ -60,6 +62,7 @@ impl Envelope {
 }
 
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SequenceMode {
     Step4,
     Step5,
@@ -90,6 +93,11 @@ impl SequenceMode {
         }
     }
 
+    /// The 4-step sequence raises the frame interrupt on its last step
+    pub fn is_frame_irq(&self, cycle: u16) -> bool {
+        *self == Self::Step4 && (29828..=29829).contains(&cycle)
+    }
+
     pub fn get_max(&self) -> u16 {
         match *self {
             Self::Step4 => 29830,
@@ -99,6 +107,7 @@ impl SequenceMode {
 }
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timer {
     timer_reload: u16,
     counter: u16,
@@ -145,6 +154,7 @@ impl Timer {
 }
 
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LengthCounter {
     counter: u8,
     halt: bool,
@@ -1,9 +1,19 @@
 use alloc::vec::Vec;
 use libm::{ceilf, floorf};
 
+use super::filter::FilterChain;
+
 const MAX_SAMPLES: usize = 1024;
 const CPU_FREQUENCY: f32 = 1789773.0;
 
+fn cpu_cycles_per_samples(sample_rate: f32) -> [u16; 2] {
+    [
+        floorf(CPU_FREQUENCY / sample_rate) as u16,
+        ceilf(CPU_FREQUENCY / sample_rate) as u16,
+    ]
+}
+
+#[derive(Clone)]
 pub struct Dac {
     sample_rate: f32,
     cpu_cycles_per_samples: [u16; 2],
@@ -11,7 +21,8 @@ pub struct Dac {
 
     sample_sum: f32,
     sample_count: u16,
-    samples: Vec<i16>,
+    filters: FilterChain,
+    samples: Vec<f32>,
 }
 
 impl Default for Dac {
@@ -24,14 +35,12 @@ impl Dac {
     pub fn new(sample_rate: f32) -> Self {
         Self {
             sample_rate,
-            cpu_cycles_per_samples: [
-                floorf(CPU_FREQUENCY / sample_rate) as u16,
-                ceilf(CPU_FREQUENCY / sample_rate) as u16,
-            ],
+            cpu_cycles_per_samples: cpu_cycles_per_samples(sample_rate),
             index: 0,
 
             sample_sum: 0.0,
             sample_count: 0,
+            filters: FilterChain::new(sample_rate),
             samples: Vec::with_capacity(MAX_SAMPLES),
         }
     }
@@ -40,7 +49,29 @@ impl Dac {
         self.sample_rate
     }
 
+    /// Change the output rate while keeping the pending samples, so the playback doesn't pop.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.cpu_cycles_per_samples = cpu_cycles_per_samples(sample_rate);
+        self.filters.set_sample_rate(sample_rate);
+    }
+
+    pub fn is_filtering_enabled(&self) -> bool {
+        self.filters.is_enabled()
+    }
+
+    pub fn set_filtering_enabled(&mut self, enabled: bool) {
+        self.filters.set_enabled(enabled);
+    }
+
     pub fn take_samples(&mut self) -> Vec<i16> {
+        self.take_samples_f32()
+            .into_iter()
+            .map(|sample| (sample * i16::MAX as f32) as i16) // Remap to i16
+            .collect()
+    }
+
+    pub fn take_samples_f32(&mut self) -> Vec<f32> {
         let mut samples = Vec::with_capacity(MAX_SAMPLES);
         core::mem::swap(&mut self.samples, &mut samples);
         samples
@@ -50,21 +81,22 @@ impl Dac {
         self.sample_sum += sample;
         self.sample_count += 1;
 
-        if self.sample_count == self.cpu_cycles_per_samples[self.index] {
+        // The rate can be lowered while a sample is being accumulated, so this can overshoot
+        if self.sample_count >= self.cpu_cycles_per_samples[self.index] {
             self.index = (self.index + 1) % 2;
 
             let sample = self.downsample();
+            let sample = self.filters.process(sample);
             self.samples.push(sample);
         }
     }
 
-    fn downsample(&mut self) -> i16 {
+    fn downsample(&mut self) -> f32 {
         let average = self.sample_sum / self.sample_count as f32;
 
         self.sample_sum = 0.0;
         self.sample_count = 0;
 
-        // Remap to i16
-        (average * i16::MAX as f32) as i16
+        average
     }
 }
@@ -0,0 +1,168 @@
+const RATE_TABLE: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DmcChannel {
+    irq_enabled: bool,
+    loop_flag: bool,
+    irq_set: bool,
+
+    // Timer, in CPU cycles
+    period: u16,
+    counter: u16,
+
+    // Memory reader
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    // Output unit
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    output_level: u8,
+}
+
+impl Default for DmcChannel {
+    fn default() -> Self {
+        Self {
+            irq_enabled: false,
+            loop_flag: false,
+            irq_set: false,
+
+            period: RATE_TABLE[0],
+            counter: RATE_TABLE[0],
+
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            output_level: 0,
+        }
+    }
+}
+
+impl DmcChannel {
+    pub fn write(&mut self, addr: u16, data: u8) {
+        match addr & 0b11 {
+            0 => {
+                self.irq_enabled = (data & 0x80) != 0;
+                self.loop_flag = (data & 0x40) != 0;
+                self.period = RATE_TABLE[(data & 0x0F) as usize];
+
+                if !self.irq_enabled {
+                    self.irq_set = false;
+                }
+            }
+            1 => {
+                self.output_level = data & 0x7F;
+            }
+            2 => {
+                self.sample_address = 0xC000 | ((data as u16) << 6);
+            }
+            3 => {
+                self.sample_length = ((data as u16) << 4) + 1;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if self.counter > 1 {
+            self.counter -= 1;
+            return;
+        }
+        self.counter = self.period;
+
+        if !self.silence {
+            if (self.shift_register & 0x01) != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+
+        self.shift_register >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            // Start a new output cycle with the next sample byte, if any
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.shift_register = sample;
+                    self.silence = false;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    /// Address of the next sample byte to fetch, if the sample buffer needs to be refilled
+    pub fn sample_address(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    /// Fill the sample buffer with the byte read at `sample_address()`
+    pub fn load_sample(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+
+        // The address wraps to $8000 instead of leaving the cartridge space
+        self.current_address = if self.current_address == 0xFFFF {
+            0x8000
+        } else {
+            self.current_address + 1
+        };
+
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.loop_flag {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_set = true;
+            }
+        }
+    }
+
+    pub fn set_enable(&mut self, enable: bool) {
+        self.irq_set = false;
+
+        if !enable {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq_set(&self) -> bool {
+        self.irq_set
+    }
+
+    pub fn sample(&self) -> u8 {
+        self.output_level
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+}
@@ -0,0 +1,101 @@
+// First-order filters of the console audio path
+// https://wiki.nesdev.com/w/index.php/APU_Mixer
+
+use core::f32::consts::PI;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FilterKind {
+    HighPass,
+    LowPass,
+}
+
+#[derive(Clone)]
+struct Filter {
+    kind: FilterKind,
+    cutoff: f32,
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl Filter {
+    fn new(kind: FilterKind, cutoff: f32, sample_rate: f32) -> Self {
+        let mut filter = Self {
+            kind,
+            cutoff,
+            alpha: 0.0,
+            previous_input: 0.0,
+            previous_output: 0.0,
+        };
+        filter.set_sample_rate(sample_rate);
+        filter
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        let rc = 1.0 / (2.0 * PI * self.cutoff);
+        let dt = 1.0 / sample_rate;
+        self.alpha = match self.kind {
+            FilterKind::HighPass => rc / (rc + dt),
+            FilterKind::LowPass => dt / (rc + dt),
+        };
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = match self.kind {
+            FilterKind::HighPass => {
+                self.alpha * (self.previous_output + input - self.previous_input)
+            }
+            FilterKind::LowPass => {
+                self.previous_output + self.alpha * (input - self.previous_output)
+            }
+        };
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+/// The two high-pass and the low-pass filters the NES applies to its output, at the output sample rate
+#[derive(Clone)]
+pub struct FilterChain {
+    filters: [Filter; 3],
+    enabled: bool,
+}
+
+impl FilterChain {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            filters: [
+                Filter::new(FilterKind::HighPass, 90.0, sample_rate),
+                Filter::new(FilterKind::HighPass, 440.0, sample_rate),
+                Filter::new(FilterKind::LowPass, 14000.0, sample_rate),
+            ],
+            enabled: true,
+        }
+    }
+
+    /// Only the coefficients change, so the output stays continuous
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        for filter in self.filters.iter_mut() {
+            filter.set_sample_rate(sample_rate);
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        if !self.enabled {
+            return sample;
+        }
+
+        self.filters
+            .iter_mut()
+            .fold(sample, |sample, filter| filter.process(sample))
+    }
+}
@@ -3,12 +3,15 @@ use bitflags::bitflags;
 
 mod common;
 mod dac;
+mod dmc;
+mod filter;
 mod noise;
 mod pulse;
 mod triangle;
 
 use self::common::SequenceMode;
 use self::dac::Dac;
+use self::dmc::DmcChannel;
 use self::noise::NoiseChannel;
 use self::pulse::PulseChannel;
 use self::triangle::TriangleChannel;
@@ -40,15 +43,63 @@ bitflags! {
         const TRIANGLE_ENABLE = 0b00000100;
         const NOISE_ENABLE = 0b00001000;
         const DMC_ENABLE = 0b00010000;
+        const FRAME_INTERRUPT = 0b01000000;
+        const DMC_INTERRUPT = 0b10000000;
     }
 }
 
+/// Sound channels of the APU
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApuChannel {
+    Pulse1,
+    Pulse2,
+    Triangle,
+    Noise,
+    Dmc,
+}
+
+/// How the channels are mixed together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MixingModel {
+    /// The nonlinear response of the 2A03 DAC, from the lookup tables
+    Nonlinear,
+    /// Linear approximation of the DAC, which is cheaper but doesn't sound quite like the console
+    Linear,
+}
+
+impl Default for MixingModel {
+    fn default() -> Self {
+        Self::Nonlinear
+    }
+}
+
+/// Settings of the frontend, which are kept across resets and loaded states
+#[derive(Clone, Copy)]
+struct ApuSettings {
+    muted_channels: [bool; 5], // Indexed by `ApuChannel`
+    silence_ultrasonic_triangle: bool,
+    mixing_model: MixingModel,
+}
+
+impl Default for ApuSettings {
+    fn default() -> Self {
+        Self {
+            muted_channels: [false; 5],
+            silence_ultrasonic_triangle: true,
+            mixing_model: MixingModel::Nonlinear,
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Apu {
     // Channels
     pulse_channel_1: PulseChannel,
     pulse_channel_2: PulseChannel,
     triangle_channel: TriangleChannel,
     noise_channel: NoiseChannel,
+    dmc_channel: DmcChannel,
 
     // Frame counter
     disable_interrupts: bool,
@@ -56,11 +107,13 @@ pub struct Apu {
     frame_counter: u16,
 
     // Sampling
+    #[cfg_attr(feature = "serde", serde(skip))]
     dac: Dac,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    settings: ApuSettings,
 
     // IRQ
     frame_irq_set: bool,
-    dmc_irq_set: bool,
 }
 
 impl Default for Apu {
@@ -76,36 +129,110 @@ impl Apu {
             pulse_channel_2: PulseChannel::new(false),
             triangle_channel: Default::default(),
             noise_channel: Default::default(),
+            dmc_channel: Default::default(),
 
             disable_interrupts: false,
             sequence_mode: Default::default(),
             frame_counter: 0,
 
             dac: Default::default(),
+            settings: Default::default(),
 
             frame_irq_set: false,
-            dmc_irq_set: false,
         }
     }
 
-    pub fn reset(&mut self) {
+    /// Replace the state with a snapshot, keeping the sample rate and the pending samples of the frontend
+    #[cfg(feature = "serde")]
+    pub(crate) fn load_state(&mut self, mut state: Apu) {
+        state.dac = core::mem::take(&mut self.dac);
+        state.settings = self.settings;
+        *self = state;
+    }
+
+    /// Power-up state of the 2A03: `$4015` and `$4017` cleared (all channels silenced,
+    /// 4-step sequence with IRQ enabled), length counters cleared and noise LFSR seeded to 1.
+    pub fn power_on(&mut self) {
         let sample_rate = self.dac.get_sample_rate();
+        let filtering_enabled = self.dac.is_filtering_enabled();
+        let settings = self.settings;
         *self = Default::default();
-        self.set_sample_rate(sample_rate);
+        self.dac = Dac::new(sample_rate);
+        self.dac.set_filtering_enabled(filtering_enabled);
+        self.settings = settings;
     }
 
+    /// On reset, the 2A03 silences the channels as if `$4015` was cleared and restarts the frame counter.
+    /// The `$4017` mode and the rest of the channels state are kept.
+    pub fn reset(&mut self) {
+        self.pulse_channel_1.set_length_counter_enable(false);
+        self.pulse_channel_2.set_length_counter_enable(false);
+        self.triangle_channel.set_length_counter_enable(false);
+        self.noise_channel.set_length_counter_enable(false);
+        self.dmc_channel.set_enable(false);
+
+        self.frame_counter = 0;
+        self.frame_irq_set = false;
+    }
+
+    /// Mute or unmute a channel. A muted channel keeps running, so it's heard again right where it would be
+    /// when unmuted.
+    pub fn set_channel_enabled(&mut self, channel: ApuChannel, enabled: bool) {
+        self.settings.muted_channels[channel as usize] = !enabled;
+    }
+
+    pub fn is_channel_enabled(&self, channel: ApuChannel) -> bool {
+        !self.settings.muted_channels[channel as usize]
+    }
+
+    /// Mute every channel but `channel`
+    pub fn solo_channel(&mut self, channel: ApuChannel) {
+        self.settings.muted_channels = [true; 5];
+        self.settings.muted_channels[channel as usize] = false;
+    }
+
+    /// Whether the triangle channel stops at periods below 2 instead of playing an ultrasonic tone.
+    /// Some games use it to silence the channel, which pops when the tone is played. Defaults to `true`.
+    pub fn set_ultrasonic_triangle_silenced(&mut self, silenced: bool) {
+        self.settings.silence_ultrasonic_triangle = silenced;
+    }
+
+    /// Defaults to `MixingModel::Nonlinear`
+    pub fn set_mixing_model(&mut self, model: MixingModel) {
+        self.settings.mixing_model = model;
+    }
+
+    /// Only the resampling is affected, the channels keep running as-is.
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
-        self.dac = Dac::new(sample_rate);
+        self.dac.set_sample_rate(sample_rate);
+    }
+
+    /// Whether the output goes through the high-pass and low-pass filters of the console. Defaults to `true`.
+    pub fn set_filtering_enabled(&mut self, enabled: bool) {
+        self.dac.set_filtering_enabled(enabled);
+    }
+
+    /// State of the IRQ line, which stays asserted until the frame or DMC interrupt is acknowledged
+    pub fn irq_state(&self) -> bool {
+        self.frame_irq_set || self.dmc_channel.irq_set()
     }
 
     pub fn take_irq_set_state(&mut self) -> bool {
-        let state = self.frame_irq_set || self.dmc_irq_set;
+        let state = self.frame_irq_set || self.dmc_channel.irq_set();
         self.frame_irq_set = false;
-        self.dmc_irq_set = false;
         state
     }
 
-    #[cfg(feature = "audio")]
+    /// Address the DMC needs to read its next sample byte from, if any.
+    /// The byte must then be given back through `load_dmc_sample`.
+    pub fn dmc_sample_address(&self) -> Option<u16> {
+        self.dmc_channel.sample_address()
+    }
+
+    pub fn load_dmc_sample(&mut self, data: u8) {
+        self.dmc_channel.load_sample(data);
+    }
+
     pub fn write(&mut self, addr: u16, data: u8) {
         match addr {
             0x4000..=0x4003 => {
@@ -126,6 +253,7 @@ impl Apu {
             }
             0x4010..=0x4013 => {
                 // dmc
+                self.dmc_channel.write(addr & 0b11, data);
             }
             0x4015 => {
                 // channel enable and length counter status
@@ -137,10 +265,15 @@ impl Apu {
                     .set_length_counter_enable((data & ChannelEnable::TRIANGLE_ENABLE.bits()) != 0);
                 self.noise_channel
                     .set_length_counter_enable((data & ChannelEnable::NOISE_ENABLE.bits()) != 0);
+                self.dmc_channel
+                    .set_enable((data & ChannelEnable::DMC_ENABLE.bits()) != 0);
             }
             0x4017 => {
                 // frame counter
                 self.disable_interrupts = (data & 0x40) != 0;
+                if self.disable_interrupts {
+                    self.frame_irq_set = false;
+                }
                 self.sequence_mode = if (data & 0x80) != 0 {
                     SequenceMode::Step5
                 } else {
@@ -162,12 +295,36 @@ impl Apu {
         }
     }
 
-    #[cfg(not(feature = "audio"))]
-    pub fn write(&mut self, _addr: u16, _data: u8) {
-        // DO NOTHING
+    /// `$4015`: which channels are still playing, and the pending interrupts.
+    /// Reading it acknowledges the frame interrupt.
+    pub fn read_status(&mut self) -> u8 {
+        let mut enable = ChannelEnable::empty();
+        enable.set(
+            ChannelEnable::PULSE1_ENABLE,
+            self.pulse_channel_1.length_counter_active(),
+        );
+        enable.set(
+            ChannelEnable::PULSE2_ENABLE,
+            self.pulse_channel_2.length_counter_active(),
+        );
+        enable.set(
+            ChannelEnable::TRIANGLE_ENABLE,
+            self.triangle_channel.length_counter_active(),
+        );
+        enable.set(
+            ChannelEnable::NOISE_ENABLE,
+            self.noise_channel.length_counter_active(),
+        );
+        enable.set(ChannelEnable::DMC_ENABLE, self.dmc_channel.active());
+        enable.set(ChannelEnable::FRAME_INTERRUPT, self.frame_irq_set);
+        enable.set(ChannelEnable::DMC_INTERRUPT, self.dmc_channel.irq_set());
+
+        // Reading the status acknowledges the frame interrupt
+        self.frame_irq_set = false;
+
+        enable.bits()
     }
 
-    #[cfg(feature = "audio")]
     pub fn read(&mut self, addr: u16) -> u8 {
         match addr {
             0x4000..=0x4013 | 0x4017 => {
@@ -178,48 +335,25 @@ impl Apu {
                 );
                 0
             }
-            0x4015 => {
-                // channel enable and length counter status
-                let mut enable = ChannelEnable::empty();
-                enable.set(
-                    ChannelEnable::PULSE1_ENABLE,
-                    self.pulse_channel_1.length_counter_active(),
-                );
-                enable.set(
-                    ChannelEnable::PULSE2_ENABLE,
-                    self.pulse_channel_2.length_counter_active(),
-                );
-                enable.set(
-                    ChannelEnable::TRIANGLE_ENABLE,
-                    self.triangle_channel.length_counter_active(),
-                );
-                enable.set(
-                    ChannelEnable::NOISE_ENABLE,
-                    self.noise_channel.length_counter_active(),
-                );
-
-                enable.bits()
-            }
+            0x4015 => self.read_status(),
             _ => {
                 unreachable!("bad apu addr {:#X}", addr);
             }
         }
     }
 
-    #[cfg(not(feature = "audio"))]
-    pub fn read(&mut self, _addr: u16) -> u8 {
-        0
-    }
-
-    #[cfg(feature = "audio")]
+    /// The channels and the frame counter always run, since games rely on their interrupts and status.
+    /// Only the mixing of the output is left out without the `audio` feature.
     pub fn clock(&mut self) {
         // Pulse and noise channels run every second CPU cycle, while triangle runs every cycle
-        self.triangle_channel.clock();
+        self.triangle_channel
+            .clock(self.settings.silence_ultrasonic_triangle);
         if (self.frame_counter % 2) == 1 {
             self.pulse_channel_1.clock();
             self.pulse_channel_2.clock();
             self.noise_channel.clock();
         }
+        self.dmc_channel.clock();
 
         if self.sequence_mode.is_quarter_frame(self.frame_counter) {
             self.clock_quarter_frame();
@@ -229,16 +363,15 @@ impl Apu {
             self.clock_half_frame();
         }
 
+        if !self.disable_interrupts && self.sequence_mode.is_frame_irq(self.frame_counter) {
+            self.frame_irq_set = true;
+        }
+
+        #[cfg(feature = "audio")]
         self.dac.add_sample(self.mix_samples());
         self.frame_counter = (self.frame_counter + 1) % self.sequence_mode.get_max();
     }
 
-    #[cfg(not(feature = "audio"))]
-    pub fn clock(&mut self) {
-        // DO NOTHING
-    }
-
-    #[cfg(feature = "audio")]
     fn clock_quarter_frame(&mut self) {
         self.pulse_channel_1.clock_quarter_frame();
         self.pulse_channel_2.clock_quarter_frame();
@@ -246,7 +379,6 @@ impl Apu {
         self.noise_channel.clock_quarter_frame();
     }
 
-    #[cfg(feature = "audio")]
     fn clock_half_frame(&mut self) {
         self.pulse_channel_1.clock_half_frame();
         self.pulse_channel_2.clock_half_frame();
@@ -256,20 +388,406 @@ impl Apu {
 
     #[cfg(feature = "audio")]
     fn mix_samples(&self) -> f32 {
-        let pulse1 = self.pulse_channel_1.sample() * 1;
-        let pulse2 = self.pulse_channel_2.sample() * 1;
-        let triangle = self.triangle_channel.sample() * 1;
-        let noise = self.noise_channel.sample() * 1;
-        let dmc = 0;
-
-        // Lookup table mixing
-        let pulse_out = PULSE_MIXING_TABLE[(pulse1 + pulse2) as usize];
-        let tnd_out = TND_MIXING_TABLE[(3 * triangle + 2 * noise + dmc) as usize];
+        let pulse1 =
+            self.pulse_channel_1.sample() * self.is_channel_enabled(ApuChannel::Pulse1) as u8;
+        let pulse2 =
+            self.pulse_channel_2.sample() * self.is_channel_enabled(ApuChannel::Pulse2) as u8;
+        let triangle =
+            self.triangle_channel.sample() * self.is_channel_enabled(ApuChannel::Triangle) as u8;
+        let noise = self.noise_channel.sample() * self.is_channel_enabled(ApuChannel::Noise) as u8;
+        let dmc = self.dmc_channel.sample() * self.is_channel_enabled(ApuChannel::Dmc) as u8;
+
+        match self.settings.mixing_model {
+            MixingModel::Nonlinear => {
+                // Lookup table mixing
+                let pulse_out = PULSE_MIXING_TABLE[(pulse1 + pulse2) as usize];
+                let tnd_out = TND_MIXING_TABLE[(3 * triangle + 2 * noise + dmc) as usize];
+
+                pulse_out + tnd_out
+            }
+            MixingModel::Linear => {
+                let pulse_out = 0.00752 * (pulse1 + pulse2) as f32;
+                let tnd_out =
+                    0.00851 * triangle as f32 + 0.00494 * noise as f32 + 0.00335 * dmc as f32;
 
-        pulse_out + tnd_out
+                pulse_out + tnd_out
+            }
+        }
     }
 
     pub fn take_samples(&mut self) -> Vec<i16> {
         self.dac.take_samples()
     }
+
+    /// Same as `take_samples`, but normalized between -1.0 and 1.0
+    pub fn take_samples_f32(&mut self) -> Vec<f32> {
+        self.dac.take_samples_f32()
+    }
+}
+
+#[cfg(all(test, feature = "audio"))]
+mod tests {
+    use super::*;
+
+    fn playing_apu() -> Apu {
+        let mut apu = Apu::new();
+        apu.set_sample_rate(44100.0);
+
+        apu.write(0x4015, 0x05);
+
+        // Pulse 1, 50% duty at constant volume
+        apu.write(0x4000, 0xBF);
+        apu.write(0x4002, 0x40);
+        apu.write(0x4003, 0x01);
+
+        // Triangle
+        apu.write(0x4008, 0xFF);
+        apu.write(0x400A, 0x80);
+        apu.write(0x400B, 0x00);
+
+        apu
+    }
+
+    #[test]
+    fn mixing_models() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x03);
+
+        // Both pulses at 50% duty and the same period, at constant volumes of 15 and 7
+        apu.write(0x4000, 0xBF);
+        apu.write(0x4002, 0x40);
+        apu.write(0x4003, 0x01);
+        apu.write(0x4004, 0xB7);
+        apu.write(0x4006, 0x40);
+        apu.write(0x4007, 0x01);
+
+        // The halted triangle still outputs its last step
+        apu.set_channel_enabled(ApuChannel::Triangle, false);
+
+        while apu.pulse_channel_1.sample() == 0 || apu.pulse_channel_2.sample() == 0 {
+            apu.clock();
+        }
+        assert_eq!(
+            apu.pulse_channel_1.sample() + apu.pulse_channel_2.sample(),
+            22
+        );
+
+        let nonlinear = 95.52 / (8128.0 / 22.0 + 100.0);
+        assert!((apu.mix_samples() - nonlinear).abs() < 1e-6);
+        assert_eq!(apu.mix_samples(), PULSE_MIXING_TABLE[22]);
+
+        apu.set_mixing_model(MixingModel::Linear);
+        assert!((apu.mix_samples() - 0.00752 * 22.0).abs() < 1e-6);
+        assert!(apu.mix_samples() < nonlinear);
+    }
+
+    #[test]
+    fn power_on_state() {
+        let mut apu = playing_apu();
+        apu.write(0x400E, 0x80);
+        apu.write(0x4017, 0xC0);
+        for _ in 0..10000 {
+            apu.clock();
+        }
+        assert_ne!(apu.noise_channel.shift_register(), 1);
+
+        apu.power_on();
+
+        assert_eq!(apu.noise_channel.shift_register(), 1);
+        assert_eq!(apu.read(0x4015), 0);
+        assert!(!apu.disable_interrupts);
+        assert!(apu.sequence_mode == SequenceMode::Step4);
+        assert_eq!(apu.frame_counter, 0);
+        assert_eq!(apu.dac.get_sample_rate(), 44100.0);
+
+        // Length counters are disabled until `$4015` is written
+        apu.write(0x4003, 0x08);
+        assert_eq!(apu.read(0x4015), 0);
+    }
+
+    #[test]
+    fn reset_keeps_frame_counter_mode() {
+        let mut apu = playing_apu();
+        apu.write(0x4017, 0xC0);
+        for _ in 0..1000 {
+            apu.clock();
+        }
+        assert_eq!(apu.read(0x4015), 0x05);
+
+        apu.reset();
+
+        assert_eq!(apu.read(0x4015), 0);
+        assert!(apu.disable_interrupts);
+        assert!(apu.sequence_mode == SequenceMode::Step5);
+        assert_eq!(apu.frame_counter, 0);
+    }
+
+    // Clock the APU, feeding the DMC with its sample address as data, and return the addresses read
+    fn run_dmc(apu: &mut Apu, cycles: usize) -> Vec<u16> {
+        let mut reads = Vec::new();
+        for _ in 0..cycles {
+            apu.clock();
+            if let Some(addr) = apu.dmc_sample_address() {
+                reads.push(addr);
+                apu.load_dmc_sample(addr as u8);
+            }
+        }
+        reads
+    }
+
+    #[test]
+    fn dmc_address_wraps_to_8000() {
+        let mut apu = Apu::new();
+        apu.write(0x4010, 0x0F); // Fastest rate, no loop
+        apu.write(0x4012, 0xFF); // $FFC0
+        apu.write(0x4013, 0x04); // 65 bytes
+        apu.write(0x4015, 0x10);
+
+        let reads = run_dmc(&mut apu, 100000);
+        assert_eq!(reads.len(), 65);
+        assert!(reads[..64].iter().copied().eq(0xFFC0..=0xFFFF));
+        assert_eq!(reads[64], 0x8000);
+        assert_eq!(apu.read(0x4015) & 0x10, 0);
+    }
+
+    #[test]
+    fn dmc_end_of_sample() {
+        // Loop restarts the sample without raising an IRQ
+        let mut apu = Apu::new();
+        apu.write(0x4010, 0xCF);
+        apu.write(0x4012, 0xFF);
+        apu.write(0x4013, 0x00); // 1 byte
+        apu.write(0x4015, 0x10);
+
+        let reads = run_dmc(&mut apu, 2000);
+        assert!(reads.len() > 1);
+        assert!(reads.iter().all(|&addr| addr == 0xFFC0));
+        assert!(!apu.take_irq_set_state());
+
+        // Otherwise, the IRQ is raised at the end and stays until $4015 is written
+        apu.write(0x4010, 0x8F);
+        let reads = run_dmc(&mut apu, 2000);
+        assert!(reads.len() <= 1);
+        assert_eq!(apu.read(0x4015) & 0x90, 0x80);
+        assert!(apu.take_irq_set_state());
+        assert!(apu.take_irq_set_state());
+
+        apu.write(0x4015, 0x00);
+        assert!(!apu.take_irq_set_state());
+    }
+
+    #[test]
+    fn sample_rate_change_keeps_channels_running() {
+        let mut reference = playing_apu();
+        let mut apu = playing_apu();
+
+        for _ in 0..10000 {
+            reference.clock();
+            apu.clock();
+        }
+
+        // The samples of the reference are taken here, but the ones of the tested APU are kept pending
+        let samples_before = reference.take_samples().len();
+        apu.set_sample_rate(48000.0);
+
+        for _ in 0..10000 {
+            reference.clock();
+            apu.clock();
+
+            assert_eq!(reference.frame_counter, apu.frame_counter);
+            assert_eq!(reference.mix_samples(), apu.mix_samples());
+        }
+
+        // Only the output rate changed
+        assert_eq!(apu.dac.get_sample_rate(), 48000.0);
+        let samples_after = reference.take_samples().len();
+        assert!(apu.take_samples().len() > samples_before + samples_after);
+    }
+
+    #[test]
+    fn muted_channels_keep_running() {
+        let mut reference = playing_apu();
+        let mut apu = playing_apu();
+        apu.set_channel_enabled(ApuChannel::Pulse1, false);
+
+        let mut pulse_heard = false;
+        for _ in 0..10000 {
+            reference.clock();
+            apu.clock();
+
+            // The pulse channel keeps running, but only the others are mixed
+            assert_eq!(
+                reference.pulse_channel_1.sample(),
+                apu.pulse_channel_1.sample()
+            );
+            let tnd = 3 * apu.triangle_channel.sample()
+                + 2 * apu.noise_channel.sample()
+                + apu.dmc_channel.sample();
+            assert_eq!(apu.mix_samples(), TND_MIXING_TABLE[tnd as usize]);
+            pulse_heard |= reference.mix_samples() != apu.mix_samples();
+        }
+        assert!(pulse_heard);
+
+        apu.set_channel_enabled(ApuChannel::Pulse1, true);
+        assert_eq!(reference.mix_samples(), apu.mix_samples());
+    }
+
+    #[test]
+    fn status_length_counter() {
+        let mut apu = Apu::new();
+        apu.write(0x4015, 0x01);
+        apu.write(0x4000, 0x10); // Constant volume, length counter not halted
+        apu.write(0x4003, 0x18); // Length of 2 half frames
+        assert_eq!(apu.read_status(), 0x01);
+
+        // The first half frame is at cycle 14913
+        for _ in 0..14914 {
+            apu.clock();
+        }
+        assert_eq!(apu.read_status(), 0x01);
+
+        // The second one, at the end of the 4-step sequence, also raises the frame interrupt
+        for _ in 0..14916 {
+            apu.clock();
+        }
+        assert_eq!(apu.read_status(), 0x40);
+        assert_eq!(apu.read_status(), 0x00);
+    }
+
+    #[test]
+    fn frame_irq() {
+        let mut apu = Apu::new();
+        for _ in 0..29830 {
+            apu.clock();
+        }
+
+        // The IRQ stays asserted until the status is read
+        assert!(apu.irq_state());
+        assert!(apu.irq_state());
+        assert_eq!(apu.read(0x4015), 0x40);
+        assert!(!apu.irq_state());
+        assert_eq!(apu.read(0x4015), 0x00);
+
+        // Inhibiting the interrupt also acknowledges it
+        for _ in 0..29830 {
+            apu.clock();
+        }
+        assert!(apu.irq_state());
+        apu.write(0x4017, 0x40);
+        assert!(!apu.irq_state());
+
+        for _ in 0..29830 * 2 {
+            apu.clock();
+        }
+        assert!(!apu.irq_state());
+    }
+
+    #[test]
+    fn ultrasonic_triangle() {
+        let ultrasonic_apu = |silenced: bool| {
+            let mut apu = Apu::new();
+            apu.set_ultrasonic_triangle_silenced(silenced);
+            apu.write(0x4015, 0x04);
+            apu.write(0x4008, 0xFF);
+            apu.write(0x400A, 0x01);
+            apu.write(0x400B, 0x00);
+            apu
+        };
+
+        // The channel starts once the linear counter is reloaded on the first quarter frame
+        let mut apu = ultrasonic_apu(true);
+        for _ in 0..10000 {
+            apu.clock();
+        }
+        assert!(!apu.triangle_channel.is_muted());
+
+        // The sequencer stays where it is, so the output doesn't change
+        let sample = apu.triangle_channel.sample();
+        for _ in 0..1000 {
+            apu.clock();
+            assert_eq!(apu.triangle_channel.sample(), sample);
+        }
+
+        // Otherwise, it steps through the whole sequence in 64 cycles
+        let mut apu = ultrasonic_apu(false);
+        for _ in 0..10000 {
+            apu.clock();
+        }
+        let mut samples = Vec::new();
+        for _ in 0..64 {
+            apu.clock();
+            samples.push(apu.triangle_channel.sample());
+        }
+        assert!((0..=15).all(|value| samples.contains(&value)));
+    }
+
+    #[test]
+    fn solo_channel() {
+        let mut apu = playing_apu();
+        apu.solo_channel(ApuChannel::Triangle);
+        assert!(apu.is_channel_enabled(ApuChannel::Triangle));
+        assert!(!apu.is_channel_enabled(ApuChannel::Pulse1));
+
+        // Only the triangle is heard, even though the pulse channel is playing too
+        let mut pulse_playing = false;
+        let mut triangle_heard = false;
+        for _ in 0..10000 {
+            apu.clock();
+            let triangle = apu.triangle_channel.sample();
+            assert_eq!(apu.mix_samples(), TND_MIXING_TABLE[3 * triangle as usize]);
+            pulse_playing |= apu.pulse_channel_1.sample() != 0;
+            triangle_heard |= apu.mix_samples() != 0.0;
+        }
+        assert!(pulse_playing);
+        assert!(triangle_heard);
+    }
+
+    #[test]
+    fn filters_remove_dc_offset() {
+        // The DMC output level stays where it's set without a sample, and the silent triangle holds its level
+        let dc_apu = |filtering: bool| {
+            let mut apu = Apu::new();
+            apu.set_filtering_enabled(filtering);
+            apu.write(0x4011, 0x7F);
+            let offset = apu.mix_samples();
+
+            // A tenth of a second
+            for _ in 0..178_977 {
+                apu.clock();
+            }
+            (offset, apu.take_samples_f32())
+        };
+
+        let (offset, samples) = dc_apu(false);
+        assert!(offset > 0.0);
+        assert!(samples
+            .iter()
+            .all(|&sample| (sample - offset).abs() < 0.0001));
+
+        // The high-pass filters start from the offset and bring it down to nothing
+        let (_, samples) = dc_apu(true);
+        assert!(samples[0] > offset * 0.25);
+        assert!(samples[samples.len() - 1].abs() < offset * 0.001);
+    }
+
+    #[test]
+    fn f32_samples_match_i16() {
+        let mut apu_i16 = playing_apu();
+        let mut apu_f32 = playing_apu();
+
+        for _ in 0..10000 {
+            apu_i16.clock();
+            apu_f32.clock();
+        }
+
+        let samples_i16 = apu_i16.take_samples();
+        let samples_f32 = apu_f32.take_samples_f32();
+        assert!(!samples_i16.is_empty());
+        assert_eq!(samples_i16.len(), samples_f32.len());
+
+        for (&s16, &s32) in samples_i16.iter().zip(samples_f32.iter()) {
+            assert!((-1.0..=1.0).contains(&s32));
+            assert!((s16 as f32 / 32768.0 - s32).abs() < 2.0 / 32768.0);
+        }
+    }
 }
@@ -4,6 +4,8 @@ const PERIOD_TABLE: [u16; 16] = [
     4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
 ];
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NoiseChannel {
     envelope: Envelope,
     timer: Timer,
@@ -76,6 +78,11 @@ impl NoiseChannel {
         self.length_counter.set_enable(enable);
     }
 
+    #[cfg(test)]
+    pub fn shift_register(&self) -> u16 {
+        self.shift_register
+    }
+
     pub fn sample(&self) -> u8 {
         if self.is_muted() {
             0
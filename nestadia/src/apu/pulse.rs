@@ -10,6 +10,7 @@ const DUTY_SEQUENCES: [[u8; 8]; 4] = [
 
 bitfield! {
     #[derive(Clone, Copy, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Sweep(u8);
     impl Debug;
 
@@ -19,7 +20,8 @@ bitfield! {
     pub enable, set_enable: 7;
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PulseChannel {
     envelope: Envelope,
     sweep: Sweep,
@@ -8,6 +8,7 @@ const SEQUENCE: [u8; 32] = [
 
 bitfield! {
     #[derive(Clone, Copy, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct LinearCounterRegister(u8);
     impl Debug;
 
@@ -15,7 +16,8 @@ bitfield! {
     pub control, set_control: 7;
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct LinearCounter {
     register: LinearCounterRegister,
     counter: u8,
@@ -48,7 +50,8 @@ impl LinearCounter {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TriangleChannel {
     timer: Timer,
     length_counter: LengthCounter,
@@ -79,9 +82,11 @@ impl TriangleChannel {
         }
     }
 
-    pub fn clock(&mut self) {
+    /// With `silence_ultrasonic`, the sequencer stops at periods below 2 instead of producing an inaudible
+    /// tone, which would pop when mixed at the output sample rate.
+    pub fn clock(&mut self, silence_ultrasonic: bool) {
         self.timer.clock();
-        if self.timer.done() && !self.is_muted() {
+        if self.timer.done() && !self.is_muted() && !(silence_ultrasonic && self.is_ultrasonic()) {
             self.sequence_index = (self.sequence_index + 1) % 32;
         }
     }
@@ -106,9 +111,11 @@ impl TriangleChannel {
         SEQUENCE[self.sequence_index as usize]
     }
 
-    fn is_muted(&self) -> bool {
-        self.timer.counter() < 2
-            || self.linear_counter.counter() == 0
-            || self.length_counter.counter() == 0
+    pub fn is_muted(&self) -> bool {
+        self.linear_counter.counter() == 0 || self.length_counter.counter() == 0
+    }
+
+    fn is_ultrasonic(&self) -> bool {
+        self.timer.period() < 2
     }
 }
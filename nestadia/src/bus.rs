@@ -1,5 +1,6 @@
 use crate::cartridge::Cartridge;
 use crate::cartridge::Mirroring;
+use crate::controller::Zapper;
 use crate::Apu;
 use crate::Ppu;
 use crate::RAM_SIZE;
@@ -12,6 +13,7 @@ macro_rules! borrow_cpu_bus {
             &mut $owner.controller_state,
             &mut $owner.controller1_snapshot,
             &mut $owner.controller2_snapshot,
+            &$owner.zapper,
             &mut $owner.ram,
             &mut $owner.apu,
             &mut $owner.cartridge,
@@ -27,17 +29,75 @@ macro_rules! borrow_ppu_bus {
     }};
 }
 
+/// Labeled range of the CPU address space
+#[cfg(feature = "debugger")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub start: u16,
+    pub end: u16, // Inclusive
+    pub label: &'static str,
+    pub prg_bank: Option<u8>, // 16KB PRG ROM bank currently mapped, if any
+}
+
+/// Layout of the CPU address space, as decoded by `CpuBus`.
+/// The PRG ROM regions reflect the banks currently selected by the mapper.
+#[cfg(feature = "debugger")]
+pub fn memory_map(cartridge: &Cartridge) -> alloc::vec::Vec<MemoryRegion> {
+    let region = |start, end, label| MemoryRegion {
+        start,
+        end,
+        label,
+        prg_bank: None,
+    };
+
+    let mut regions = alloc::vec![
+        region(0x0000, 0x1FFF, "RAM"),
+        region(0x2000, 0x3FFF, "PPU registers"),
+        region(0x4000, 0x4017, "APU and I/O registers"),
+        region(0x4018, 0x401F, "APU and I/O test registers"),
+        region(0x4020, 0x5FFF, "Cartridge expansion"),
+        if cartridge.has_prg_ram() {
+            region(0x6000, 0x7FFF, "PRG RAM")
+        } else {
+            region(0x6000, 0x7FFF, "Unmapped")
+        },
+    ];
+
+    // Mappers switch banks as small as 8KB, so merge the 8KB windows that are in the same bank.
+    // Banks are numbered in 16KB, so a mirrored bank in the other 16KB half is kept as its own region.
+    for start in (0x8000..=0xE000u16).step_by(0x2000) {
+        let bank = cartridge.get_prg_bank(start);
+        match regions.last_mut() {
+            Some(last)
+                if last.label == "PRG ROM" && last.prg_bank == bank && start & 0x3FFF != 0 =>
+            {
+                last.end = start + 0x1FFF;
+            }
+            _ => regions.push(MemoryRegion {
+                start,
+                end: start + 0x1FFF,
+                label: "PRG ROM",
+                prg_bank: bank,
+            }),
+        }
+    }
+
+    regions
+}
+
 pub struct CpuBus<'a> {
     controller1: &'a mut u8,
     controller2: &'a mut u8,
     controller_state: &'a mut bool,
     controller1_snapshot: &'a mut u8,
     controller2_snapshot: &'a mut u8,
+    zapper: &'a Option<Zapper>,
     ram: &'a mut [u8; RAM_SIZE as usize],
     apu: &'a mut Apu,
     cartridge: &'a mut Cartridge,
     ppu: &'a mut Ppu,
     name_tables: &'a mut [u8; 1024 * 4],
+    oam_dma: bool, // An OAM DMA was started during this cycle
 }
 
 impl<'a> CpuBus<'a> {
@@ -48,6 +108,7 @@ impl<'a> CpuBus<'a> {
         controller_state: &'a mut bool,
         controller1_snapshot: &'a mut u8,
         controller2_snapshot: &'a mut u8,
+        zapper: &'a Option<Zapper>,
         ram: &'a mut [u8; RAM_SIZE as usize],
         apu: &'a mut Apu,
         cartridge: &'a mut Cartridge,
@@ -60,11 +121,13 @@ impl<'a> CpuBus<'a> {
             controller_state,
             controller1_snapshot,
             controller2_snapshot,
+            zapper,
             ram,
             apu,
             cartridge,
             ppu,
             name_tables,
+            oam_dma: false,
         }
     }
 }
@@ -78,6 +141,22 @@ impl CpuBus<'_> {
         self.ram[(addr & (RAM_SIZE - 1)) as usize]
     }
 
+    /// Read the CPU address space without side effects, for the debugger.
+    /// Reading the registers has side effects, so `None` is returned for them instead.
+    #[cfg(feature = "debugger")]
+    pub fn peek(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0..=0x1FFF => Some(self.read_ram(addr)),
+            0x2000..=0x401F => None,
+            0x4020..=0xFFFF => Some(self.cartridge.read_prg_mem(addr)),
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn get_prg_bank(&self, addr: u16) -> Option<u8> {
+        self.cartridge.get_prg_bank(addr)
+    }
+
     // TODO: Cleanup if current solution is working
     /*#[cfg(feature = "audio")]*/
     pub fn write_apu_register(&mut self, addr: u16, data: u8) {
@@ -91,6 +170,10 @@ impl CpuBus<'_> {
         self.apu.read(addr)
     }
 
+    pub fn read_apu_status(&mut self) -> u8 {
+        self.apu.read_status()
+    }
+
     pub fn write_ppu_register(&mut self, addr: u16, data: u8) {
         let mut ppu_bus = borrow_ppu_bus!(self);
         self.ppu.write(&mut ppu_bus, addr, data);
@@ -103,27 +186,41 @@ impl CpuBus<'_> {
     }
 
     pub fn controller_write(&mut self, data: u8) {
-        *self.controller_state = data & 0x01 == 0x01;
-        *self.controller1_snapshot = *self.controller1;
-        *self.controller2_snapshot = *self.controller2;
+        // The shift registers reload while the strobe is high, and keep the state latched when it goes low.
+        // Writing 0 while it is already low doesn't reload them.
+        let strobe = data & 0x01 == 0x01;
+        if strobe || *self.controller_state {
+            *self.controller1_snapshot = *self.controller1;
+            *self.controller2_snapshot = *self.controller2;
+        }
+        *self.controller_state = strobe;
     }
 
     pub fn read_controller1_snapshot(&mut self) -> u8 {
         if *self.controller_state {
-            *self.controller1 & 0x80 >> 7
+            // The shift register keeps reloading, so only A is reported
+            (*self.controller1 & 0x80) >> 7
         } else {
+            // A is shifted out first. Official controllers report 1 once the 8 buttons are read
             let data = (*self.controller1_snapshot & 0x80) >> 7;
-            *self.controller1_snapshot <<= 1;
+            *self.controller1_snapshot = (*self.controller1_snapshot << 1) | 0x01;
             data
         }
     }
 
     pub fn read_controller2_snapshot(&mut self) -> u8 {
+        // The zapper replaces the controller
+        if let Some(zapper) = self.zapper {
+            return zapper.read();
+        }
+
         if *self.controller_state {
-            *self.controller2 & 0x80 >> 7
+            // The shift register keeps reloading, so only A is reported
+            (*self.controller2 & 0x80) >> 7
         } else {
+            // A is shifted out first. Official controllers report 1 once the 8 buttons are read
             let data = (*self.controller2_snapshot & 0x80) >> 7;
-            *self.controller2_snapshot <<= 1;
+            *self.controller2_snapshot = (*self.controller2_snapshot << 1) | 0x01;
             data
         }
     }
@@ -138,6 +235,12 @@ impl CpuBus<'_> {
 
     pub fn write_ppu_oam_dma(&mut self, buffer: &[u8; 256]) {
         self.ppu.write_oam_dma(buffer);
+        self.oam_dma = true;
+    }
+
+    /// Whether an OAM DMA was started, in which case the CPU must be suspended during the transfer
+    pub fn take_oam_dma(&mut self) -> bool {
+        core::mem::take(&mut self.oam_dma)
     }
 }
 
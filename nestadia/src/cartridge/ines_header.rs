@@ -1,56 +1,56 @@
-#![allow(clippy::identity_op)]
+// Header of the ROM files, in the iNES format or its NES 2.0 extension
+// https://wiki.nesdev.com/w/index.php/INES
+// https://wiki.nesdev.com/w/index.php/NES_2.0
 
 use core::convert::TryFrom;
 
 use bitflags::bitflags;
 
-use crate::cartridge::RomParserError;
-
-#[derive(Debug)]
-pub struct INesHeader {
-    pub mapper_id: u8,
-    pub prg_size: u8,
-    pub chr_size: u8,
-    pub flags6: Flags6,
-    pub flags7: Flags7,
-    pub flags8: u8, // Flags 8 is actually the PRG ram size
-    pub flags9: Flags9,
-    pub flags10: Flags10,
-}
+use crate::cartridge::{Mirroring, RomParserError};
 
-bitflags! {
-    pub struct Flags6: u8 {
-        const MIRRORING = (1 << 0);
-        const PRG_RAM = (1 << 1);
-        const TRAINER = (1 << 2);
-        const FOUR_SCREEN = (1 << 3);
-    }
-}
+const PRG_ROM_UNIT: usize = 16384;
+const CHR_ROM_UNIT: usize = 8192;
+// Unit of the PRG RAM size of iNES headers, where 0 also means 8KB
+const INES_PRG_RAM_UNIT: usize = 8192;
 
-bitflags! {
-    pub struct Flags7: u8 {
-        const VS_UNISYSTEM = (1 << 0);
-        const PLAYCHOICE_10 = (1 << 1);
-        const NES2 = (1 << 2) | (1 << 3);
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFormat {
+    INes,
+    Nes2,
 }
 
-bitflags! {
-    pub struct Flags9: u8 {
-        const TV_SYSTEM = (1 << 0);
-    }
+/// Header of the ROM file. The sizes are in bytes.
+/// iNES headers don't declare some of them, so those are filled with what games conventionally expect.
+#[derive(Debug, Clone)]
+pub struct CartridgeHeader {
+    pub format: HeaderFormat,
+    pub mapper_id: u16,
+    /// Variant of the mapper, only declared by NES 2.0 headers
+    pub submapper: u8,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub prg_ram_size: usize,
+    /// Battery-backed PRG RAM
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    /// Battery-backed CHR RAM
+    pub chr_nvram_size: usize,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    /// A 512-byte trainer is between the header and the PRG ROM
+    pub trainer: bool,
 }
 
 bitflags! {
-    pub struct Flags10: u8 {
-        const PAL = (2 << 0);
-        const DUAL = (1 << 0);
-        const PRG_RAM = (1 << 4);
-        const BUS_CONFLICT = (1 << 5);
+    struct Flags6: u8 {
+        const MIRRORING = (1 << 0);
+        const PRG_RAM = (1 << 1); // Battery-backed PRG RAM
+        const TRAINER = (1 << 2);
+        const FOUR_SCREEN = (1 << 3);
     }
 }
 
-impl TryFrom<&[u8]> for INesHeader {
+impl TryFrom<&[u8]> for CartridgeHeader {
     type Error = RomParserError;
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
@@ -61,29 +61,133 @@ impl TryFrom<&[u8]> for INesHeader {
         };
 
         if data[..4] != MAGIC_BYTES {
-            return Err(RomParserError::InvalidMagicBytes);
+            return Err(RomParserError::InvalidMagic);
         };
 
-        let prg_size = data[4];
-        let chr_size = data[5];
+        let flags6 = Flags6::from_bits_truncate(data[6]);
+        let mirroring = if flags6.contains(Flags6::FOUR_SCREEN) {
+            Mirroring::FourScreen
+        } else if flags6.contains(Flags6::MIRRORING) {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+        let battery = flags6.contains(Flags6::PRG_RAM);
+
+        let mut header = CartridgeHeader {
+            format: HeaderFormat::INes,
+            mapper_id: u16::from((data[6] >> 4) | (data[7] & 0xf0)),
+            submapper: 0,
+            prg_rom_size: data[4] as usize * PRG_ROM_UNIT,
+            chr_rom_size: data[5] as usize * CHR_ROM_UNIT,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            mirroring,
+            battery,
+            trainer: flags6.contains(Flags6::TRAINER),
+        };
 
-        let mapper_id = (data[6] >> 4) | (data[7] & 0xf0);
+        // Bits 2-3 of flags 7 are 0b10 in NES 2.0 headers
+        if data[7] & 0x0C == 0x08 {
+            header.format = HeaderFormat::Nes2;
+            header.mapper_id |= u16::from(data[8] & 0x0F) << 8;
+            header.submapper = data[8] >> 4;
+            header.prg_rom_size = rom_size(data[4], data[9] & 0x0F, PRG_ROM_UNIT)?;
+            header.chr_rom_size = rom_size(data[5], data[9] >> 4, CHR_ROM_UNIT)?;
+            header.prg_ram_size = ram_size(data[10] & 0x0F);
+            header.prg_nvram_size = ram_size(data[10] >> 4);
+            header.chr_ram_size = ram_size(data[11] & 0x0F);
+            header.chr_nvram_size = ram_size(data[11] >> 4);
+        } else {
+            // The PRG RAM is battery-backed when the battery flag is set
+            let prg_ram_size = data[8].max(1) as usize * INES_PRG_RAM_UNIT;
+            if battery {
+                header.prg_nvram_size = prg_ram_size;
+            } else {
+                header.prg_ram_size = prg_ram_size;
+            }
+
+            // Cartridges without CHR ROM have 8KB of CHR RAM
+            if header.chr_rom_size == 0 {
+                header.chr_ram_size = CHR_ROM_UNIT;
+            }
+        }
+
+        Ok(header)
+    }
+}
 
-        let flags6 = Flags6::from_bits_truncate(data[6]);
-        let flags7 = Flags7::from_bits_truncate(data[7]);
-        let flags8 = data[8];
-        let flags9 = Flags9::from_bits_truncate(data[9]);
-        let flags10 = Flags10::from_bits_truncate(data[10]);
-
-        Ok(INesHeader {
-            mapper_id,
-            prg_size,
-            chr_size,
-            flags6,
-            flags7,
-            flags8,
-            flags9,
-            flags10,
-        })
+// A most significant nibble of $F means the size is given as 2^E * (MM * 2 + 1), from the EEEEEEMM least significant byte
+fn rom_size(lsb: u8, msb: u8, unit: usize) -> Result<usize, RomParserError> {
+    if msb == 0x0F {
+        let multiplier = (lsb & 0x03) as usize * 2 + 1;
+        1usize
+            .checked_shl(u32::from(lsb >> 2))
+            .and_then(|size| size.checked_mul(multiplier))
+            .ok_or(RomParserError::RomTooLarge)
+    } else {
+        Ok(((msb as usize) << 8 | lsb as usize) * unit)
+    }
+}
+
+// RAM sizes are shift counts, 64 << shift bytes, where 0 means there's none
+fn ram_size(shift: u8) -> usize {
+    match shift {
+        0 => 0,
+        shift => 64 << shift,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Result<CartridgeHeader, RomParserError> {
+        let mut data = [0u8; 16];
+        data[..4].copy_from_slice(b"NES\x1A");
+        data[4..4 + bytes.len()].copy_from_slice(bytes);
+        CartridgeHeader::try_from(&data[..])
+    }
+
+    #[test]
+    fn ines_header() {
+        // Battery-backed MMC1 with vertical mirroring and CHR RAM
+        let header = parse(&[0x08, 0x00, 0x13, 0x00, 0x00]).unwrap();
+        assert_eq!(header.format, HeaderFormat::INes);
+        assert_eq!(header.mapper_id, 1);
+        assert_eq!(header.prg_rom_size, 128 * 1024);
+        assert_eq!(header.chr_rom_size, 0);
+        assert_eq!((header.prg_ram_size, header.prg_nvram_size), (0, 8192));
+        assert_eq!(header.chr_ram_size, 8192);
+        assert!(matches!(header.mirroring, Mirroring::Vertical));
+        assert!(header.battery);
+        assert!(!header.trainer);
+    }
+
+    #[test]
+    fn nes2_header() {
+        // Mapper 0x123 submapper 4, 2MB of PRG ROM from the exponent form and 0x120 8K CHR banks,
+        // 8KB of PRG RAM and 32KB of PRG NVRAM, and 2KB of CHR RAM
+        let header = parse(&[
+            0x54, 0x20, 0x34, 0x28, 0x41, 0x1F, 0x97, 0x05, 0x00, 0x00, 0x00,
+        ])
+        .unwrap();
+        assert_eq!(header.format, HeaderFormat::Nes2);
+        assert_eq!(header.mapper_id, 0x123);
+        assert_eq!(header.submapper, 4);
+        assert_eq!(header.prg_rom_size, 1 << 21);
+        assert_eq!(header.chr_rom_size, 0x120 * 8192);
+        assert_eq!((header.prg_ram_size, header.prg_nvram_size), (8192, 32768));
+        assert_eq!((header.chr_ram_size, header.chr_nvram_size), (2048, 0));
+        assert!(matches!(header.mirroring, Mirroring::Horizontal));
+        assert!(header.trainer);
+
+        // The exponent form can describe sizes that don't fit in memory
+        assert_eq!(
+            parse(&[0xFF, 0x00, 0x00, 0x08, 0x00, 0x0F]).err(),
+            Some(RomParserError::RomTooLarge)
+        );
     }
 }
@@ -1,5 +1,7 @@
 use super::{CartridgeReadTarget, Mapper, Mirroring};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper000 {
     prg_banks: u8,
     mirroring: Mirroring,
@@ -43,11 +45,25 @@ impl Mapper for Mapper000 {
         None
     }
 
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg_banks)
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         match addr {
             0x8000..=0xBFFF => Some(0),
-            0xC000..=0xFFFF => Some(1),
+            // 16KB ROMs are mirrored
+            0xC000..=0xFFFF => Some(self.prg_banks - 1),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some((addr / 0x400) as u8),
             _ => None,
         }
     }
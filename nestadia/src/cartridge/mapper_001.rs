@@ -3,17 +3,19 @@ use super::{CartridgeReadTarget, Mapper, Mirroring};
 const CHR_MODE_MASK: u8 = 0b10000;
 const PRG_MODE_MASK: u8 = 0b01100;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper001 {
     prg_banks: u8,
-    prg_bank_selector_32: u8,
-    prg_bank_selector_16_lo: u8,
-    prg_bank_selector_16_hi: u8,
-    chr_bank_selector_8: u8,
-    chr_bank_selector_4_lo: u8,
-    chr_bank_selector_4_hi: u8,
+    // The registers are kept as written, and the banks are computed from them on each access,
+    // so changing the banking mode applies right away
+    prg_bank: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
     load_register: u8,
     load_register_count: u8,
     control_register: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::state::byte_array"))]
     ram_data: [u8; 0x2000],
     mirroring: Mirroring,
 }
@@ -32,12 +34,9 @@ impl Mapper001 {
 
         Self {
             prg_banks,
-            prg_bank_selector_32: 0,
-            prg_bank_selector_16_lo: 0,
-            prg_bank_selector_16_hi: prg_banks - 1,
-            chr_bank_selector_8: 0,
-            chr_bank_selector_4_lo: 0,
-            chr_bank_selector_4_hi: 0,
+            prg_bank: 0,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
             load_register: 0,
             load_register_count: 0,
             control_register: 0x0C,
@@ -45,6 +44,40 @@ impl Mapper001 {
             mirroring,
         }
     }
+
+    // 16KB PRG bank mapped at this $8000-$FFFF address
+    fn prg_bank_at(&self, addr: u16) -> u8 {
+        let bank = self.prg_bank & 0x0F;
+        match ((self.control_register & PRG_MODE_MASK) >> 2, addr) {
+            // 32K mode, which ignores the low bit of the bank
+            (0..=1, 0x8000..=0xBFFF) => bank & 0x0E,
+            (0..=1, _) => bank | 0x01,
+            // 16K mode, fix low bank
+            (2, 0x8000..=0xBFFF) => 0,
+            (2, _) => bank,
+            // 16K mode, fix high bank
+            (_, 0x8000..=0xBFFF) => bank,
+            (_, _) => self.prg_banks - 1,
+        }
+    }
+
+    // 4KB CHR bank mapped at this $0000-$1FFF address
+    fn chr_bank_at(&self, addr: u16) -> u8 {
+        if (self.control_register & CHR_MODE_MASK) != 0 {
+            // 4K CHR mode
+            match addr & 0x1FFF {
+                0x0000..=0x0FFF => self.chr_bank_0,
+                _ => self.chr_bank_1,
+            }
+        } else {
+            // 8K CHR mode, which ignores the low bit of the bank
+            (self.chr_bank_0 & 0x1E) | ((addr & 0x1000) >> 12) as u8
+        }
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        (self.chr_bank_at(addr) as usize) * 0x1000 + (addr & 0x0FFF) as usize
+    }
 }
 
 impl Mapper for Mapper001 {
@@ -55,29 +88,12 @@ impl Mapper for Mapper001 {
                 CartridgeReadTarget::PrgRam(self.ram_data[(addr & 0x1FFF) as usize])
                 // TODO: windowed RAM?
             }
+            0x8000..=0xFFFF => CartridgeReadTarget::PrgRom(
+                (self.prg_bank_at(addr) as usize) * 0x4000 + (addr & 0x3FFF) as usize,
+            ),
             _ => {
-                if (self.control_register & PRG_MODE_MASK) > 1 {
-                    // 16K PRG mode
-                    match addr {
-                        0x8000..=0xBFFF => CartridgeReadTarget::PrgRom(
-                            (self.prg_bank_selector_16_lo as usize) * 0x4000
-                                + (addr & 0x3FFF) as usize,
-                        ),
-                        0xC000..=0xFFFF => CartridgeReadTarget::PrgRom(
-                            (self.prg_bank_selector_16_hi as usize) * 0x4000
-                                + (addr & 0x3FFF) as usize,
-                        ),
-                        _ => {
-                            log::warn!("Attempted to read address w/o known mapping {:#06x}", addr);
-                            CartridgeReadTarget::PrgRom(0)
-                        }
-                    }
-                } else {
-                    // 32K PRG mode
-                    CartridgeReadTarget::PrgRom(
-                        (self.prg_bank_selector_32 as usize) * 0x8000 + (addr & 0x7FFF) as usize,
-                    )
-                }
+                log::warn!("Attempted to read address w/o known mapping {:#06x}", addr);
+                CartridgeReadTarget::PrgRom(0)
             }
         }
     }
@@ -118,34 +134,15 @@ impl Mapper for Mapper001 {
                 }
                 0x2000 => {
                     // CHR bank 0
-                    if (self.control_register & CHR_MODE_MASK) != 0 {
-                        self.chr_bank_selector_4_lo = self.load_register & 0x1F;
-                    } else {
-                        self.chr_bank_selector_8 = self.load_register & 0x1E;
-                    }
+                    self.chr_bank_0 = self.load_register & 0x1F;
                 }
                 0x4000 => {
                     // CHR bank 1
-                    self.chr_bank_selector_4_hi = self.load_register & 0x1F;
+                    self.chr_bank_1 = self.load_register & 0x1F;
                 }
                 0x6000 => {
                     // PRG bank
-                    match (self.control_register & PRG_MODE_MASK) >> 2 {
-                        2 => {
-                            // 16K mode, fix low bank
-                            self.prg_bank_selector_16_lo = 0;
-                            self.prg_bank_selector_16_hi = self.load_register & 0x0F;
-                        }
-                        3 => {
-                            // 16K mode, fix high bank
-                            self.prg_bank_selector_16_lo = self.load_register & 0x0F;
-                            self.prg_bank_selector_16_hi = self.prg_banks - 1;
-                        }
-                        _ => {
-                            // 32K mode
-                            self.prg_bank_selector_32 = (self.load_register & 0x0E) >> 1;
-                        }
-                    }
+                    self.prg_bank = self.load_register & 0x1F;
                 }
                 _ => unreachable!(),
             }
@@ -156,23 +153,16 @@ impl Mapper for Mapper001 {
         }
     }
 
+    fn has_register_at(&self, _addr: u16) -> bool {
+        true
+    }
+
     fn ppu_map_read(&mut self, addr: u16) -> usize {
-        if (self.control_register & CHR_MODE_MASK) != 0 {
-            // 4K CHR mode
-            match addr {
-                0x0000..=0x0FFF => {
-                    (self.chr_bank_selector_4_lo as usize) * 0x1000 + (addr & 0x0FFF) as usize
-                }
-                _ => (self.chr_bank_selector_4_hi as usize) * 0x1000 + (addr & 0x0FFF) as usize,
-            }
-        } else {
-            // 8K CHR mode
-            (self.chr_bank_selector_8 as usize) * 0x2000 + (addr & 0x1FFF) as usize
-        }
+        self.chr_addr(addr)
     }
 
     fn ppu_map_write(&self, addr: u16) -> Option<usize> {
-        Some((self.chr_bank_selector_8 as usize) * 0x2000 + (addr & 0x1FFF) as usize)
+        Some(self.chr_addr(addr))
     }
 
     fn mirroring(&self) -> Mirroring {
@@ -183,23 +173,108 @@ impl Mapper for Mapper001 {
         Some(&self.ram_data)
     }
 
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg_banks)
+    }
+
+    // The last bank is fixed at $C000, where the vectors are
+    fn power_on(&mut self) {
+        self.prg_bank = 0;
+        self.chr_bank_0 = 0;
+        self.chr_bank_1 = 0;
+        self.load_register = 0;
+        self.load_register_count = 0;
+        self.control_register = 0x0C;
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         match addr {
-            0x0000..=0x7FFF => None,
-            _ => {
-                if (self.control_register & PRG_MODE_MASK) > 1 {
-                    // 16K PRG mode
-                    match addr {
-                        0x8000..=0xBFFF => Some(self.prg_bank_selector_16_lo),
-                        0xC000..=0xFFFF => Some(self.prg_bank_selector_16_hi),
-                        _ => None,
-                    }
-                } else {
-                    // 32K PRG mode
-                    Some(self.prg_bank_selector_32)
-                }
-            }
+            0x8000..=0xFFFF => Some(self.prg_bank_at(addr)),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some(self.chr_bank_at(addr) * 4 + ((addr & 0x0FFF) / 0x400) as u8),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Registers are written one bit at a time, starting with the lowest
+    fn write_register(mapper: &mut Mapper001, addr: u16, value: u8) {
+        for i in 0..5 {
+            mapper.cpu_map_write(addr, (value >> i) & 0x01);
+        }
+    }
+
+    fn prg_bank(mapper: &Mapper001, addr: u16) -> usize {
+        match mapper.cpu_map_read(addr) {
+            CartridgeReadTarget::PrgRom(rom_addr) => rom_addr / 0x4000,
+            CartridgeReadTarget::PrgRam(_) => unreachable!(),
         }
     }
+
+    #[test]
+    fn prg_banking_modes() {
+        let mut mapper = Mapper001::new(8, Mirroring::Vertical, None);
+        assert_eq!(prg_bank(&mapper, 0x8000), 0);
+        assert_eq!(prg_bank(&mapper, 0xC000), 7);
+
+        write_register(&mut mapper, 0xE000, 0x05);
+        assert_eq!(prg_bank(&mapper, 0x8000), 5);
+        assert_eq!(prg_bank(&mapper, 0xC000), 7);
+
+        // Fix the first bank at $8000. The bank that was written before moves right away.
+        write_register(&mut mapper, 0x8000, 0x08);
+        assert_eq!(prg_bank(&mapper, 0x8000), 0);
+        assert_eq!(prg_bank(&mapper, 0xC000), 5);
+
+        // Mode 1 is also 32K mode
+        write_register(&mut mapper, 0x8000, 0x04);
+        assert_eq!(prg_bank(&mapper, 0x8000), 4);
+        assert_eq!(prg_bank(&mapper, 0xC000), 5);
+
+        // Writing with bit 7 set aborts the write and goes back to the power-on mode
+        mapper.cpu_map_write(0xE000, 0x01);
+        mapper.cpu_map_write(0xE000, 0x80);
+        write_register(&mut mapper, 0xE000, 0x02);
+        assert_eq!(prg_bank(&mapper, 0x8000), 2);
+        assert_eq!(prg_bank(&mapper, 0xC000), 7);
+    }
+
+    #[test]
+    fn chr_banking_modes() {
+        let mut mapper = Mapper001::new(2, Mirroring::Vertical, None);
+
+        // 8K mode ignores the low bit of the first bank
+        write_register(&mut mapper, 0xA000, 0x03);
+        write_register(&mut mapper, 0xC000, 0x06);
+        assert_eq!(mapper.ppu_map_read(0x0010), 2 * 0x1000 + 0x10);
+        assert_eq!(mapper.ppu_map_read(0x1010), 3 * 0x1000 + 0x10);
+
+        write_register(&mut mapper, 0x8000, 0x1C);
+        assert_eq!(mapper.ppu_map_read(0x0010), 3 * 0x1000 + 0x10);
+        assert_eq!(mapper.ppu_map_read(0x1010), 6 * 0x1000 + 0x10);
+        assert_eq!(mapper.ppu_map_write(0x1010), Some(6 * 0x1000 + 0x10));
+    }
+
+    #[test]
+    fn mirroring_control() {
+        let mut mapper = Mapper001::new(2, Mirroring::Vertical, None);
+
+        write_register(&mut mapper, 0x8000, 0x0F);
+        assert!(matches!(mapper.mirroring(), Mirroring::Horizontal));
+
+        write_register(&mut mapper, 0x8000, 0x0C);
+        assert!(matches!(mapper.mirroring(), Mirroring::OneScreenLower));
+    }
 }
@@ -1,23 +1,48 @@
 use super::{CartridgeReadTarget, Mapper, Mirroring};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper002 {
-    prg_bank_selector: u8,
-    prg_banks: u8,
+    prg: UxRomPrg,
     mirroring: Mirroring,
 }
 
 impl Mapper002 {
     pub fn new(prg_banks: u8, mirroring: Mirroring) -> Self {
         Self {
-            prg_bank_selector: 0,
-            prg_banks,
+            prg: UxRomPrg::new(prg_banks),
             mirroring,
         }
     }
 }
 
-impl Mapper for Mapper002 {
-    fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget {
+/// UxROM PRG banking: a switchable 16KB bank at 0x8000-0xBFFF and the last bank fixed at 0xC000-0xFFFF.
+/// Boards that copy it only differ in how the bank register is decoded.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(super) struct UxRomPrg {
+    prg_bank_selector: u8,
+    prg_banks: u8,
+}
+
+impl UxRomPrg {
+    pub(super) fn new(prg_banks: u8) -> Self {
+        Self {
+            prg_bank_selector: 0,
+            prg_banks,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(super) fn prg_banks(&self) -> u8 {
+        self.prg_banks
+    }
+
+    pub(super) fn select(&mut self, bank: u8) {
+        self.prg_bank_selector = bank;
+    }
+
+    pub(super) fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget {
         match addr {
             0x8000..=0xBFFF => CartridgeReadTarget::PrgRom(
                 (self.prg_bank_selector as usize) * 0x4000 + (addr & 0x3FFF) as usize,
@@ -28,12 +53,31 @@ impl Mapper for Mapper002 {
         }
     }
 
+    #[cfg(feature = "debugger")]
+    pub(super) fn get_prg_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xBFFF => Some(self.prg_bank_selector),
+            0xC000..=0xFFFF => Some(self.prg_banks - 1),
+            _ => None,
+        }
+    }
+}
+
+impl Mapper for Mapper002 {
+    fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget {
+        self.prg.cpu_map_read(addr)
+    }
+
     fn cpu_map_write(&mut self, addr: u16, data: u8) {
         if addr >= 0x8000 {
-            self.prg_bank_selector = data;
+            self.prg.select(data);
         }
     }
 
+    fn has_register_at(&self, _addr: u16) -> bool {
+        true
+    }
+
     fn ppu_map_read(&mut self, addr: u16) -> usize {
         addr as usize
     }
@@ -50,11 +94,20 @@ impl Mapper for Mapper002 {
         None
     }
 
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg.prg_banks())
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
+        self.prg.get_prg_bank(addr)
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
         match addr {
-            0x8000..=0xBFFF => Some(self.prg_bank_selector),
-            0xC000..=0xFFFF => Some(self.prg_banks - 1),
+            0x0000..=0x1FFF => Some((addr / 0x400) as u8),
             _ => None,
         }
     }
@@ -1,5 +1,7 @@
 use super::{CartridgeReadTarget, Mapper, Mirroring};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper003 {
     chr_bank_selector: u8,
     prg_banks: u8,
@@ -27,6 +29,10 @@ impl Mapper for Mapper003 {
         self.chr_bank_selector = data & 0x03;
     }
 
+    fn has_register_at(&self, _addr: u16) -> bool {
+        true
+    }
+
     fn ppu_map_read(&mut self, addr: u16) -> usize {
         (self.chr_bank_selector as usize) * 0x2000 + (addr & 0x1fff) as usize
     }
@@ -43,11 +49,25 @@ impl Mapper for Mapper003 {
         None
     }
 
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg_banks)
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         match addr {
             0x8000..=0xBFFF => Some(0),
-            0xC000..=0xFFFF => Some(1),
+            // 16KB ROMs are mirrored
+            0xC000..=0xFFFF => Some(self.prg_banks - 1),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some(self.chr_bank_selector * 8 + (addr / 0x400) as u8),
             _ => None,
         }
     }
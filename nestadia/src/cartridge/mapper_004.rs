@@ -1,8 +1,7 @@
-use alloc::vec;
-use alloc::vec::Vec;
-
 use super::{CartridgeReadTarget, Mapper, Mirroring};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper004 {
     prg_banks: u8,
     prg_bank_selector: [u8; 4],
@@ -12,7 +11,8 @@ pub struct Mapper004 {
     chr_inverson: bool,
     register: [u8; 8],
     target_register: u8,
-    ram_data: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::state::byte_array"))]
+    ram_data: [u8; 0x2000],
 
     last_chr_bank_bit: bool, // Used to detect changed between sprites and background rendering for scanline counter
 
@@ -24,17 +24,27 @@ pub struct Mapper004 {
 }
 
 impl Mapper004 {
-    pub fn new(prg_banks: u8, mirroring: Mirroring) -> Self {
-        Self {
+    pub fn new(prg_banks: u8, mirroring: Mirroring, save_data: Option<&[u8]>) -> Self {
+        let mut ram_data = [0u8; 0x2000];
+
+        // Load the save data
+        if let Some(save_data) = save_data {
+            ram_data
+                .iter_mut()
+                .zip(save_data.iter())
+                .for_each(|(r, s)| *r = *s)
+        };
+
+        let mut mapper = Self {
             prg_banks,
-            prg_bank_selector: [0u8, 0u8, 0u8, prg_banks * 2 - 1],
+            prg_bank_selector: [0u8; 4],
             chr_bank_selector: [0u8; 8],
             mirroring,
             prg_mode: false,
             chr_inverson: false,
             register: [0u8; 8],
             target_register: 0,
-            ram_data: vec![0u8; 0x2000],
+            ram_data,
 
             last_chr_bank_bit: false,
 
@@ -43,6 +53,42 @@ impl Mapper004 {
             irq_reload: false,
             irq_counter: 0,
             irq_latch: 0,
+        };
+        mapper.update_bank_selectors();
+        mapper
+    }
+
+    fn update_bank_selectors(&mut self) {
+        if self.prg_mode {
+            self.prg_bank_selector[0] = self.prg_banks * 2 - 2;
+            self.prg_bank_selector[2] = self.register[6] & 0x3F;
+        } else {
+            self.prg_bank_selector[0] = self.register[6] & 0x3F;
+            self.prg_bank_selector[2] = self.prg_banks * 2 - 2;
+        }
+        self.prg_bank_selector[1] = self.register[7] & 0x3F;
+        self.prg_bank_selector[3] = self.prg_banks * 2 - 1;
+
+        // R0 and R1 select 2KB banks, ignoring their low bit
+        let chr_2k = [
+            self.register[0] & 0xFE,
+            self.register[0] | 0x01,
+            self.register[1] & 0xFE,
+            self.register[1] | 0x01,
+        ];
+        let chr_1k = [
+            self.register[2],
+            self.register[3],
+            self.register[4],
+            self.register[5],
+        ];
+
+        if self.chr_inverson {
+            self.chr_bank_selector[..4].copy_from_slice(&chr_1k);
+            self.chr_bank_selector[4..].copy_from_slice(&chr_2k);
+        } else {
+            self.chr_bank_selector[..4].copy_from_slice(&chr_2k);
+            self.chr_bank_selector[4..].copy_from_slice(&chr_1k);
         }
     }
 }
@@ -88,38 +134,10 @@ impl Mapper for Mapper004 {
                 } else {
                     // Bank data
                     self.register[self.target_register as usize] = data;
-
-                    // Update bank selectors
-                    if self.prg_mode {
-                        self.prg_bank_selector[0] = self.prg_banks * 2 - 2;
-                        self.prg_bank_selector[2] = self.register[6] & 0x3F;
-                    } else {
-                        self.prg_bank_selector[0] = self.register[6] & 0x3F;
-                        self.prg_bank_selector[2] = self.prg_banks * 2 - 2;
-                    }
-                    self.prg_bank_selector[1] = self.register[7] & 0x3F;
-                    self.prg_bank_selector[3] = self.prg_banks * 2 - 1;
-
-                    if self.chr_inverson {
-                        self.chr_bank_selector[0] = self.register[2];
-                        self.chr_bank_selector[1] = self.register[3];
-                        self.chr_bank_selector[2] = self.register[4];
-                        self.chr_bank_selector[3] = self.register[5];
-                        self.chr_bank_selector[4] = self.register[0] & 0xFE;
-                        self.chr_bank_selector[5] = self.register[0] + 1;
-                        self.chr_bank_selector[6] = self.register[1] & 0xFE;
-                        self.chr_bank_selector[7] = self.register[1] + 1;
-                    } else {
-                        self.chr_bank_selector[0] = self.register[0] & 0xFE;
-                        self.chr_bank_selector[1] = self.register[0] + 1;
-                        self.chr_bank_selector[2] = self.register[1] & 0xFE;
-                        self.chr_bank_selector[3] = self.register[1] + 1;
-                        self.chr_bank_selector[4] = self.register[2];
-                        self.chr_bank_selector[5] = self.register[3];
-                        self.chr_bank_selector[6] = self.register[4];
-                        self.chr_bank_selector[7] = self.register[5];
-                    }
                 }
+
+                // The modes apply right away, not on the next bank data write
+                self.update_bank_selectors();
             }
             0xA000..=0xBFFF => {
                 if (addr & 0x01) == 0 {
@@ -165,6 +183,10 @@ impl Mapper for Mapper004 {
         }
     }
 
+    fn has_register_at(&self, _addr: u16) -> bool {
+        true
+    }
+
     fn ppu_map_read(&mut self, addr: u16) -> usize {
         let chr_bank_bit = addr & 0x1000 == 0x1000;
         if !self.last_chr_bank_bit && chr_bank_bit {
@@ -230,14 +252,15 @@ impl Mapper for Mapper004 {
         self.irq_active
     }
 
-    fn irq_clear(&mut self) {
-        self.irq_active = false;
-    }
-
     fn get_sram(&self) -> Option<&[u8]> {
         Some(&self.ram_data)
     }
 
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg_banks)
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         match addr {
@@ -249,4 +272,87 @@ impl Mapper for Mapper004 {
             0xE000..=0xFFFF => Some(self.prg_bank_selector[3] / 2),
         }
     }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some(self.chr_bank_selector[(addr / 0x400) as usize]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock_scanline(mapper: &mut Mapper004) {
+        // A rising edge on A12 clocks the scanline counter
+        mapper.ppu_map_read(0x0000);
+        mapper.ppu_map_read(0x1000);
+    }
+
+    fn prg_bank(mapper: &Mapper004, addr: u16) -> usize {
+        match mapper.cpu_map_read(addr) {
+            CartridgeReadTarget::PrgRom(rom_addr) => rom_addr / 0x2000,
+            CartridgeReadTarget::PrgRam(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn bank_modes_apply_immediately() {
+        let mut mapper = Mapper004::new(4, Mirroring::Vertical, None);
+        assert_eq!(
+            [0x8000, 0xA000, 0xC000, 0xE000].map(|addr| prg_bank(&mapper, addr)),
+            [0, 0, 6, 7]
+        );
+
+        mapper.cpu_map_write(0x8000, 0x06);
+        mapper.cpu_map_write(0x8001, 0x02);
+        assert_eq!(prg_bank(&mapper, 0x8000), 2);
+        assert_eq!(prg_bank(&mapper, 0xC000), 6);
+
+        // Swapping the PRG mode doesn't wait for a bank data write
+        mapper.cpu_map_write(0x8000, 0x40);
+        assert_eq!(prg_bank(&mapper, 0x8000), 6);
+        assert_eq!(prg_bank(&mapper, 0xC000), 2);
+
+        // 2KB CHR banks ignore the low bit of R0
+        mapper.cpu_map_write(0x8000, 0x00);
+        mapper.cpu_map_write(0x8001, 0x05);
+        mapper.cpu_map_write(0x8000, 0x02);
+        mapper.cpu_map_write(0x8001, 0x09);
+        assert_eq!(mapper.ppu_map_read(0x0000), 4 * 0x400);
+        assert_eq!(mapper.ppu_map_read(0x0400), 5 * 0x400);
+        assert_eq!(mapper.ppu_map_read(0x1000), 9 * 0x400);
+
+        mapper.cpu_map_write(0x8000, 0x80);
+        assert_eq!(mapper.ppu_map_read(0x0000), 9 * 0x400);
+        assert_eq!(mapper.ppu_map_read(0x1000), 4 * 0x400);
+        assert_eq!(mapper.ppu_map_read(0x1400), 5 * 0x400);
+    }
+
+    #[test]
+    fn irq_stays_asserted_until_acknowledged() {
+        let mut mapper = Mapper004::new(2, Mirroring::Vertical, None);
+
+        mapper.cpu_map_write(0xC000, 2); // IRQ latch
+        mapper.cpu_map_write(0xC001, 0); // IRQ reload
+        mapper.cpu_map_write(0xE001, 0); // IRQ enable
+
+        clock_scanline(&mut mapper); // Reload to 2
+        clock_scanline(&mut mapper); // 1
+        assert!(!mapper.irq_state());
+        clock_scanline(&mut mapper); // 0
+        assert!(mapper.irq_state());
+
+        // Polling the line must not acknowledge it
+        assert!(mapper.irq_state());
+        clock_scanline(&mut mapper);
+        assert!(mapper.irq_state());
+
+        // Writing to $E000 acknowledges the IRQ
+        mapper.cpu_map_write(0xE000, 0);
+        assert!(!mapper.irq_state());
+    }
 }
@@ -1,15 +1,18 @@
 use super::{CartridgeReadTarget, Mapper, Mirroring};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper007 {
     prg_bank_selector: u8,
     mirroring: Mirroring,
 }
 
 impl Mapper007 {
+    // Same as a write of 0 to the bank register
     pub fn new() -> Self {
         Self {
             prg_bank_selector: 0,
-            mirroring: Mirroring::OneScreenUpper,
+            mirroring: Mirroring::OneScreenLower,
         }
     }
 }
@@ -32,6 +35,10 @@ impl Mapper for Mapper007 {
         };
     }
 
+    fn has_register_at(&self, _addr: u16) -> bool {
+        true
+    }
+
     fn ppu_map_read(&mut self, addr: u16) -> usize {
         (addr & 0x1fff) as usize
     }
@@ -48,6 +55,10 @@ impl Mapper for Mapper007 {
         None
     }
 
+    fn power_on(&mut self) {
+        *self = Self::new();
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         match addr {
@@ -55,4 +66,12 @@ impl Mapper for Mapper007 {
             _ => None,
         }
     }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some((addr / 0x400) as u8),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,207 @@
+use super::{CartridgeReadTarget, Mapper, Mirroring};
+
+/// CHR banking shared by MMC2 and MMC4.
+/// Each 4KB half of the pattern tables has two banks, and the one in use is selected by a latch
+/// that flips when the PPU fetches the tiles $FD or $FE.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChrLatches {
+    banks: [[u8; 2]; 2], // Indexed by [half][latch], where the latch is 0 for $FD and 1 for $FE
+    latches: [usize; 2],
+    single_trigger_lo: bool, // MMC2 only triggers the lower latch on the first row of the tile
+}
+
+impl ChrLatches {
+    pub fn new(single_trigger_lo: bool) -> Self {
+        Self {
+            banks: [[0u8; 2]; 2],
+            latches: [1, 1],
+            single_trigger_lo,
+        }
+    }
+
+    pub fn set_bank(&mut self, half: usize, latch: usize, data: u8) {
+        self.banks[half][latch] = data & 0x1F;
+    }
+
+    /// 1KB bank currently mapped at this address, without flipping the latches
+    #[cfg(feature = "debugger")]
+    pub fn bank(&self, addr: u16) -> u8 {
+        let half = ((addr >> 12) & 1) as usize;
+        self.banks[half][self.latches[half]] * 4 + ((addr & 0x0FFF) / 0x400) as u8
+    }
+
+    /// Map the address to the CHR memory, then update the latches since they flip after the fetch
+    pub fn read(&mut self, addr: u16) -> usize {
+        let half = ((addr >> 12) & 1) as usize;
+        let mapped =
+            (self.banks[half][self.latches[half]] as usize) * 0x1000 + (addr & 0x0FFF) as usize;
+
+        let tile_addr = if half == 0 && self.single_trigger_lo {
+            addr & 0x1FFF
+        } else {
+            addr & 0x1FF8
+        };
+
+        match tile_addr {
+            0x0FD8 | 0x1FD8 => self.latches[half] = 0,
+            0x0FE8 | 0x1FE8 => self.latches[half] = 1,
+            _ => {}
+        }
+
+        mapped
+    }
+}
+
+/// Mirroring register of MMC2 and MMC4
+pub fn mirroring_from_register(data: u8) -> Mirroring {
+    if data & 0x01 == 0x01 {
+        Mirroring::Horizontal
+    } else {
+        Mirroring::Vertical
+    }
+}
+
+/// MMC2, used by Punch-Out!!
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper009 {
+    prg_banks: u8, // Number of 8KB banks
+    prg_bank_selector: u8,
+    chr_latches: ChrLatches,
+    mirroring: Mirroring,
+}
+
+impl Mapper009 {
+    pub fn new(prg_banks: u8, mirroring: Mirroring) -> Self {
+        Self {
+            prg_banks: prg_banks * 2,
+            prg_bank_selector: 0,
+            chr_latches: ChrLatches::new(true),
+            mirroring,
+        }
+    }
+}
+
+impl Mapper for Mapper009 {
+    fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget {
+        let bank = match addr {
+            0x8000..=0x9FFF => self.prg_bank_selector as usize,
+            0xA000..=0xBFFF => (self.prg_banks as usize).saturating_sub(3),
+            0xC000..=0xDFFF => (self.prg_banks as usize).saturating_sub(2),
+            0xE000..=0xFFFF => self.prg_banks as usize - 1,
+            _ => {
+                log::warn!("Attempted to read address w/o known mapping {:#06x}", addr);
+                return CartridgeReadTarget::PrgRom(0);
+            }
+        };
+
+        CartridgeReadTarget::PrgRom(bank * 0x2000 + (addr & 0x1FFF) as usize)
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0xA000..=0xAFFF => self.prg_bank_selector = data & 0x0F,
+            0xB000..=0xBFFF => self.chr_latches.set_bank(0, 0, data),
+            0xC000..=0xCFFF => self.chr_latches.set_bank(0, 1, data),
+            0xD000..=0xDFFF => self.chr_latches.set_bank(1, 0, data),
+            0xE000..=0xEFFF => self.chr_latches.set_bank(1, 1, data),
+            0xF000..=0xFFFF => self.mirroring = mirroring_from_register(data),
+            _ => {}
+        }
+    }
+
+    fn has_register_at(&self, addr: u16) -> bool {
+        addr >= 0xA000
+    }
+
+    fn ppu_map_read(&mut self, addr: u16) -> usize {
+        self.chr_latches.read(addr)
+    }
+
+    fn ppu_map_write(&self, _addr: u16) -> Option<usize> {
+        None
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_sram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg_banks)
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_prg_bank(&self, addr: u16) -> Option<u8> {
+        // The banks are 8KB, so divide by 2 to get 16KB bank
+        match addr {
+            0x8000..=0x9FFF => Some(self.prg_bank_selector / 2),
+            0xA000..=0xBFFF => Some(self.prg_banks.saturating_sub(3) / 2),
+            0xC000..=0xDFFF => Some(self.prg_banks.saturating_sub(2) / 2),
+            0xE000..=0xFFFF => Some((self.prg_banks - 1) / 2),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some(self.chr_latches.bank(addr)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chr_latch_switch() {
+        let mut mapper = Mapper009::new(8, Mirroring::Vertical);
+        mapper.cpu_map_write(0xB000, 2); // $0000, $FD
+        mapper.cpu_map_write(0xC000, 3); // $0000, $FE
+        mapper.cpu_map_write(0xD000, 4); // $1000, $FD
+        mapper.cpu_map_write(0xE000, 5); // $1000, $FE
+
+        // Latches start on $FE
+        assert_eq!(mapper.ppu_map_read(0x0010), 3 * 0x1000 + 0x10);
+        assert_eq!(mapper.ppu_map_read(0x1010), 5 * 0x1000 + 0x10);
+
+        // The fetch that triggers the latch still uses the previous bank
+        assert_eq!(mapper.ppu_map_read(0x0FD8), 3 * 0x1000 + 0xFD8);
+        assert_eq!(mapper.ppu_map_read(0x0010), 2 * 0x1000 + 0x10);
+        assert_eq!(mapper.ppu_map_read(0x1010), 5 * 0x1000 + 0x10);
+
+        // Other rows of the tile don't trigger the lower latch
+        mapper.ppu_map_read(0x0FE9);
+        assert_eq!(mapper.ppu_map_read(0x0010), 2 * 0x1000 + 0x10);
+        mapper.ppu_map_read(0x0FE8);
+        assert_eq!(mapper.ppu_map_read(0x0010), 3 * 0x1000 + 0x10);
+
+        // But any row triggers the upper one
+        mapper.ppu_map_read(0x1FDD);
+        assert_eq!(mapper.ppu_map_read(0x1010), 4 * 0x1000 + 0x10);
+        mapper.ppu_map_read(0x1FEF);
+        assert_eq!(mapper.ppu_map_read(0x1010), 5 * 0x1000 + 0x10);
+    }
+
+    #[test]
+    fn prg_banks() {
+        let mut mapper = Mapper009::new(8, Mirroring::Vertical);
+        mapper.cpu_map_write(0xA000, 5);
+
+        let prg_rom_addr = |addr| match mapper.cpu_map_read(addr) {
+            CartridgeReadTarget::PrgRom(addr) => addr,
+            CartridgeReadTarget::PrgRam(_) => panic!("unexpected PRG RAM read"),
+        };
+        assert_eq!(prg_rom_addr(0x8001), 5 * 0x2000 + 1);
+        assert_eq!(prg_rom_addr(0xA001), 13 * 0x2000 + 1);
+        assert_eq!(prg_rom_addr(0xFFFF), 16 * 0x2000 - 1);
+    }
+}
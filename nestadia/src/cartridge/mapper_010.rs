@@ -0,0 +1,127 @@
+use super::mapper_009::{mirroring_from_register, ChrLatches};
+use super::{CartridgeReadTarget, Mapper, Mirroring};
+
+/// MMC4, which is MMC2 with 16KB PRG banks and PRG RAM. Used by Fire Emblem.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper010 {
+    prg_banks: u8,
+    prg_bank_selector: u8,
+    chr_latches: ChrLatches,
+    #[cfg_attr(feature = "serde", serde(with = "crate::state::byte_array"))]
+    ram_data: [u8; 0x2000],
+    mirroring: Mirroring,
+}
+
+impl Mapper010 {
+    pub fn new(prg_banks: u8, mirroring: Mirroring, save_data: Option<&[u8]>) -> Self {
+        let mut ram_data = [0u8; 0x2000];
+
+        // Load the save data
+        if let Some(save_data) = save_data {
+            ram_data
+                .iter_mut()
+                .zip(save_data.iter())
+                .for_each(|(r, s)| *r = *s)
+        };
+
+        Self {
+            prg_banks,
+            prg_bank_selector: 0,
+            chr_latches: ChrLatches::new(false),
+            ram_data,
+            mirroring,
+        }
+    }
+}
+
+impl Mapper for Mapper010 {
+    fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget {
+        match addr {
+            0x6000..=0x7FFF => CartridgeReadTarget::PrgRam(self.ram_data[(addr & 0x1FFF) as usize]),
+            0x8000..=0xBFFF => CartridgeReadTarget::PrgRom(
+                (self.prg_bank_selector as usize) * 0x4000 + (addr & 0x3FFF) as usize,
+            ),
+            0xC000..=0xFFFF => CartridgeReadTarget::PrgRom(
+                (self.prg_banks as usize - 1) * 0x4000 + (addr & 0x3FFF) as usize,
+            ),
+            _ => {
+                log::warn!("Attempted to read address w/o known mapping {:#06x}", addr);
+                CartridgeReadTarget::PrgRom(0)
+            }
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => self.ram_data[(addr & 0x1FFF) as usize] = data,
+            0xA000..=0xAFFF => self.prg_bank_selector = data & 0x0F,
+            0xB000..=0xBFFF => self.chr_latches.set_bank(0, 0, data),
+            0xC000..=0xCFFF => self.chr_latches.set_bank(0, 1, data),
+            0xD000..=0xDFFF => self.chr_latches.set_bank(1, 0, data),
+            0xE000..=0xEFFF => self.chr_latches.set_bank(1, 1, data),
+            0xF000..=0xFFFF => self.mirroring = mirroring_from_register(data),
+            _ => {}
+        }
+    }
+
+    fn has_register_at(&self, addr: u16) -> bool {
+        addr >= 0xA000
+    }
+
+    fn ppu_map_read(&mut self, addr: u16) -> usize {
+        self.chr_latches.read(addr)
+    }
+
+    fn ppu_map_write(&self, _addr: u16) -> Option<usize> {
+        None
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_sram(&self) -> Option<&[u8]> {
+        Some(&self.ram_data)
+    }
+
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg_banks)
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_prg_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xBFFF => Some(self.prg_bank_selector),
+            0xC000..=0xFFFF => Some(self.prg_banks - 1),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some(self.chr_latches.bank(addr)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chr_latch_switch() {
+        let mut mapper = Mapper010::new(8, Mirroring::Vertical, None);
+        mapper.cpu_map_write(0xB000, 2);
+        mapper.cpu_map_write(0xC000, 3);
+
+        // Unlike MMC2, any row of the tile triggers the lower latch
+        mapper.ppu_map_read(0x0FDB);
+        assert_eq!(mapper.ppu_map_read(0x0010), 2 * 0x1000 + 0x10);
+        mapper.ppu_map_read(0x0FEF);
+        assert_eq!(mapper.ppu_map_read(0x0010), 3 * 0x1000 + 0x10);
+    }
+}
@@ -1,5 +1,7 @@
 use super::{CartridgeReadTarget, Mapper, Mirroring};
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Mapper066 {
     prg_bank_selector: u8,
     chr_bank_selector: u8,
@@ -28,6 +30,10 @@ impl Mapper for Mapper066 {
         self.prg_bank_selector = (data & 0x30) >> 4;
     }
 
+    fn has_register_at(&self, _addr: u16) -> bool {
+        true
+    }
+
     fn ppu_map_read(&mut self, addr: u16) -> usize {
         (self.chr_bank_selector as usize) * 0x2000 + addr as usize
     }
@@ -44,6 +50,11 @@ impl Mapper for Mapper066 {
         None
     }
 
+    fn power_on(&mut self) {
+        self.prg_bank_selector = 0;
+        self.chr_bank_selector = 0;
+    }
+
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         match addr {
@@ -51,4 +62,12 @@ impl Mapper for Mapper066 {
             _ => None,
         }
     }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some(self.chr_bank_selector * 8 + (addr / 0x400) as u8),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,120 @@
+use super::mapper_002::UxRomPrg;
+use super::{CartridgeReadTarget, Mapper, Mirroring};
+
+/// Codemasters boards. This is UxROM with the bank register at 0xC000-0xFFFF,
+/// and a one-screen mirroring control at 0x9000-0x9FFF used by Fire Hawk.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mapper071 {
+    prg: UxRomPrg,
+    mirroring: Mirroring,
+}
+
+impl Mapper071 {
+    pub fn new(prg_banks: u8, mirroring: Mirroring) -> Self {
+        Self {
+            prg: UxRomPrg::new(prg_banks),
+            mirroring,
+        }
+    }
+}
+
+impl Mapper for Mapper071 {
+    fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget {
+        self.prg.cpu_map_read(addr)
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x9000..=0x9FFF => {
+                // Only Fire Hawk writes here, other boards have hardwired mirroring
+                self.mirroring = if data & 0x10 == 0x10 {
+                    Mirroring::OneScreenUpper
+                } else {
+                    Mirroring::OneScreenLower
+                };
+            }
+            0xC000..=0xFFFF => {
+                self.prg.select(data & 0x0F);
+            }
+            _ => {}
+        }
+    }
+
+    fn has_register_at(&self, addr: u16) -> bool {
+        (0x9000..=0x9FFF).contains(&addr) || addr >= 0xC000
+    }
+
+    fn ppu_map_read(&mut self, addr: u16) -> usize {
+        addr as usize
+    }
+
+    fn ppu_map_write(&self, addr: u16) -> Option<usize> {
+        Some(addr as usize)
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_sram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        Some(self.prg.prg_banks())
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_prg_bank(&self, addr: u16) -> Option<u8> {
+        self.prg.get_prg_bank(addr)
+    }
+
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0x0000..=0x1FFF => Some((addr / 0x400) as u8),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prg_rom_addr(mapper: &Mapper071, addr: u16) -> usize {
+        match mapper.cpu_map_read(addr) {
+            CartridgeReadTarget::PrgRom(addr) => addr,
+            CartridgeReadTarget::PrgRam(_) => panic!("unexpected PRG RAM read"),
+        }
+    }
+
+    #[test]
+    fn prg_bank_switch() {
+        let mut mapper = Mapper071::new(8, Mirroring::Vertical);
+        assert_eq!(prg_rom_addr(&mapper, 0x8000), 0);
+        assert_eq!(prg_rom_addr(&mapper, 0xC000), 7 * 0x4000);
+
+        mapper.cpu_map_write(0xC000, 3);
+        assert_eq!(prg_rom_addr(&mapper, 0x8123), 3 * 0x4000 + 0x123);
+        assert_eq!(prg_rom_addr(&mapper, 0xC000), 7 * 0x4000);
+
+        // Not the bank register
+        mapper.cpu_map_write(0x8000, 5);
+        assert_eq!(prg_rom_addr(&mapper, 0x8123), 3 * 0x4000 + 0x123);
+        assert!(matches!(mapper.mirroring(), Mirroring::Vertical));
+    }
+
+    #[test]
+    fn fire_hawk_mirroring() {
+        let mut mapper = Mapper071::new(8, Mirroring::Vertical);
+
+        mapper.cpu_map_write(0x9000, 0x10);
+        assert!(matches!(mapper.mirroring(), Mirroring::OneScreenUpper));
+
+        mapper.cpu_map_write(0x9000, 0x00);
+        assert!(matches!(mapper.mirroring(), Mirroring::OneScreenLower));
+    }
+}
@@ -1,3 +1,4 @@
+mod hash;
 mod ines_header;
 mod mapper_000;
 mod mapper_001;
@@ -5,23 +6,83 @@ mod mapper_002;
 mod mapper_003;
 mod mapper_004;
 mod mapper_007;
+mod mapper_009;
+mod mapper_010;
 mod mapper_066;
+mod mapper_071;
+mod patch;
 
 use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::convert::TryFrom as _;
 
-use self::ines_header::{Flags6, INesHeader};
+#[cfg(feature = "serde")]
+use crate::state::StateError;
+
 use self::mapper_000::Mapper000;
 use self::mapper_001::Mapper001;
 use self::mapper_002::Mapper002;
 use self::mapper_003::Mapper003;
 use self::mapper_004::Mapper004;
 use self::mapper_007::Mapper007;
+use self::mapper_009::Mapper009;
+use self::mapper_010::Mapper010;
 use self::mapper_066::Mapper066;
+use self::mapper_071::Mapper071;
+
+pub use self::ines_header::{CartridgeHeader, HeaderFormat};
+pub use self::patch::PatchError;
+
+type MapperConstructor = fn(u8, Mirroring, Option<&[u8]>) -> Box<dyn Mapper>;
+
+// Constructors of the supported mappers, from the PRG bank count, the header mirroring and the save data
+const MAPPERS: [(u16, MapperConstructor); 10] = [
+    (0, |prg_banks, mirroring, _| {
+        Box::new(Mapper000::new(prg_banks, mirroring))
+    }),
+    (1, |prg_banks, mirroring, save_data| {
+        Box::new(Mapper001::new(prg_banks, mirroring, save_data))
+    }),
+    (2, |prg_banks, mirroring, _| {
+        Box::new(Mapper002::new(prg_banks, mirroring))
+    }),
+    (3, |prg_banks, mirroring, _| {
+        Box::new(Mapper003::new(prg_banks, mirroring))
+    }),
+    (4, |prg_banks, mirroring, save_data| {
+        Box::new(Mapper004::new(prg_banks, mirroring, save_data))
+    }),
+    (7, |_, _, _| Box::new(Mapper007::new())),
+    (9, |prg_banks, mirroring, _| {
+        Box::new(Mapper009::new(prg_banks, mirroring))
+    }),
+    (10, |prg_banks, mirroring, save_data| {
+        Box::new(Mapper010::new(prg_banks, mirroring, save_data))
+    }),
+    (66, |_, mirroring, _| Box::new(Mapper066::new(mirroring))),
+    (71, |prg_banks, mirroring, _| {
+        Box::new(Mapper071::new(prg_banks, mirroring))
+    }),
+];
+
+/// Mapper numbers that can be loaded by `Cartridge::load`
+pub const SUPPORTED_MAPPERS: &[u16] = &{
+    let mut ids = [0u16; MAPPERS.len()];
+    let mut i = 0;
+    while i < MAPPERS.len() {
+        ids[i] = MAPPERS[i].0;
+        i += 1;
+    }
+    ids
+};
+
+pub fn is_mapper_supported(mapper_id: u16) -> bool {
+    SUPPORTED_MAPPERS.contains(&mapper_id)
+}
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mirroring {
     Horizontal,
     Vertical,
@@ -30,16 +91,54 @@ pub enum Mirroring {
     OneScreenUpper,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RomParserError {
+    /// The file is shorter than the 16-byte iNES header
     TooShort,
-    InvalidMagicBytes,
-    MapperNotImplemented,
+    /// The file doesn't start with `NES<EOF>`
+    InvalidMagic,
+    /// The mapper number from the header, which isn't in `SUPPORTED_MAPPERS`
+    UnsupportedMapper(u16),
+    /// The file ends before the PRG ROM size declared in the header
+    TruncatedPrgRom,
+    /// The file ends before the CHR ROM size declared in the header
+    TruncatedChrRom,
+    /// The ROM sizes declared in the header are too large to be addressed
+    RomTooLarge,
+    /// The IPS or BPS patch couldn't be applied
+    InvalidPatch(PatchError),
+}
+
+/// Diagnostic events raised by the cartridge, to help triage compatibility issues
+#[cfg(feature = "debugger")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// Write to $6000-$7FFF on a cartridge without PRG-RAM
+    MissingPrgRamWrite { addr: u16, data: u8 },
+    /// Write to $8000-$FFFF that doesn't reach a mapper register, like self-modifying code
+    PrgRomWrite { addr: u16, data: u8 },
 }
 
+#[cfg(feature = "debugger")]
+const MAX_TRACE_EVENTS: usize = 1024;
+
 impl core::fmt::Display for RomParserError {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        write!(f, "{:?}", &self)
+        match self {
+            RomParserError::TooShort => write!(f, "the file is too short to be an iNES ROM"),
+            RomParserError::InvalidMagic => {
+                write!(f, "the file is not an iNES ROM (invalid magic bytes)")
+            }
+            RomParserError::UnsupportedMapper(id) => write!(f, "mapper {} is not supported", id),
+            RomParserError::TruncatedPrgRom => {
+                write!(f, "the PRG ROM is shorter than declared in the header")
+            }
+            RomParserError::TruncatedChrRom => {
+                write!(f, "the CHR ROM is shorter than declared in the header")
+            }
+            RomParserError::RomTooLarge => write!(f, "the ROM is too large to be loaded"),
+            RomParserError::InvalidPatch(e) => write!(f, "invalid patch: {}", e),
+        }
     }
 }
 
@@ -48,7 +147,7 @@ enum CartridgeReadTarget {
     PrgRom(usize),
 }
 
-trait Mapper: Send + Sync {
+trait Mapper: MapperClone + MapperState + Send + Sync {
     fn cpu_map_read(&self, addr: u16) -> CartridgeReadTarget;
     fn cpu_map_write(&mut self, addr: u16, data: u8);
     fn ppu_map_read(&mut self, addr: u16) -> usize; // This is mutable because of side effects on some mapper that serves as a scanline counter
@@ -56,20 +155,110 @@ trait Mapper: Send + Sync {
     fn mirroring(&self) -> Mirroring;
     fn get_sram(&self) -> Option<&[u8]>;
 
+    fn has_prg_ram(&self) -> bool {
+        self.get_sram().is_some()
+    }
+
+    // Whether a write at this $8000-$FFFF address reaches a mapper register
+    fn has_register_at(&self, _addr: u16) -> bool {
+        false
+    }
+
+    // IRQ line of the mapper. This is level-triggered: it stays asserted until the game
+    // acknowledges it through the mapper's registers.
     fn irq_state(&self) -> bool {
         false
     }
-    fn irq_clear(&mut self) {}
+
+    // Number of PRG banks of the ROM, for the mappers that store it. It comes from the ROM, so a save state
+    // of the same cartridge can't change it.
+    #[cfg(feature = "serde")]
+    fn prg_banks(&self) -> Option<u8> {
+        None
+    }
+
+    // Put the bank registers back in their power-on state, keeping the PRG RAM.
+    // Only needed by the mappers that can switch out the bank holding the vectors.
+    fn power_on(&mut self) {}
 
     #[cfg(feature = "debugger")]
     fn get_prg_bank(&self, addr: u16) -> Option<u8>;
+
+    // 1KB CHR bank mapped at this $0000-$1FFF address
+    #[cfg(feature = "debugger")]
+    fn get_chr_bank(&self, addr: u16) -> Option<u8>;
 }
 
+// Allows cloning the mappers behind a `Box<dyn Mapper>`
+trait MapperClone {
+    fn box_clone(&self) -> Box<dyn Mapper>;
+}
+
+impl<T: 'static + Mapper + Clone> MapperClone for T {
+    fn box_clone(&self) -> Box<dyn Mapper> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Mapper> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+// Allows saving the state of the mappers behind a `Box<dyn Mapper>`
+#[cfg(feature = "serde")]
+trait MapperState {
+    fn save_state(&self) -> Vec<u8>;
+    fn load_state(&mut self, data: &[u8]) -> Result<(), StateError>;
+}
+
+#[cfg(feature = "serde")]
+impl<T: Mapper + serde::Serialize + serde::de::DeserializeOwned> MapperState for T {
+    fn save_state(&self) -> Vec<u8> {
+        crate::state::encode(self)
+    }
+
+    fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let state: T = crate::state::decode(data)?;
+        if state.prg_banks() != self.prg_banks() {
+            return Err(StateError::Corrupt);
+        }
+
+        *self = state;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+trait MapperState {}
+
+#[cfg(not(feature = "serde"))]
+impl<T> MapperState for T {}
+
+/// State of the cartridge hardware. The ROMs are left out, since they are loaded with the cartridge
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct CartridgeState {
+    mapper: Vec<u8>, // Bank registers and PRG RAM
+    chr_ram: Option<Vec<u8>>,
+    save_dirty: bool,
+}
+
+#[derive(Clone)]
 pub struct Cartridge {
     chr_ram: bool,
     prg_memory: Vec<u8>, // program ROM, used by CPU
     chr_memory: Vec<u8>, // character ROM, used by PPU
     mapper: Box<dyn Mapper>,
+    rom_crc32: u32,
+    rom_sha1: [u8; 20],
+    header: CartridgeHeader,
+    battery: bool,
+    save_dirty: bool, // The battery-backed RAM was written since the cartridge was loaded
+
+    #[cfg(feature = "debugger")]
+    trace_events: Vec<TraceEvent>,
 }
 
 impl Cartridge {
@@ -77,72 +266,93 @@ impl Cartridge {
         const PRG_BANK_SIZE: usize = 16384;
         const CHR_BANK_SIZE: usize = 8192;
 
-        let header: INesHeader = INesHeader::try_from(rom)?;
+        let header = CartridgeHeader::try_from(rom)?;
 
         log::info!("ROM info: {:?}", &header);
 
-        let mirroring = if header.flags6.contains(Flags6::FOUR_SCREEN) {
-            Mirroring::FourScreen
-        } else if header.flags6.contains(Flags6::MIRRORING) {
-            Mirroring::Vertical
-        } else {
-            Mirroring::Horizontal
-        };
+        // NES 2.0 headers can declare sizes that aren't a multiple of the bank size, which are mirrored in the last bank
+        let prg_banks = u8::try_from(header.prg_rom_size.div_ceil(PRG_BANK_SIZE))
+            .map_err(|_| RomParserError::RomTooLarge)?;
+        let mirroring = header.mirroring;
 
-        let mapper: Box<dyn Mapper> = match header.mapper_id {
-            0 => Box::new(Mapper000::new(header.prg_size, mirroring)),
-            1 => Box::new(Mapper001::new(header.prg_size, mirroring, save_data)),
-            2 => Box::new(Mapper002::new(header.prg_size, mirroring)),
-            3 => Box::new(Mapper003::new(header.prg_size, mirroring)),
-            4 => Box::new(Mapper004::new(header.prg_size, mirroring)),
-            7 => Box::new(Mapper007::new()),
-            66 => Box::new(Mapper066::new(mirroring)),
-            _ => return Err(RomParserError::MapperNotImplemented),
-        };
+        let constructor = MAPPERS
+            .iter()
+            .find(|&&(id, _)| id == header.mapper_id)
+            .map(|&(_, constructor)| constructor)
+            .ok_or(RomParserError::UnsupportedMapper(header.mapper_id))?;
+        let mapper = constructor(prg_banks, mirroring, save_data);
 
-        let chr_memory_len = CHR_BANK_SIZE * (header.chr_size as usize);
-        let prg_memory_len = PRG_BANK_SIZE * (header.prg_size as usize);
+        let chr_memory_len = header.chr_rom_size;
+        let prg_memory_len = header.prg_rom_size;
 
-        let prg_start = if header.flags6.contains(Flags6::TRAINER) {
-            512 + 16
-        } else {
-            16
-        };
+        let prg_start = if header.trainer { 512 + 16 } else { 16 };
+
+        let prg_end = prg_start + prg_memory_len;
+        if rom.len() < prg_end {
+            log::error!(
+                "Invalid ROM size: expected {} bytes of PRG memory, but ROM has {}",
+                prg_memory_len,
+                rom.len().saturating_sub(prg_start)
+            );
+            return Err(RomParserError::TruncatedPrgRom);
+        }
 
-        let expected_rom_size = prg_start + prg_memory_len + chr_memory_len;
-        if rom.len() < expected_rom_size {
+        if rom.len() < prg_end + chr_memory_len {
             log::error!(
-                "Invalid ROM size: expected {} bytes of memory, but ROM has {}",
-                expected_rom_size,
-                rom.len()
+                "Invalid ROM size: expected {} bytes of CHR memory, but ROM has {}",
+                chr_memory_len,
+                rom.len() - prg_end
             );
-            return Err(RomParserError::TooShort);
+            return Err(RomParserError::TruncatedChrRom);
         }
 
         // PRG memory
-        let prg_end = prg_start + prg_memory_len;
         let prg_memory = rom[prg_start..prg_end].to_vec();
         assert_eq!(prg_memory.len(), prg_memory_len);
 
         // CHR memory
-        // Don't parse if it's RAM
-        let chr_ram = header.chr_size == 0;
+        // Don't parse if it's RAM. ROMs that don't have CHR ROM always get at least 8KB of CHR RAM, whatever the mapper
+        // and the size in a NES 2.0 header, since that's how real-world ROMs are meant to be interpreted.
+        let chr_ram = header.chr_rom_size == 0;
         let chr_memory = if !chr_ram {
             let chr_start = prg_end;
             let chr_end = prg_end + chr_memory_len;
             rom[chr_start..chr_end].to_vec()
         } else {
-            vec![0u8; CHR_BANK_SIZE]
+            vec![0u8; (header.chr_ram_size + header.chr_nvram_size).max(CHR_BANK_SIZE)]
         };
 
+        // Databases identify the ROMs by the hash of their PRG and CHR data, without the header
+        let rom_data = &rom[prg_start..prg_end + chr_memory_len];
+        let rom_crc32 = hash::crc32(rom_data);
+        let rom_sha1 = hash::sha1(rom_data);
+
         Ok(Cartridge {
             chr_ram,
             prg_memory,
             chr_memory,
             mapper,
+            rom_crc32,
+            rom_sha1,
+            battery: header.battery,
+            header,
+            save_dirty: false,
+
+            #[cfg(feature = "debugger")]
+            trace_events: Vec::new(),
         })
     }
 
+    /// Same as `load`, but the IPS or BPS `patch` is applied to the ROM file first
+    pub fn load_with_patch(
+        rom: &[u8],
+        patch: &[u8],
+        save_data: Option<&[u8]>,
+    ) -> Result<Self, RomParserError> {
+        let rom = patch::apply_patch(rom, patch).map_err(RomParserError::InvalidPatch)?;
+        Self::load(&rom, save_data)
+    }
+
     pub fn mirroring(&self) -> Mirroring {
         self.mapper.mirroring()
     }
@@ -157,9 +367,73 @@ impl Cartridge {
     }
 
     pub fn write_prg_mem(&mut self, addr: u16, data: u8) {
+        if (0x6000..=0x7FFF).contains(&addr) && !self.mapper.has_prg_ram() {
+            // Nothing is there to receive the write, so don't let the mapper see it as a register write
+            log::debug!(
+                "attempted to write {:#X} at {:#X}, but this cartridge has no PRG RAM",
+                data,
+                addr
+            );
+
+            #[cfg(feature = "debugger")]
+            self.push_trace_event(TraceEvent::MissingPrgRamWrite { addr, data });
+
+            return;
+        }
+
+        if (0x6000..=0x7FFF).contains(&addr) && self.battery {
+            self.save_dirty = true;
+        }
+
+        if addr >= 0x8000 && !self.mapper.has_register_at(addr) {
+            // The ROM can't be written, so this is most likely a bug in the game
+            log::debug!(
+                "attempted to write {:#X} at {:#X}, but this is PRG ROM",
+                data,
+                addr
+            );
+
+            #[cfg(feature = "debugger")]
+            self.push_trace_event(TraceEvent::PrgRomWrite { addr, data });
+
+            return;
+        }
+
         self.mapper.cpu_map_write(addr, data);
     }
 
+    /// Write a patched byte in the PRG space. Unlike `write_prg_mem`, this never reaches the mapper
+    /// registers: only the PRG RAM can be patched. Returns whether the byte was applied.
+    pub fn patch_prg_mem(&mut self, addr: u16, data: u8) -> bool {
+        if (0x6000..=0x7FFF).contains(&addr) && self.mapper.has_prg_ram() {
+            self.write_prg_mem(addr, data);
+            true
+        } else {
+            log::warn!(
+                "ignored patch of {:#X} at {:#X}, which isn't in PRG RAM",
+                data,
+                addr
+            );
+            false
+        }
+    }
+
+    /// Write a patched byte in the PPU pattern tables ($0000-$1FFF), through the CHR banks selected by the mapper.
+    /// Only CHR RAM can be patched. Returns whether the byte was applied.
+    pub fn patch_chr_mem(&mut self, addr: u16, data: u8) -> bool {
+        if addr <= 0x1FFF && self.chr_ram && self.mapper.ppu_map_write(addr).is_some() {
+            self.write_chr_mem(addr, data);
+            true
+        } else {
+            log::warn!(
+                "ignored CHR patch of {:#X} at {:#X}, which isn't in CHR RAM",
+                data,
+                addr
+            );
+            false
+        }
+    }
+
     pub fn read_chr_mem(&mut self, addr: u16) -> u8 {
         let addr = self.mapper.ppu_map_read(addr);
         self.chr_memory[addr % self.chr_memory.len()]
@@ -168,7 +442,9 @@ impl Cartridge {
     pub fn write_chr_mem(&mut self, addr: u16, data: u8) {
         if self.chr_ram {
             if let Some(addr) = self.mapper.ppu_map_write(addr) {
-                self.chr_memory[addr] = data;
+                // Mappers can select banks outside of the CHR RAM
+                let len = self.chr_memory.len();
+                self.chr_memory[addr % len] = data;
             } else {
                 log::warn!(
                     "attempted to write on CHR memory at {}, but this is not supported by this mapper",
@@ -183,18 +459,430 @@ impl Cartridge {
         };
     }
 
+    /// Content of the PRG RAM, if the cartridge has a battery to keep it
     pub fn get_save_data(&self) -> Option<&[u8]> {
-        self.mapper.get_sram()
+        if self.battery {
+            self.mapper.get_sram()
+        } else {
+            None
+        }
+    }
+
+    pub fn is_save_dirty(&self) -> bool {
+        self.save_dirty
+    }
+
+    /// Header of the ROM file the cartridge was loaded from
+    pub fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    /// CRC32 of the PRG and CHR ROM
+    pub fn rom_crc32(&self) -> u32 {
+        self.rom_crc32
+    }
+
+    /// SHA-1 of the PRG and CHR ROM
+    pub fn rom_sha1(&self) -> [u8; 20] {
+        self.rom_sha1
+    }
+
+    pub fn irq_state(&self) -> bool {
+        self.mapper.irq_state()
     }
 
-    pub fn take_irq_set_state(&mut self) -> bool {
-        let state = self.mapper.irq_state();
-        self.mapper.irq_clear();
-        state
+    /// Bring the mapper back to its power-on banking, so the reset vector is read from the right bank
+    pub fn power_on(&mut self) {
+        self.mapper.power_on();
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn save_state(&self) -> CartridgeState {
+        CartridgeState {
+            mapper: self.mapper.save_state(),
+            chr_ram: if self.chr_ram {
+                Some(self.chr_memory.clone())
+            } else {
+                None
+            },
+            save_dirty: self.save_dirty,
+        }
+    }
+
+    /// The cartridge is left untouched if the state doesn't match it
+    #[cfg(feature = "serde")]
+    pub(crate) fn load_state(&mut self, state: CartridgeState) -> Result<(), StateError> {
+        let mut mapper = self.mapper.clone();
+        mapper.load_state(&state.mapper)?;
+
+        match state.chr_ram {
+            Some(chr_ram) if self.chr_ram && chr_ram.len() == self.chr_memory.len() => {
+                self.chr_memory = chr_ram
+            }
+            None if !self.chr_ram => {}
+            _ => return Err(StateError::Corrupt),
+        }
+
+        self.mapper = mapper;
+        self.save_dirty = state.save_dirty;
+        Ok(())
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn has_prg_ram(&self) -> bool {
+        self.mapper.has_prg_ram()
     }
 
     #[cfg(feature = "debugger")]
     pub fn get_prg_bank(&self, addr: u16) -> Option<u8> {
         self.mapper.get_prg_bank(addr)
     }
+
+    #[cfg(feature = "debugger")]
+    pub fn get_chr_bank(&self, addr: u16) -> Option<u8> {
+        self.mapper.get_chr_bank(addr)
+    }
+
+    /// CHR memory currently mapped at $0000-$1FFF. Unlike `read_chr_mem`, this has no side effect on the mapper.
+    #[cfg(feature = "debugger")]
+    pub fn chr_dump(&self) -> Vec<u8> {
+        const WINDOW_SIZE: usize = 0x400;
+
+        let mut dump = Vec::with_capacity(0x2000);
+        for window in 0..8 {
+            let bank = self
+                .get_chr_bank((window * WINDOW_SIZE) as u16)
+                .unwrap_or(0);
+            let start = (bank as usize * WINDOW_SIZE) % self.chr_memory.len();
+            dump.extend_from_slice(&self.chr_memory[start..start + WINDOW_SIZE]);
+        }
+        dump
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn take_trace_events(&mut self) -> Vec<TraceEvent> {
+        core::mem::take(&mut self.trace_events)
+    }
+
+    #[cfg(feature = "debugger")]
+    fn push_trace_event(&mut self, event: TraceEvent) {
+        // Drop events if nobody takes them, instead of growing forever
+        if self.trace_events.len() < MAX_TRACE_EVENTS {
+            self.trace_events.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(mapper_id: u8, prg_banks: u8, chr_banks: u8) -> Vec<u8> {
+        let mut rom = vec![0u8; 16 + 0x4000 * prg_banks as usize + 0x2000 * chr_banks as usize];
+        rom[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+        rom[4] = prg_banks;
+        rom[5] = chr_banks;
+        rom[6] = (mapper_id & 0x0F) << 4;
+        rom[7] = mapper_id & 0xF0;
+        rom
+    }
+
+    #[test]
+    fn supported_mappers_load() {
+        assert!(SUPPORTED_MAPPERS.contains(&0));
+
+        for mapper_id in 0..=255 {
+            let expected = if is_mapper_supported(mapper_id.into()) {
+                None
+            } else {
+                Some(RomParserError::UnsupportedMapper(mapper_id.into()))
+            };
+            assert_eq!(
+                Cartridge::load(&rom(mapper_id, 2, 1), None).err(),
+                expected,
+                "mapper {}",
+                mapper_id
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_roms_are_rejected() {
+        let load = |rom: &[u8]| Cartridge::load(rom, None).err();
+
+        let valid = rom(0, 2, 1);
+        assert_eq!(load(&valid[..15]), Some(RomParserError::TooShort));
+        assert_eq!(
+            load(&valid[..16 + 0x8000 - 1]),
+            Some(RomParserError::TruncatedPrgRom)
+        );
+        assert_eq!(
+            load(&valid[..valid.len() - 1]),
+            Some(RomParserError::TruncatedChrRom)
+        );
+
+        let mut bad_magic = valid.clone();
+        bad_magic[3] = 0x00;
+        assert_eq!(load(&bad_magic), Some(RomParserError::InvalidMagic));
+
+        // NES 2.0 header with 258 PRG banks, more than the mappers can switch
+        let mut too_large = valid;
+        too_large[7] |= 0x08;
+        too_large[9] = 0x01;
+        assert_eq!(load(&too_large), Some(RomParserError::RomTooLarge));
+    }
+
+    #[test]
+    fn nes2_rom_loads() {
+        // NROM with 8KB of PRG ROM, which can only be declared in the exponent form
+        let mut rom = rom(0, 0, 1);
+        rom[7] |= 0x08;
+        rom[9] = 0x0F;
+        rom[4] = 13 << 2;
+        rom.splice(16..16, (0..0x2000).map(|i| (i >> 8) as u8));
+
+        let cart = Cartridge::load(&rom, None).unwrap();
+        assert_eq!(cart.header().format, HeaderFormat::Nes2);
+        assert_eq!(cart.header().prg_rom_size, 0x2000);
+        assert_eq!(cart.header().chr_rom_size, 0x2000);
+
+        // The 8KB are mirrored across $8000-$FFFF
+        for &addr in &[0x8100, 0xA100, 0xC100, 0xE100] {
+            assert_eq!(cart.read_prg_mem(addr), 0x01);
+        }
+    }
+
+    #[test]
+    fn load_with_ips_patch() {
+        let rom = rom(0, 1, 1);
+
+        // Write 3 bytes at the start of the PRG, and fill the next 4 with $EA
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x03, 0xA9, 0x42, 0x60]);
+        patch.extend_from_slice(&[0x00, 0x00, 0x13, 0x00, 0x00, 0x00, 0x04, 0xEA]);
+        patch.extend_from_slice(b"EOF");
+
+        let cart = Cartridge::load_with_patch(&rom, &patch, None).unwrap();
+        let prg: Vec<u8> = (0x8000..0x8008)
+            .map(|addr| cart.read_prg_mem(addr))
+            .collect();
+        assert_eq!(prg, [0xA9, 0x42, 0x60, 0xEA, 0xEA, 0xEA, 0xEA, 0x00]);
+
+        patch.truncate(patch.len() - 3);
+        assert_eq!(
+            Cartridge::load_with_patch(&rom, &patch, None).err(),
+            Some(RomParserError::InvalidPatch(PatchError::Truncated))
+        );
+    }
+
+    #[test]
+    fn mmc1_bank_switch() {
+        // Battery-backed MMC1 with each PRG bank starting with its number
+        let mut rom = rom(1, 4, 0);
+        rom[6] |= 0x02;
+        for bank in 0..4 {
+            rom[16 + bank * 0x4000] = bank as u8;
+        }
+
+        let mut cart = Cartridge::load(&rom, None).unwrap();
+        assert_eq!(cart.read_prg_mem(0x8000), 0);
+        assert_eq!(cart.read_prg_mem(0xC000), 3);
+
+        // The bank number is shifted in one bit per write
+        for bit in [0, 1, 0, 0, 0] {
+            cart.write_prg_mem(0xE000, bit);
+        }
+        assert_eq!(cart.read_prg_mem(0x8000), 2);
+        assert_eq!(cart.read_prg_mem(0xC000), 3);
+
+        cart.write_prg_mem(0x6000, 0x42);
+        assert_eq!(cart.get_save_data().unwrap()[0], 0x42);
+    }
+
+    #[test]
+    fn uxrom_bank_switch() {
+        let mut rom = rom(2, 4, 0);
+        for bank in 0..4 {
+            rom[16 + bank * 0x4000] = bank as u8;
+        }
+
+        let mut cart = Cartridge::load(&rom, None).unwrap();
+        assert_eq!(cart.read_prg_mem(0x8000), 0);
+        assert_eq!(cart.read_prg_mem(0xC000), 3);
+
+        // Any write to the ROM selects the bank at $8000
+        cart.write_prg_mem(0xD123, 2);
+        assert_eq!(cart.read_prg_mem(0x8000), 2);
+        assert_eq!(cart.read_prg_mem(0xC000), 3);
+    }
+
+    #[test]
+    fn cnrom_bank_switch() {
+        let mut rom = rom(3, 2, 4);
+        for bank in 0..4 {
+            rom[16 + 2 * 0x4000 + bank * 0x2000 + 0x1234] = bank as u8;
+        }
+
+        let mut cart = Cartridge::load(&rom, None).unwrap();
+        assert_eq!(cart.read_chr_mem(0x1234), 0);
+
+        cart.write_prg_mem(0x8000, 3);
+        assert_eq!(cart.read_chr_mem(0x1234), 3);
+        // The PRG isn't banked
+        assert_eq!(cart.read_prg_mem(0x8000), 0);
+    }
+
+    #[test]
+    fn no_chr_rom_gets_chr_ram() {
+        for &mapper_id in SUPPORTED_MAPPERS {
+            let mut cart = Cartridge::load(&rom(mapper_id as u8, 2, 0), None).unwrap();
+            assert!(cart.chr_ram);
+            assert_eq!(cart.chr_memory.len(), 0x2000);
+
+            // Select the last bank, which would be outside of the CHR RAM
+            cart.write_prg_mem(0x8000, 0xFF);
+            for addr in 0..0x2000 {
+                cart.write_chr_mem(addr, 0x42);
+                cart.read_chr_mem(addr);
+            }
+        }
+
+        // CNROM writes the CHR RAM
+        let mut cart = Cartridge::load(&rom(3, 1, 0), None).unwrap();
+        cart.write_prg_mem(0x8000, 0x03);
+        cart.write_chr_mem(0x0010, 0x42);
+        assert_eq!(cart.read_chr_mem(0x0010), 0x42);
+    }
+
+    #[test]
+    fn missing_prg_ram_write_dropped() {
+        // CNROM, with a different byte at the start of each CHR bank
+        let mut rom = rom(3, 1, 2);
+        rom[16 + 0x4000 + 0x2000] = 0x42;
+        let mut cart = Cartridge::load(&rom, None).unwrap();
+
+        cart.write_prg_mem(0x6000, 1);
+        assert_eq!(cart.read_chr_mem(0), 0);
+
+        // Sanity check that the write would switch bank on the register
+        cart.write_prg_mem(0x8000, 1);
+        assert_eq!(cart.read_chr_mem(0), 0x42);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn missing_prg_ram_write_traced() {
+        let mut cart = Cartridge::load(&rom(0, 1, 1), None).unwrap();
+
+        cart.write_prg_mem(0x6123, 0x45);
+        assert_eq!(
+            cart.take_trace_events(),
+            [TraceEvent::MissingPrgRamWrite {
+                addr: 0x6123,
+                data: 0x45
+            }]
+        );
+        assert!(cart.take_trace_events().is_empty());
+
+        // Cartridge with PRG RAM doesn't raise the event
+        let mut cart = Cartridge::load(&rom(4, 2, 1), None).unwrap();
+        cart.write_prg_mem(0x6123, 0x45);
+        assert_eq!(cart.read_prg_mem(0x6123), 0x45);
+        assert!(cart.take_trace_events().is_empty());
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn prg_rom_write_traced() {
+        let mut nrom = rom(0, 1, 1);
+        nrom[16 + 0x0123] = 0x42;
+        let mut cart = Cartridge::load(&nrom, None).unwrap();
+
+        cart.write_prg_mem(0x8123, 0x45);
+        assert_eq!(
+            cart.take_trace_events(),
+            [TraceEvent::PrgRomWrite {
+                addr: 0x8123,
+                data: 0x45
+            }]
+        );
+        assert_eq!(cart.read_prg_mem(0x8123), 0x42);
+
+        // Mapper registers don't raise the event
+        let mut cart = Cartridge::load(&rom(2, 2, 1), None).unwrap();
+        cart.write_prg_mem(0x8000, 0x01);
+        assert!(cart.take_trace_events().is_empty());
+
+        // MMC2 only has registers from $A000
+        let mut cart = Cartridge::load(&rom(9, 8, 2), None).unwrap();
+        cart.write_prg_mem(0xA000, 0x01);
+        assert!(cart.take_trace_events().is_empty());
+        cart.write_prg_mem(0x9000, 0x01);
+        assert_eq!(
+            cart.take_trace_events(),
+            [TraceEvent::PrgRomWrite {
+                addr: 0x9000,
+                data: 0x01
+            }]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn corrupt_mapper_state() {
+        let mut cart = Cartridge::load(&rom(4, 2, 1), None).unwrap();
+        cart.write_prg_mem(0x6000, 0x42);
+        let state = cart.save_state();
+
+        // Truncated PRG RAM
+        let mut truncated = cart.save_state();
+        truncated.mapper.truncate(truncated.mapper.len() - 1);
+        assert!(cart.load_state(truncated).is_err());
+
+        // The PRG bank count comes first, and doesn't match the ROM
+        let mut no_banks = cart.save_state();
+        no_banks.mapper[0] = 0;
+        assert_eq!(cart.load_state(no_banks).err(), Some(StateError::Corrupt));
+
+        cart.write_prg_mem(0x6000, 0x00);
+        cart.load_state(state).unwrap();
+        assert_eq!(cart.read_prg_mem(0x6000), 0x42);
+    }
+
+    #[test]
+    fn rom_hashes() {
+        let mut rom = rom(0, 1, 1);
+        for (i, b) in rom[16..16 + 0x4000].iter_mut().enumerate() {
+            *b = (i * 7) as u8;
+        }
+        for (i, b) in rom[16 + 0x4000..].iter_mut().enumerate() {
+            *b = (i * 13) as u8;
+        }
+
+        // Reference values from Python's zlib.crc32 and hashlib.sha1
+        let cart = Cartridge::load(&rom, None).unwrap();
+        assert_eq!(cart.rom_crc32(), 0x184FEE07);
+        assert_eq!(
+            cart.rom_sha1(),
+            [
+                0x29, 0xeb, 0xb3, 0x20, 0xda, 0xb7, 0xb8, 0x8d, 0x5c, 0x42, 0x9b, 0x19, 0x88, 0xb7,
+                0x23, 0xb4, 0xf5, 0xbb, 0x4e, 0x7b
+            ]
+        );
+
+        // The header isn't part of the hash
+        rom[8] = 0x01;
+        let cart = Cartridge::load(&rom, None).unwrap();
+        assert_eq!(cart.rom_crc32(), 0x184FEE07);
+    }
+
+    #[test]
+    fn bundled_rom_hashes() {
+        // nestest has no trainer, so its PRG and CHR ROM are the whole file after the header
+        const ROM: &[u8] = include_bytes!("../../../default_roms/nestest.nes");
+        let cart = Cartridge::load(ROM, None).unwrap();
+        assert_eq!(ROM.len(), 16 + 0x4000 + 0x2000);
+        assert_eq!(cart.rom_crc32(), hash::crc32(&ROM[16..]));
+        assert_eq!(cart.rom_sha1(), hash::sha1(&ROM[16..]));
+    }
 }
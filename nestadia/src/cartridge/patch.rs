@@ -0,0 +1,317 @@
+// Patch formats used to distribute ROM hacks and translations
+// IPS: https://zerosoft.zophar.net/ips.php
+// BPS: https://www.romhacking.net/documents/746/
+
+use alloc::vec::Vec;
+
+use super::hash::crc32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchError {
+    /// The patch is neither an IPS nor a BPS patch
+    UnknownFormat,
+    /// The patch ends in the middle of a record
+    Truncated,
+    /// The BPS patch was made for another ROM
+    WrongSourceRom,
+    /// The content of the patch is inconsistent, or its checksum doesn't match
+    Corrupt,
+}
+
+impl core::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "the patch is not an IPS or BPS patch"),
+            PatchError::Truncated => write!(f, "the patch is truncated"),
+            PatchError::WrongSourceRom => write!(f, "the patch was made for another ROM"),
+            PatchError::Corrupt => write!(f, "the patch is corrupted"),
+        }
+    }
+}
+
+/// Apply an IPS or BPS patch to the whole ROM file, header included
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(b"PATCH") {
+        apply_ips(rom, &patch[5..])
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+/// Reads the records of a patch, failing when they go past its end
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(PatchError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn be(&mut self, len: usize) -> Result<usize, PatchError> {
+        Ok(self
+            .bytes(len)?
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | byte as usize))
+    }
+
+    // BPS numbers are encoded 7 bits at a time, with an offset that makes each encoding unique
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            value = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|v| v.checked_add(value))
+                .ok_or(PatchError::Corrupt)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or(PatchError::Corrupt)?;
+            value = value.checked_add(shift).ok_or(PatchError::Corrupt)?;
+        }
+    }
+}
+
+fn apply_ips(rom: &[u8], records: &[u8]) -> Result<Vec<u8>, PatchError> {
+    const EOF: usize = 0x454F46;
+
+    let mut output = rom.to_vec();
+    let mut reader = PatchReader {
+        data: records,
+        pos: 0,
+    };
+
+    loop {
+        let offset = reader.be(3)?;
+        if offset == EOF {
+            break;
+        }
+
+        let size = reader.be(2)?;
+        let (len, data) = if size == 0 {
+            // Run-length encoded record
+            let len = reader.be(2)?;
+            (len, None)
+        } else {
+            (size, Some(reader.bytes(size)?))
+        };
+
+        // Records can write past the end of the ROM to extend it
+        if output.len() < offset + len {
+            output.resize(offset + len, 0);
+        }
+
+        match data {
+            Some(data) => output[offset..offset + len].copy_from_slice(data),
+            None => {
+                let value = reader.byte()?;
+                output[offset..offset + len].fill(value);
+            }
+        }
+    }
+
+    // Some patches shrink the ROM with a size after the EOF marker
+    if let Ok(size) = reader.be(3) {
+        output.truncate(size);
+    }
+
+    Ok(output)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    const FOOTER_SIZE: usize = 12;
+
+    if patch.len() < 4 + FOOTER_SIZE {
+        return Err(PatchError::Truncated);
+    }
+
+    let (actions, footer) = patch.split_at(patch.len() - FOOTER_SIZE);
+    let checksum =
+        |i: usize| u32::from_le_bytes([footer[i], footer[i + 1], footer[i + 2], footer[i + 3]]);
+    if crc32(&patch[..patch.len() - 4]) != checksum(8) {
+        return Err(PatchError::Corrupt);
+    }
+    if crc32(rom) != checksum(0) {
+        return Err(PatchError::WrongSourceRom);
+    }
+
+    let mut reader = PatchReader {
+        data: actions,
+        pos: 4,
+    };
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(PatchError::WrongSourceRom);
+    }
+
+    // The sizes come from the patch, so the output only grows as the actions are applied
+    let mut output = Vec::new();
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+
+    while reader.pos < actions.len() {
+        let action = reader.varint()?;
+        let len = (action >> 2) + 1;
+        if output
+            .len()
+            .checked_add(len)
+            .is_none_or(|end| end > target_size)
+        {
+            return Err(PatchError::Corrupt);
+        }
+
+        match action & 0x03 {
+            // SourceRead
+            0 => {
+                let data = range(rom, output.len(), len)?;
+                output.extend_from_slice(data);
+            }
+            // TargetRead
+            1 => output.extend_from_slice(reader.bytes(len)?),
+            // SourceCopy
+            2 => {
+                source_offset = relative_offset(source_offset, reader.varint()?)?;
+                let data = range(rom, source_offset, len)?;
+                output.extend_from_slice(data);
+                source_offset += len;
+            }
+            // TargetCopy, which can overlap the bytes being written to repeat a pattern
+            _ => {
+                target_offset = relative_offset(target_offset, reader.varint()?)?;
+                if target_offset >= output.len() {
+                    return Err(PatchError::Corrupt);
+                }
+                for _ in 0..len {
+                    output.push(output[target_offset]);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size || crc32(&output) != checksum(4) {
+        return Err(PatchError::Corrupt);
+    }
+
+    Ok(output)
+}
+
+// `len` bytes of `data` from `start`, failing instead of overflowing
+fn range(data: &[u8], start: usize, len: usize) -> Result<&[u8], PatchError> {
+    start
+        .checked_add(len)
+        .and_then(|end| data.get(start..end))
+        .ok_or(PatchError::Corrupt)
+}
+
+// The lowest bit is the sign of the offset
+fn relative_offset(offset: usize, value: usize) -> Result<usize, PatchError> {
+    if value & 1 != 0 {
+        offset.checked_sub(value >> 1)
+    } else {
+        offset.checked_add(value >> 1)
+    }
+    .ok_or(PatchError::Corrupt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bps_patch(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = b"BPS1".to_vec();
+        patch.push(0x80 | source.len() as u8);
+        patch.push(0x80 | target.len() as u8);
+        patch.push(0x80); // No metadata
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn bps_actions() {
+        let source = b"ABCDEFGH";
+        let target = b"ABCDxyxyxyGH";
+        #[rustfmt::skip]
+        let actions = [
+            0x80 | 3 << 2,          // SourceRead 4 bytes
+            0x80 | 1 << 2 | 1,      // TargetRead 2 bytes
+            b'x', b'y',
+            0x80 | 3 << 2 | 3,      // TargetCopy 4 bytes, starting 4 bytes forward
+            0x80 | 4 << 1,
+            0x80 | 1 << 2 | 2,      // SourceCopy 2 bytes, starting 6 bytes forward
+            0x80 | 6 << 1,
+        ];
+        let patch = bps_patch(source, target, &actions);
+
+        assert_eq!(apply_patch(source, &patch).unwrap(), target);
+        assert_eq!(
+            apply_patch(b"ABCDEFGX", &patch),
+            Err(PatchError::WrongSourceRom)
+        );
+
+        let mut corrupt = patch.clone();
+        corrupt[7] ^= 0x01;
+        assert_eq!(apply_patch(source, &corrupt), Err(PatchError::Corrupt));
+    }
+
+    #[test]
+    fn bps_huge_target_size() {
+        // Target size of about 2^57, with valid checksums
+        let source = b"ABCD";
+        let mut patch = b"BPS1".to_vec();
+        patch.push(0x80 | 4);
+        patch.extend_from_slice(&[0x7F; 8]);
+        patch.push(0x80);
+        patch.push(0x80); // No metadata
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&0u32.to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+
+        assert_eq!(apply_patch(source, &patch), Err(PatchError::Corrupt));
+    }
+
+    #[test]
+    fn invalid_ips_patches() {
+        assert_eq!(
+            apply_patch(b"ROM", b"PATCX"),
+            Err(PatchError::UnknownFormat)
+        );
+        // Record cut in its data
+        assert_eq!(
+            apply_patch(b"ROM", b"PATCH\x00\x00\x01\x00\x04AB"),
+            Err(PatchError::Truncated)
+        );
+        // Missing EOF marker
+        assert_eq!(
+            apply_patch(b"ROM", b"PATCH\x00\x00\x01\x00\x01A"),
+            Err(PatchError::Truncated)
+        );
+        // RLE record that extends the ROM
+        assert_eq!(
+            apply_patch(b"ROM", b"PATCH\x00\x00\x02\x00\x00\x00\x03ZEOF").unwrap(),
+            b"ROZZZ"
+        );
+    }
+}
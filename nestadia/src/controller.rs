@@ -0,0 +1,139 @@
+use bitflags::bitflags;
+
+use crate::Ppu;
+
+bitflags! {
+    /// Buttons of a standard controller. The most significant bit is shifted out first.
+    pub struct Buttons: u8 {
+        const A = 0x80;
+        const B = 0x40;
+        const SELECT = 0x20;
+        const START = 0x10;
+        const UP = 0x08;
+        const DOWN = 0x04;
+        const LEFT = 0x02;
+        const RIGHT = 0x01;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControllerPort {
+    One,
+    Two,
+}
+
+/// How to resolve opposing directions pressed at the same time (SOCD), which is impossible on a real d-pad
+/// and glitches some games.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocdPolicy {
+    /// Opposing directions cancel each other
+    Neutral,
+    /// The direction pressed last wins
+    LastWins,
+    /// Pass both directions to the game
+    Allow,
+}
+
+impl Default for SocdPolicy {
+    fn default() -> Self {
+        Self::Neutral
+    }
+}
+
+impl SocdPolicy {
+    /// Resolve the new `buttons` of a controller, given its previous raw and resolved states
+    pub fn resolve(self, previous_raw: Buttons, previous: Buttons, buttons: Buttons) -> Buttons {
+        let mut resolved = buttons;
+        for &axis in &[Buttons::LEFT | Buttons::RIGHT, Buttons::UP | Buttons::DOWN] {
+            if !buttons.contains(axis) {
+                continue;
+            }
+
+            resolved.remove(axis);
+            match self {
+                Self::Neutral => {}
+                Self::LastWins => {
+                    let held = previous_raw & axis;
+                    if held == axis {
+                        // Both were already pressed, keep what was decided then
+                        resolved.insert(previous & axis);
+                    } else if !held.is_empty() {
+                        resolved.insert(axis - held);
+                    }
+                }
+                Self::Allow => resolved.insert(axis),
+            }
+        }
+        resolved
+    }
+}
+
+/// Autofire buttons of `mask` that are released on `frame`, so they alternate every `period` frames while held
+pub(crate) fn turbo_released(mask: Buttons, frame: u64, period: u8) -> Buttons {
+    if (frame / u64::from(period.max(1))) % 2 == 1 {
+        mask
+    } else {
+        Buttons::empty()
+    }
+}
+
+// Scanlines the light sensor stays lit after seeing a bright pixel
+const ZAPPER_LIGHT_SCANLINES: u8 = 26;
+// Minimum sum of the RGB components of a pixel that lights the sensor
+const ZAPPER_BRIGHTNESS_THRESHOLD: u16 = 3 * 85;
+
+/// Zapper light gun, plugged in the second controller port.
+/// Its sensor only sees the pointed pixel as the beam draws it, then stays lit for about 26 scanlines.
+#[derive(Clone, Copy, Debug)]
+pub struct Zapper {
+    x: u16,
+    y: u16,
+    trigger: bool,
+    light_scanlines: u8,
+}
+
+impl Zapper {
+    pub fn new(x: u16, y: u16, trigger: bool) -> Self {
+        Self {
+            x,
+            y,
+            trigger,
+            light_scanlines: 0,
+        }
+    }
+
+    pub fn aim(&mut self, x: u16, y: u16, trigger: bool) {
+        self.x = x;
+        self.y = y;
+        self.trigger = trigger;
+    }
+
+    /// Follow the beam, after each PPU cycle
+    pub fn clock(&mut self, ppu: &Ppu, palette: &[[u8; 3]; 64]) {
+        if ppu.cycle() == 0 {
+            self.light_scanlines = self.light_scanlines.saturating_sub(1);
+        }
+
+        // Pixels are drawn one cycle after the start of the scanline. Off-screen, nothing is ever seen.
+        if self.x < 256
+            && self.y < 240
+            && i32::from(ppu.scanline()) == i32::from(self.y)
+            && ppu.cycle() == self.x + 1
+        {
+            let color = ppu.frame()[self.y as usize * 256 + self.x as usize];
+            let brightness: u16 = palette[(color & 0x3F) as usize]
+                .iter()
+                .map(|&c| u16::from(c))
+                .sum();
+            if brightness >= ZAPPER_BRIGHTNESS_THRESHOLD {
+                self.light_scanlines = ZAPPER_LIGHT_SCANLINES;
+            }
+        }
+    }
+
+    /// State reported at `$4017`: bit 3 is cleared while light is sensed, and bit 4 is set while the trigger is pulled
+    pub fn read(&self) -> u8 {
+        let no_light = (self.light_scanlines == 0) as u8;
+        (no_light << 3) | ((self.trigger as u8) << 4)
+    }
+}
@@ -1,4 +1,5 @@
 use super::opcode::Opcode;
+use crate::bus::CpuBus;
 use alloc::format;
 use alloc::string::String;
 use alloc::string::ToString;
@@ -68,40 +69,55 @@ impl AddressingMode {
     }
 }
 
-pub fn disassemble(
-    cart: &crate::cartridge::Cartridge,
-    start: u16,
-) -> Vec<(Option<u8>, u16, String)> {
-    let mut addr: u16 = start;
+/// Disassemble the CPU address space between `start` and `end`, inclusively.
+/// The operands of the instruction at `end` are decoded even if they are past it.
+/// The registers can't be read without side effects, so they are skipped.
+pub fn disassemble(bus: &mut CpuBus<'_>, start: u16, end: u16) -> Vec<(Option<u8>, u16, String)> {
+    // Use a wider type to avoid overflowing at the end of the address space
+    let mut addr = u32::from(start);
+    let end = u32::from(end);
     let mut disassembly = Vec::new();
 
-    while addr < 0xFFFF {
+    while addr <= end {
         let mut disas = String::new();
-        let prg_bank = cart.get_prg_bank(addr);
-        if let Ok(opcode) = Opcode::try_from(cart.read_prg_mem(addr)) {
-            disas += &format!("{:?}", &opcode)[..3].to_lowercase();
-
-            let required_bytes = opcode.addressing_mode().required_bytes();
-
-            if required_bytes < 1 {
-                disassembly.push((prg_bank, addr, disas));
+        let current_addr = addr as u16;
+        let prg_bank = bus.get_prg_bank(current_addr);
+        let data = match bus.peek(current_addr) {
+            Some(data) => data,
+            None => {
                 addr += 1;
-            } else if required_bytes < (0xFFFF - addr) {
-                let data = (0..required_bytes)
-                    .map(|i| cart.read_prg_mem(addr + i + 1))
-                    .collect::<Vec<_>>();
+                continue;
+            }
+        };
 
-                disas += " ";
-                disas += &opcode
-                    .addressing_mode()
-                    .format(data.as_slice(), addr + required_bytes + 1);
-                disassembly.push((prg_bank, addr, disas));
-                addr += required_bytes + 1;
+        if let Ok(opcode) = Opcode::try_from(data) {
+            disas += &format!("{:?}", &opcode)[..3].to_lowercase();
+
+            let required_bytes = u32::from(opcode.addressing_mode().required_bytes());
+            let operands = if addr + required_bytes <= 0xFFFF {
+                (1..=required_bytes)
+                    .map(|i| bus.peek((addr + i) as u16))
+                    .collect::<Option<Vec<_>>>()
             } else {
-                addr += 1;
+                None
+            };
+
+            match operands {
+                Some(data) => {
+                    if required_bytes > 0 {
+                        disas += " ";
+                        disas += &opcode
+                            .addressing_mode()
+                            .format(data.as_slice(), (addr + required_bytes + 1) as u16);
+                    }
+                    disassembly.push((prg_bank, current_addr, disas));
+                    addr += required_bytes + 1;
+                }
+                // The operands are past the end of the address space or in the registers
+                None => addr += 1,
             }
         } else {
-            disassembly.push((prg_bank, addr, "???".to_string()));
+            disassembly.push((prg_bank, current_addr, "???".to_string()));
             addr += 1;
         }
     }
@@ -15,6 +15,7 @@ const IRQ_HANDLER: u16 = 0xFFFE;
 const NMI_HANDLER: u16 = 0xFFFA;
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct StatusRegister: u8 {
         const C = (1 << 0);
         const Z = (1 << 1);
@@ -28,14 +29,25 @@ bitflags! {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cpu {
     pub a: u8,
     pub x: u8,
     pub y: u8,
     pub st: u8,
     pub pc: u16,
-    pub cycles: u8,
+    pub cycles: u16,
     pub status_register: StatusRegister,
+
+    /// An NMI was raised and will be serviced after the current instruction
+    pub nmi_pending: bool,
+    /// The IRQ line is asserted. It's only serviced if interrupts are not disabled
+    pub irq_pending: bool,
+
+    /// Bitmap of the opcodes executed since power-on
+    #[cfg(feature = "opcode-coverage")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) executed_opcodes: [u64; 4],
 }
 
 impl Default for Cpu {
@@ -48,6 +60,12 @@ impl Default for Cpu {
             pc: 0,
             cycles: 0,
             status_register: StatusRegister::empty(),
+
+            nmi_pending: false,
+            irq_pending: false,
+
+            #[cfg(feature = "opcode-coverage")]
+            executed_opcodes: [0; 4],
         }
     }
 }
@@ -60,9 +78,21 @@ impl Cpu {
         self.st = 0xFD;
         self.cycles = 8;
         self.status_register = StatusRegister::U | StatusRegister::I;
+        self.nmi_pending = false;
+        self.irq_pending = false;
         self.pc = u16::from(bus.read(PC_START)) | (u16::from(bus.read(PC_START + 1)) << 8);
     }
 
+    /// Which opcodes were executed since power-on, indexed by opcode
+    #[cfg(feature = "opcode-coverage")]
+    pub fn executed_opcodes(&self) -> [bool; 256] {
+        let mut executed = [false; 256];
+        for (opcode, executed) in executed.iter_mut().enumerate() {
+            *executed = self.executed_opcodes[opcode / 64] & (1 << (opcode % 64)) != 0;
+        }
+        executed
+    }
+
     pub fn irq(&mut self, bus: &mut CpuBus<'_>) {
         if !self.status_register.contains(StatusRegister::I) {
             // Push current PC
@@ -84,6 +114,8 @@ impl Cpu {
     }
 
     pub fn nmi(&mut self, bus: &mut CpuBus<'_>) {
+        self.nmi_pending = false;
+
         // Push current PC
         self.stack_push(bus, ((self.pc >> 8) & 0xff) as u8);
         self.stack_push(bus, (self.pc & 0xff) as u8);
@@ -101,14 +133,35 @@ impl Cpu {
         self.cycles = 8;
     }
 
+    /// Clock until the current instruction is done, and return the number of cycles it took.
+    /// Called between instructions, this runs exactly the next one.
+    pub fn step(&mut self, bus: &mut CpuBus<'_>) -> u16 {
+        let mut cycles = 0;
+        loop {
+            self.clock(bus);
+            cycles += 1;
+
+            if self.cycles == 0 {
+                return cycles;
+            }
+        }
+    }
+
     pub fn clock(&mut self, bus: &mut CpuBus<'_>) {
         if self.cycles == 0 {
-            let opcode = match Opcode::try_from(bus.read(self.pc)) {
+            let data = bus.read(self.pc);
+
+            #[cfg(feature = "opcode-coverage")]
+            {
+                self.executed_opcodes[data as usize / 64] |= 1 << (data % 64);
+            }
+
+            let opcode = match Opcode::try_from(data) {
                 Ok(o) => o,
                 Err(_) => {
                     log::warn!(
                         "Unknown opcode {} at pc {:#06x}, treating as a NOP...",
-                        bus.read(self.pc),
+                        data,
                         self.pc
                     );
                     Opcode::Nop
@@ -835,11 +888,17 @@ impl Cpu {
                 Opcode::Inx => {
                     self.inst_inx();
                 }
-                Opcode::SbcImm => {
+                Opcode::SbcImm | Opcode::SbcImmEB => {
                     let op = self.am_imm(bus);
                     self.inst_sbc(op);
                 }
-                Opcode::Nop => {
+                Opcode::Nop
+                | Opcode::Nop1A
+                | Opcode::Nop3A
+                | Opcode::Nop5A
+                | Opcode::Nop7A
+                | Opcode::NopDA
+                | Opcode::NopFA => {
                     // This is intended, a NOP actually does nothing.
                 }
                 Opcode::CpxAbs => {
@@ -910,9 +969,420 @@ impl Cpu {
                     let result = self.inst_inc(op);
                     bus.write(addr, result);
                 }
+
+                // Unofficial opcodes
+                Opcode::LaxIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    self.inst_lax(op);
+                }
+                Opcode::LaxZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    self.inst_lax(op);
+                }
+                Opcode::LaxAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    self.inst_lax(op);
+                }
+                Opcode::LaxIndY => {
+                    let (addr, extra_cycle) = self.am_izy(bus);
+                    if extra_cycle {
+                        self.cycles += 1;
+                    }
+
+                    let op = bus.read(addr);
+                    self.inst_lax(op);
+                }
+                Opcode::LaxZpY => {
+                    let addr = self.am_zpy(bus);
+                    let op = bus.read(addr);
+                    self.inst_lax(op);
+                }
+                Opcode::LaxAbsY => {
+                    let (addr, extra_cycle) = self.am_aby(bus);
+                    if extra_cycle {
+                        self.cycles += 1;
+                    }
+
+                    let op = bus.read(addr);
+                    self.inst_lax(op);
+                }
+
+                Opcode::SaxIndX => {
+                    let addr = self.am_izx(bus);
+                    self.inst_sax(bus, addr);
+                }
+                Opcode::SaxZp => {
+                    let addr = self.am_zp(bus);
+                    self.inst_sax(bus, addr);
+                }
+                Opcode::SaxAbs => {
+                    let addr = self.am_abs(bus);
+                    self.inst_sax(bus, addr);
+                }
+                Opcode::SaxZpY => {
+                    let addr = self.am_zpy(bus);
+                    self.inst_sax(bus, addr);
+                }
+
+                Opcode::SloIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+                Opcode::SloZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+                Opcode::SloAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+                Opcode::SloIndY => {
+                    let (addr, _) = self.am_izy(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+                Opcode::SloZpX => {
+                    let addr = self.am_zpx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+                Opcode::SloAbsY => {
+                    let (addr, _) = self.am_aby(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+                Opcode::SloAbsX => {
+                    let (addr, _) = self.am_abx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_asl(op);
+                    bus.write(addr, result);
+                    self.inst_ora(result);
+                }
+
+                Opcode::RlaIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+                Opcode::RlaZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+                Opcode::RlaAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+                Opcode::RlaIndY => {
+                    let (addr, _) = self.am_izy(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+                Opcode::RlaZpX => {
+                    let addr = self.am_zpx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+                Opcode::RlaAbsY => {
+                    let (addr, _) = self.am_aby(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+                Opcode::RlaAbsX => {
+                    let (addr, _) = self.am_abx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_rol(op);
+                    bus.write(addr, result);
+                    self.inst_and(result);
+                }
+
+                Opcode::SreIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+                Opcode::SreZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+                Opcode::SreAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+                Opcode::SreIndY => {
+                    let (addr, _) = self.am_izy(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+                Opcode::SreZpX => {
+                    let addr = self.am_zpx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+                Opcode::SreAbsY => {
+                    let (addr, _) = self.am_aby(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+                Opcode::SreAbsX => {
+                    let (addr, _) = self.am_abx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_lsr(op);
+                    bus.write(addr, result);
+                    self.inst_eor(result);
+                }
+
+                Opcode::RraIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+                Opcode::RraZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+                Opcode::RraAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+                Opcode::RraIndY => {
+                    let (addr, _) = self.am_izy(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+                Opcode::RraZpX => {
+                    let addr = self.am_zpx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+                Opcode::RraAbsY => {
+                    let (addr, _) = self.am_aby(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+                Opcode::RraAbsX => {
+                    let (addr, _) = self.am_abx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_ror(op);
+                    bus.write(addr, result);
+                    self.inst_adc(result);
+                }
+
+                Opcode::DcpIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+                Opcode::DcpZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+                Opcode::DcpAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+                Opcode::DcpIndY => {
+                    let (addr, _) = self.am_izy(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+                Opcode::DcpZpX => {
+                    let addr = self.am_zpx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+                Opcode::DcpAbsY => {
+                    let (addr, _) = self.am_aby(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+                Opcode::DcpAbsX => {
+                    let (addr, _) = self.am_abx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_dec(op);
+                    bus.write(addr, result);
+                    self.inst_cmp(result);
+                }
+
+                Opcode::IscIndX => {
+                    let addr = self.am_izx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::IscZp => {
+                    let addr = self.am_zp(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::IscAbs => {
+                    let addr = self.am_abs(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::IscIndY => {
+                    let (addr, _) = self.am_izy(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::IscZpX => {
+                    let addr = self.am_zpx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::IscAbsY => {
+                    let (addr, _) = self.am_aby(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::IscAbsX => {
+                    let (addr, _) = self.am_abx(bus);
+                    let op = bus.read(addr);
+                    let result = self.inst_inc(op);
+                    bus.write(addr, result);
+                    self.inst_sbc(result);
+                }
+                Opcode::AncImm | Opcode::AncImm2 => {
+                    let op = self.am_imm(bus);
+                    self.inst_anc(op);
+                }
+                Opcode::AlrImm => {
+                    let op = self.am_imm(bus);
+                    self.inst_alr(op);
+                }
+                Opcode::ArrImm => {
+                    let op = self.am_imm(bus);
+                    self.inst_arr(op);
+                }
+                Opcode::AxsImm => {
+                    let op = self.am_imm(bus);
+                    self.inst_axs(op);
+                }
+                // Skip NOPs, which only fetch their operand
+                Opcode::NopImm80
+                | Opcode::NopImm82
+                | Opcode::NopImm89
+                | Opcode::NopImmC2
+                | Opcode::NopImmE2 => {
+                    self.am_imm(bus);
+                }
+                Opcode::NopZp04 | Opcode::NopZp44 | Opcode::NopZp64 => {
+                    let addr = self.am_zp(bus);
+                    bus.read(addr);
+                }
+                Opcode::NopZpX14
+                | Opcode::NopZpX34
+                | Opcode::NopZpX54
+                | Opcode::NopZpX74
+                | Opcode::NopZpXD4
+                | Opcode::NopZpXF4 => {
+                    let addr = self.am_zpx(bus);
+                    bus.read(addr);
+                }
+                Opcode::NopAbs0C => {
+                    let addr = self.am_abs(bus);
+                    bus.read(addr);
+                }
+                Opcode::NopAbsX1C
+                | Opcode::NopAbsX3C
+                | Opcode::NopAbsX5C
+                | Opcode::NopAbsX7C
+                | Opcode::NopAbsXDC
+                | Opcode::NopAbsXFC => {
+                    let (addr, extra_cycle) = self.am_abx(bus);
+                    if extra_cycle {
+                        self.cycles += 1;
+                    }
+
+                    bus.read(addr);
+                }
             };
 
-            self.cycles += opcode.cycles();
+            self.cycles += u16::from(opcode.cycles());
         }
         self.cycles -= 1;
     }
@@ -1051,6 +1521,18 @@ impl Cpu {
         self.a = r;
     }
 
+    fn inst_alr(&mut self, op: u8) {
+        self.inst_and(op);
+        self.a = self.inst_lsr(self.a);
+    }
+
+    fn inst_anc(&mut self, op: u8) {
+        self.inst_and(op);
+
+        let c = self.a & 0x80 == 0x80;
+        self.status_register.set(StatusRegister::C, c);
+    }
+
     fn inst_and(&mut self, op: u8) {
         self.a &= op;
 
@@ -1061,6 +1543,18 @@ impl Cpu {
         self.status_register.set(StatusRegister::N, n);
     }
 
+    fn inst_arr(&mut self, op: u8) {
+        self.inst_and(op);
+        self.a = self.inst_ror(self.a);
+
+        // C is bit 6 of the result, and V is bit 6 xor bit 5
+        let c = self.a & 0x40 == 0x40;
+        self.status_register.set(StatusRegister::C, c);
+
+        let v = ((self.a >> 6) ^ (self.a >> 5)) & 0x01 == 0x01;
+        self.status_register.set(StatusRegister::V, v);
+    }
+
     fn inst_asl(&mut self, op: u8) -> u8 {
         self.status_register
             .set(StatusRegister::C, op & 0x80 == 0x80);
@@ -1075,6 +1569,20 @@ impl Cpu {
         result
     }
 
+    fn inst_axs(&mut self, op: u8) {
+        let value = self.a & self.x;
+        self.x = value.wrapping_sub(op);
+
+        // Same flags as CMP, without the borrow of SBC
+        let c = value >= op;
+        self.status_register.set(StatusRegister::C, c);
+
+        self.status_register.set(StatusRegister::Z, self.x == 0);
+
+        self.status_register
+            .set(StatusRegister::N, self.x & (1 << 7) > 0);
+    }
+
     fn inst_bcc(&mut self, offset: u16) {
         if !self.status_register.contains(StatusRegister::C) {
             self.branch(offset);
@@ -1294,6 +1802,11 @@ impl Cpu {
         self.status_register.set(StatusRegister::N, n);
     }
 
+    fn inst_lax(&mut self, op: u8) {
+        self.inst_lda(op);
+        self.x = op;
+    }
+
     fn inst_ldx(&mut self, op: u8) {
         self.x = op;
 
@@ -1419,6 +1932,10 @@ impl Cpu {
         self.inst_adc(op);
     }
 
+    fn inst_sax(&mut self, bus: &mut CpuBus<'_>, address: u16) {
+        bus.write(address, self.a & self.x);
+    }
+
     fn inst_sec(&mut self) {
         self.status_register.set(StatusRegister::C, true);
     }
@@ -1525,6 +2042,11 @@ impl Cpu {
     pub fn mem_dump(&mut self, bus: &mut CpuBus<'_>, addr: u16) -> u8 {
         bus.read(addr)
     }
+
+    #[cfg(feature = "debugger")]
+    pub fn mem_write(&mut self, bus: &mut CpuBus<'_>, addr: u16, data: u8) {
+        bus.write(addr, data)
+    }
 }
 
 impl CpuBus<'_> {
@@ -1535,6 +2057,8 @@ impl CpuBus<'_> {
             0x4000..=0x4013 | 0x4015 | 0x4017 => self.write_apu_register(addr, data),
             0x4014 => {
                 // https://wiki.nesdev.com/w/index.php/PPU_registers#OAMDMA
+                // The DMA goes through the regular reads, so a page of registers gets their side effects
+                // like on the real hardware. For instance, each $2007 mirror increments the VRAM address.
                 let page_begin = u16::from(data) << 8;
                 let mut buffer = [0u8; 256];
                 for offset in 0..256 {
@@ -1542,17 +2066,7 @@ impl CpuBus<'_> {
                 }
                 self.write_ppu_oam_dma(&buffer);
 
-                // FIXME: this operation takes a number of cycles to perform
-                // As per nesdev wiki:
-                // "The CPU is suspended during the transfer,
-                // which will take 513 or 514 cycles after the $4014 write tick.
-                // (1 wait state cycle while waiting for writes to complete,
-                // +1 if on an odd CPU cycle, then 256 alternating read/write cycles.)"
-                // TODO: Cpu's cycle type must be changed to a `u16`
-                // Additional cycles should be computed as follow:
-                // if cycles % 2 == 1 { 514 } else { 513 }
-                // This will requires a refactor so I'm postponing this task as I need
-                // to get PPU working ASAP.
+                // The CPU is then suspended during the transfer, see `Emulator::clock`
             }
             0x4016 => self.controller_write(data),
             0x4018..=0x401F => (), // APU and I/O functionality that is normally disabled.
@@ -1565,7 +2079,8 @@ impl CpuBus<'_> {
         match addr {
             0..=0x1FFF => self.read_ram(addr),
             0x2000..=0x3FFF => self.read_ppu_register(addr),
-            0x4000..=0x4013 | 0x4015 => self.read_apu_register(addr),
+            0x4000..=0x4013 => self.read_apu_register(addr),
+            0x4015 => self.read_apu_status(),
             0x4014 => 0, // OAMDMA is write-only
             0x4016 => self.read_controller1_snapshot(),
             0x4017 => self.read_controller2_snapshot(),
@@ -1578,6 +2093,7 @@ impl CpuBus<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::controller::Zapper;
     use crate::Apu;
     use crate::Cartridge;
     use crate::Ppu;
@@ -1591,6 +2107,7 @@ mod tests {
         controller_state: bool,
         controller1_snapshot: u8,
         controller2_snapshot: u8,
+        zapper: Option<Zapper>,
         ram: [u8; RAM_SIZE as usize],
         apu: Apu,
         cartridge: Cartridge,
@@ -1619,6 +2136,10 @@ mod tests {
         rom[16 + 0x7FFC] = 0x20;
         rom[16 + 0x7FFD] = 0x40;
 
+        mock_emu_from_rom(&rom)
+    }
+
+    fn mock_emu_from_rom(rom: &[u8]) -> MockEmulator {
         let mut emu = MockEmulator {
             cpu: Default::default(),
             controller1: 0,
@@ -1626,7 +2147,8 @@ mod tests {
             controller_state: false,
             controller1_snapshot: 0,
             controller2_snapshot: 0,
-            cartridge: Cartridge::load(&rom, None).unwrap(),
+            zapper: None,
+            cartridge: Cartridge::load(rom, None).unwrap(),
 
             ram: [0u8; RAM_SIZE as usize],
             apu: Apu::default(),
@@ -1643,13 +2165,78 @@ mod tests {
     fn execute_n(emu: &mut MockEmulator, n: usize) {
         let mut bus = borrow_cpu_bus!(emu);
         for _ in 0..n {
-            loop {
-                emu.cpu.clock(&mut bus);
-                if emu.cpu.cycles == 0 {
+            emu.cpu.step(&mut bus);
+        }
+    }
+
+    #[test]
+    fn nestest() {
+        const ROM: &[u8] = include_bytes!("../../../default_roms/nestest.nes");
+
+        // Lines of the known-good log, as (PC, A, X, Y, P, SP, CYC) before the instruction at PC runs:
+        // the start of the automation mode, the first test batch and the final RTS
+        const LOG: [(u16, u8, u8, u8, u8, u8, u64); 6] = [
+            (0xC000, 0x00, 0x00, 0x00, 0x24, 0xFD, 7),
+            (0xC5F5, 0x00, 0x00, 0x00, 0x24, 0xFD, 10),
+            (0xC5F7, 0x00, 0x00, 0x00, 0x26, 0xFD, 12),
+            (0xC5FD, 0x00, 0x00, 0x00, 0x26, 0xFD, 21),
+            (0xC72D, 0x00, 0x00, 0x00, 0x26, 0xFB, 27),
+            (0xC66E, 0x00, 0xFF, 0x15, 0x27, 0xFB, 26554),
+        ];
+
+        // In automation mode, nestest starts at $C000 with the CPU state of the first line of its log
+        let mut emu = mock_emu_from_rom(ROM);
+        emu.cpu.pc = 0xC000;
+        emu.cpu.cycles = 0;
+        let mut total_cycles = 7u64;
+
+        let mut bus = borrow_cpu_bus!(emu);
+        let mut log = LOG.iter().peekable();
+        let mut sections = 0;
+        while let Some(&&(pc, a, x, y, p, sp, cycles)) = log.peek() {
+            assert!(total_cycles <= 26554, "nestest didn't reach ${:04X}", pc);
+
+            if emu.cpu.pc == pc {
+                // The log shows the unused flag set and the break flag cleared
+                let status = (emu.cpu.status_register.bits() & !0x10) | 0x20;
+                assert_eq!(
+                    (
+                        emu.cpu.a,
+                        emu.cpu.x,
+                        emu.cpu.y,
+                        status,
+                        emu.cpu.st,
+                        total_cycles
+                    ),
+                    (a, x, y, p, sp, cycles),
+                    "state at ${:04X}",
+                    pc
+                );
+                log.next();
+                if log.peek().is_none() {
                     break;
                 }
             }
+
+            let sp = emu.cpu.st;
+            total_cycles += u64::from(emu.cpu.step(&mut bus));
+
+            // Each batch of tests is a subroutine of the main loop. nestest stores the number of the first failing
+            // test in $02 for the official opcodes and in $03 for the unofficial ones, so check them after each batch.
+            if sp == 0xFB && emu.cpu.st == 0xFD {
+                sections += 1;
+                assert_eq!(
+                    (bus.read(0x02), bus.read(0x03)),
+                    (0x00, 0x00),
+                    "failed test batch {} returning to ${:04X}",
+                    sections,
+                    emu.cpu.pc
+                );
+            }
         }
+
+        assert!(sections > 0);
+        assert_eq!((bus.read(0x02), bus.read(0x03)), (0x00, 0x00));
     }
 
     #[test]
@@ -1661,6 +2248,19 @@ mod tests {
         assert!(emu.cpu.status_register.bits & 0b1000_0000 == 0);
     }
 
+    #[test]
+    fn test_0xb1_lda_indirect_y_pointer_wraps_in_zero_page() {
+        let mut emu = mock_emu(&[0xB1, 0xFF]);
+        emu.cpu.y = 0x01;
+        emu.ram[0x00FF] = 0x34;
+        emu.ram[0x0000] = 0x02; // High byte of the pointer
+        emu.ram[0x0100] = 0x05; // Not read, the pointer doesn't cross into the stack page
+        emu.ram[0x0235] = 0x42;
+        emu.ram[0x0535] = 0x99;
+        execute_n(&mut emu, 2);
+        assert_eq!(emu.cpu.a, 0x42);
+    }
+
     #[test]
     fn test_0xaa_tax_move_a_to_x() {
         let mut emu = mock_emu(&[0xAA]);
@@ -1692,4 +2292,347 @@ mod tests {
         execute_n(&mut emu, 2);
         assert_eq!(emu.cpu.a, 0x55);
     }
+
+    #[test]
+    fn oam_dma_from_ram() {
+        let mut emu = mock_emu(&[]);
+        for (i, b) in emu.ram[0x200..0x300].iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut bus = borrow_cpu_bus!(emu);
+        bus.write(0x4014, 0x02);
+        assert!(emu.ppu.oam_bytes().iter().copied().eq(0..=255));
+    }
+
+    #[test]
+    fn oam_dma_from_registers() {
+        let mut emu = mock_emu(&[]);
+        for (i, b) in emu.name_tables.iter_mut().enumerate() {
+            *b = (i as u8).wrapping_add(0x40);
+        }
+
+        let mut bus = borrow_cpu_bus!(emu);
+        bus.write(0x2006, 0x20);
+        bus.write(0x2006, 0x00);
+        bus.write(0x4014, 0x20);
+
+        // Each of the 32 mirrors of $2007 returns the read buffer and reads the next byte
+        let oam = emu.ppu.oam_bytes();
+        assert_eq!(oam[15], 0x40);
+        assert_eq!(oam[255], 0x5E);
+
+        let mut bus = borrow_cpu_bus!(emu);
+        assert_eq!(bus.read(0x2007), 0x5F);
+
+        // The APU and I/O page is defined as well
+        bus.write(0x4014, 0x40);
+    }
+
+    #[test]
+    fn controller_bit_order() {
+        let mut emu = mock_emu(&[]);
+        emu.controller1 = crate::Buttons::SELECT.bits();
+        emu.controller2 = crate::Buttons::A.bits();
+
+        let mut bus = borrow_cpu_bus!(emu);
+
+        // While the strobe is high, A is read repeatedly
+        bus.write(0x4016, 0x01);
+        assert_eq!(bus.read(0x4016), 0);
+        assert_eq!(bus.read(0x4017), 1);
+        assert_eq!(bus.read(0x4017), 1);
+
+        bus.write(0x4016, 0x00);
+        let reads: alloc::vec::Vec<u8> = (0..10).map(|_| bus.read(0x4016)).collect();
+        assert_eq!(reads, [0, 0, 1, 0, 0, 0, 0, 0, 1, 1]);
+
+        let reads: alloc::vec::Vec<u8> = (0..8).map(|_| bus.read(0x4017)).collect();
+        assert_eq!(reads, [1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn controller_snapshot_latched_on_strobe() {
+        let mut emu = mock_emu(&[]);
+        emu.controller1 = crate::Buttons::A.bits();
+
+        let mut bus = borrow_cpu_bus!(emu);
+        bus.write(0x4016, 0x01);
+        bus.write(0x4016, 0x00);
+
+        // The live state changes after the strobe, like when the frontend polls mid-frame
+        emu.controller1 = (crate::Buttons::B | crate::Buttons::START).bits();
+        let mut bus = borrow_cpu_bus!(emu);
+        assert_eq!(bus.read(0x4016), 1);
+
+        // Writing 0 without raising the strobe doesn't reload the snapshot either
+        bus.write(0x4016, 0x00);
+        let reads: alloc::vec::Vec<u8> = (0..7).map(|_| bus.read(0x4016)).collect();
+        assert_eq!(reads, [0; 7]);
+    }
+
+    #[test]
+    fn unofficial_rmw_opcodes() {
+        let mut emu = mock_emu(&[
+            0xA9, 0x01, // LDA #$01
+            0x07, 0x10, // SLO $10
+            0x27, 0x11, // RLA $11
+            0x47, 0x12, // SRE $12
+            0x67, 0x13, // RRA $13
+            0xC7, 0x14, // DCP $14
+            0xE7, 0x15, // ISC $15
+        ]);
+        emu.ram[0x10..0x16].copy_from_slice(&[0x40, 0x0F, 0x03, 0x02, 0x83, 0x01]);
+
+        execute_n(&mut emu, 8);
+        assert_eq!(&emu.ram[0x10..0x16], &[0x80, 0x1E, 0x01, 0x81, 0x82, 0x02]);
+        assert_eq!(emu.cpu.a, 0x80);
+        assert!(emu.cpu.status_register.contains(StatusRegister::C));
+        assert!(emu.cpu.status_register.contains(StatusRegister::N));
+        assert!(!emu.cpu.status_register.contains(StatusRegister::V));
+    }
+
+    #[test]
+    fn unofficial_rmw_flags() {
+        // Opcodes for $10 and $0210, A, memory and carry before, then memory, A, and C, Z, V, N after
+        #[rustfmt::skip]
+        let cases = [
+            ([0x07, 0x0F], 0x40, 0x81, false, 0x02, 0x42, [true, false, false, false]),  // SLO
+            ([0x27, 0x2F], 0x02, 0x80, true, 0x01, 0x00, [true, true, false, false]),    // RLA
+            ([0x47, 0x4F], 0x80, 0x01, false, 0x00, 0x80, [true, false, false, true]),   // SRE
+            ([0x67, 0x6F], 0x7F, 0x01, false, 0x00, 0x80, [false, false, true, true]),   // RRA
+            ([0xC7, 0xCF], 0x10, 0x00, true, 0xFF, 0x10, [false, false, false, false]),  // DCP
+            ([0xC7, 0xCF], 0x10, 0x11, false, 0x10, 0x10, [true, true, false, false]),   // DCP
+            ([0xE7, 0xEF], 0x00, 0x7F, true, 0x80, 0x80, [false, false, true, true]),    // ISC
+        ];
+
+        for &(opcodes, a, data, carry, expected_data, expected_a, flags) in cases.iter() {
+            let programs = [[opcodes[0], 0x10, 0xEA], [opcodes[1], 0x10, 0x02]];
+            for (program, &addr) in programs.iter().zip([0x0010, 0x0210].iter()) {
+                let mut emu = mock_emu(program);
+                emu.cpu.a = a;
+                emu.cpu.status_register.set(StatusRegister::C, carry);
+                emu.ram[addr] = data;
+
+                execute_n(&mut emu, 2);
+                assert_eq!(emu.ram[addr], expected_data, "opcode {:#04x}", program[0]);
+                assert_eq!(emu.cpu.a, expected_a, "opcode {:#04x}", program[0]);
+
+                let status = emu.cpu.status_register;
+                assert_eq!(
+                    [
+                        status.contains(StatusRegister::C),
+                        status.contains(StatusRegister::Z),
+                        status.contains(StatusRegister::V),
+                        status.contains(StatusRegister::N),
+                    ],
+                    flags,
+                    "opcode {:#04x}",
+                    program[0]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_0x0b_anc_carry_from_bit_7() {
+        for &opcode in [0x0B, 0x2B].iter() {
+            let mut emu = mock_emu(&[opcode, 0x81, opcode, 0x7F]);
+            emu.cpu.a = 0xF0;
+            execute_n(&mut emu, 2);
+            assert_eq!(emu.cpu.a, 0x80);
+            assert!(emu.cpu.status_register.contains(StatusRegister::C));
+            assert!(emu.cpu.status_register.contains(StatusRegister::N));
+
+            execute_n(&mut emu, 1);
+            assert_eq!(emu.cpu.a, 0x00);
+            assert!(!emu.cpu.status_register.contains(StatusRegister::C));
+            assert!(emu.cpu.status_register.contains(StatusRegister::Z));
+        }
+    }
+
+    #[test]
+    fn test_0x4b_alr_and_then_shift_right() {
+        let mut emu = mock_emu(&[0x4B, 0x03]);
+        emu.cpu.a = 0xFF;
+        execute_n(&mut emu, 2);
+        assert_eq!(emu.cpu.a, 0x01);
+        assert!(emu.cpu.status_register.contains(StatusRegister::C));
+        assert!(!emu.cpu.status_register.contains(StatusRegister::Z));
+        assert!(!emu.cpu.status_register.contains(StatusRegister::N));
+    }
+
+    #[test]
+    fn test_0x6b_arr_carry_and_overflow_from_bits_6_and_5() {
+        let mut emu = mock_emu(&[0x6B, 0x60, 0x6B, 0xC0]);
+        emu.cpu.a = 0xFF;
+        emu.cpu.status_register.insert(StatusRegister::C);
+
+        // Rotated in through the carry: 0xB0, bit 6 clear and bit 5 set
+        execute_n(&mut emu, 2);
+        assert_eq!(emu.cpu.a, 0xB0);
+        assert!(!emu.cpu.status_register.contains(StatusRegister::C));
+        assert!(emu.cpu.status_register.contains(StatusRegister::V));
+        assert!(emu.cpu.status_register.contains(StatusRegister::N));
+
+        // 0x60, bits 6 and 5 set
+        emu.cpu.a = 0xFF;
+        execute_n(&mut emu, 1);
+        assert_eq!(emu.cpu.a, 0x60);
+        assert!(emu.cpu.status_register.contains(StatusRegister::C));
+        assert!(!emu.cpu.status_register.contains(StatusRegister::V));
+        assert!(!emu.cpu.status_register.contains(StatusRegister::N));
+    }
+
+    #[test]
+    fn test_0xcb_axs_compare_like_carry() {
+        let mut emu = mock_emu(&[0xCB, 0x05, 0xCB, 0x03]);
+        emu.cpu.a = 0x0F;
+        emu.cpu.x = 0xF3;
+        emu.cpu.status_register.insert(StatusRegister::V);
+
+        // (A & X) - imm borrows
+        execute_n(&mut emu, 2);
+        assert_eq!(emu.cpu.x, 0xFE);
+        assert_eq!(emu.cpu.a, 0x0F);
+        assert!(!emu.cpu.status_register.contains(StatusRegister::C));
+        assert!(emu.cpu.status_register.contains(StatusRegister::N));
+        assert!(emu.cpu.status_register.contains(StatusRegister::V));
+
+        // The borrow isn't used as input
+        emu.cpu.x = 0x03;
+        execute_n(&mut emu, 1);
+        assert_eq!(emu.cpu.x, 0x00);
+        assert!(emu.cpu.status_register.contains(StatusRegister::C));
+        assert!(emu.cpu.status_register.contains(StatusRegister::Z));
+    }
+
+    // Number of cycles taken by the next instruction
+    fn next_instruction_cycles(emu: &mut MockEmulator) -> u16 {
+        let mut bus = borrow_cpu_bus!(emu);
+        emu.cpu.step(&mut bus)
+    }
+
+    #[test]
+    fn skip_nops_fetch_their_operands() {
+        let mut emu = mock_emu(&[
+            0x80, 0xFF, // NOP #$FF
+            0x04, 0x10, // NOP $10
+            0x14, 0x10, // NOP $10,X
+            0x0C, 0x34, 0x12, // NOP $1234
+            0x1C, 0xFF, 0x02, // NOP $02FF,X
+            0xFC, 0x00, 0x02, // NOP $0200,X
+            0xA9, 0x42, // LDA #$42
+        ]);
+        emu.cpu.x = 0x01;
+        execute_n(&mut emu, 1);
+
+        let expected = [
+            (0x4022, 2),
+            (0x4024, 3),
+            (0x4026, 4),
+            (0x4029, 4),
+            (0x402C, 5), // Page crossed
+            (0x402F, 4),
+        ];
+        for &(pc, cycles) in expected.iter() {
+            assert_eq!(next_instruction_cycles(&mut emu), cycles);
+            assert_eq!(emu.cpu.pc, pc);
+        }
+
+        execute_n(&mut emu, 1);
+        assert_eq!(emu.cpu.a, 0x42);
+    }
+
+    #[test]
+    fn test_0xa7_lax_zero_page_load_data() {
+        let mut emu = mock_emu(&[0xA7, 0x10]);
+        emu.ram[0x10] = 0x85;
+        execute_n(&mut emu, 2);
+        assert_eq!(emu.cpu.a, 0x85);
+        assert_eq!(emu.cpu.x, 0x85);
+        assert!(emu.cpu.status_register.bits & 0b0000_0010 == 0);
+        assert!(emu.cpu.status_register.bits & 0b1000_0000 != 0);
+    }
+
+    #[test]
+    fn test_0xb7_lax_zero_page_y_zero_flag() {
+        let mut emu = mock_emu(&[0xB7, 0x10]);
+        emu.cpu.a = 0x12;
+        emu.cpu.x = 0x34;
+        emu.cpu.y = 0x05;
+        execute_n(&mut emu, 2);
+        assert_eq!(emu.cpu.a, 0);
+        assert_eq!(emu.cpu.x, 0);
+        assert!(emu.cpu.status_register.bits & 0b0000_0010 != 0);
+        assert!(emu.cpu.status_register.bits & 0b1000_0000 == 0);
+    }
+
+    #[test]
+    fn lax_indirect_modes() {
+        let mut emu = mock_emu(&[
+            0xA3, 0x20, // LAX ($20,X)
+            0xB3, 0x30, // LAX ($30),Y
+        ]);
+        emu.cpu.x = 0x02;
+        emu.cpu.y = 0x01;
+        emu.ram[0x22..0x24].copy_from_slice(&[0x00, 0x03]);
+        emu.ram[0x30..0x32].copy_from_slice(&[0x10, 0x03]);
+        emu.ram[0x300] = 0x11;
+        emu.ram[0x311] = 0x22;
+
+        execute_n(&mut emu, 2);
+        assert_eq!((emu.cpu.a, emu.cpu.x), (0x11, 0x11));
+        execute_n(&mut emu, 1);
+        assert_eq!((emu.cpu.a, emu.cpu.x), (0x22, 0x22));
+    }
+
+    #[test]
+    fn lax_page_cross_cycles_match_lda() {
+        for &(aby, izy) in &[(0xB9, 0xB1), (0xBF, 0xB3)] {
+            // LDA or LAX, without then with a page cross
+            let mut emu = mock_emu(&[
+                aby, 0xFE, 0x00, // $00FE,Y
+                aby, 0xFF, 0x00, // $00FF,Y
+                izy, 0x40, // ($40),Y
+                izy, 0x42, // ($42),Y
+            ]);
+            emu.cpu.y = 0x01;
+            emu.ram[0x40..0x44].copy_from_slice(&[0x00, 0x02, 0xFF, 0x02]);
+            emu.ram[0x100] = 0x42;
+            execute_n(&mut emu, 1);
+
+            assert_eq!(next_instruction_cycles(&mut emu), 4);
+            assert_eq!(next_instruction_cycles(&mut emu), 5);
+            assert_eq!(emu.cpu.a, 0x42);
+            assert_eq!(next_instruction_cycles(&mut emu), 5);
+            assert_eq!(next_instruction_cycles(&mut emu), 6);
+        }
+    }
+
+    #[test]
+    fn sax_stores_a_and_x() {
+        let mut emu = mock_emu(&[
+            0x87, 0x10, // SAX $10
+            0x97, 0x10, // SAX $10,Y
+            0x8F, 0x00, 0x03, // SAX $0300
+            0x83, 0x20, // SAX ($20,X)
+        ]);
+        emu.cpu.a = 0xF0;
+        emu.cpu.x = 0x3C;
+        emu.cpu.y = 0x01;
+        emu.ram[0x5C..0x5E].copy_from_slice(&[0x10, 0x03]);
+        execute_n(&mut emu, 1);
+        let status = emu.cpu.status_register;
+
+        execute_n(&mut emu, 4);
+        let mut bus = borrow_cpu_bus!(emu);
+        assert_eq!(bus.read(0x10), 0x30);
+        assert_eq!(bus.read(0x11), 0x30);
+        assert_eq!(bus.read(0x300), 0x30);
+        assert_eq!(bus.read(0x310), 0x30);
+
+        // The flags are not affected
+        assert_eq!(emu.cpu.status_register, status);
+    }
 }
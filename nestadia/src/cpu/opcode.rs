@@ -8,99 +8,154 @@ use num_enum::TryFromPrimitive;
 pub enum Opcode {
     Brk = 0x00,
     OraIndX = 0x01,
+    SloIndX = 0x03,
+    NopZp04 = 0x04,
     OraZp = 0x05,
     AslZp = 0x06,
+    SloZp = 0x07,
     Php = 0x08,
     OraImm = 0x09,
     AslAcc = 0x0a,
+    AncImm = 0x0b,
+    NopAbs0C = 0x0c,
     OraAbs = 0x0d,
     AslAbs = 0x0e,
+    SloAbs = 0x0f,
 
     Bpl = 0x10,
     OraIndY = 0x11,
+    SloIndY = 0x13,
+    NopZpX14 = 0x14,
     OraZpX = 0x15,
     AslZpX = 0x16,
+    SloZpX = 0x17,
     Clc = 0x18,
     OraAbsY = 0x19,
+    Nop1A = 0x1a,
+    SloAbsY = 0x1b,
+    NopAbsX1C = 0x1c,
     OraAbsX = 0x1d,
     AslAbsX = 0x1e,
+    SloAbsX = 0x1f,
 
     JsrAbs = 0x20,
     AndIndX = 0x21,
+    RlaIndX = 0x23,
     BitZp = 0x24,
     AndZp = 0x25,
     RolZp = 0x26,
+    RlaZp = 0x27,
     Plp = 0x28,
     AndImm = 0x29,
     RolAcc = 0x2a,
+    AncImm2 = 0x2b,
     BitAbs = 0x2c,
     AndAbs = 0x2d,
     RolAbs = 0x2e,
+    RlaAbs = 0x2f,
 
     Bmi = 0x30,
     AndIndY = 0x31,
+    RlaIndY = 0x33,
+    NopZpX34 = 0x34,
     AndZpX = 0x35,
     RolZpX = 0x36,
+    RlaZpX = 0x37,
     Sec = 0x38,
     AndAbsY = 0x39,
+    Nop3A = 0x3a,
+    RlaAbsY = 0x3b,
+    NopAbsX3C = 0x3c,
     AndAbsX = 0x3d,
     RolAbsX = 0x3e,
+    RlaAbsX = 0x3f,
 
     Rti = 0x40,
     EorIndX = 0x41,
+    SreIndX = 0x43,
+    NopZp44 = 0x44,
     EorZp = 0x45,
     LsrZp = 0x46,
+    SreZp = 0x47,
     Pha = 0x48,
     EorImm = 0x49,
     LsrAcc = 0x4a,
+    AlrImm = 0x4b,
     JmpAbs = 0x4c,
     EorAbs = 0x4d,
     LsrAbs = 0x4e,
+    SreAbs = 0x4f,
 
     Bvc = 0x50,
     EorIndY = 0x51,
+    SreIndY = 0x53,
+    NopZpX54 = 0x54,
     EorZpX = 0x55,
     LsrZpX = 0x56,
+    SreZpX = 0x57,
     Cli = 0x58,
     EorAbsY = 0x59,
+    Nop5A = 0x5a,
+    SreAbsY = 0x5b,
+    NopAbsX5C = 0x5c,
     EorAbsX = 0x5d,
     LsrAbsX = 0x5e,
+    SreAbsX = 0x5f,
 
     Rts = 0x60,
     AdcIndX = 0x61,
+    RraIndX = 0x63,
+    NopZp64 = 0x64,
     AdcZp = 0x65,
     RorZp = 0x66,
+    RraZp = 0x67,
     Pla = 0x68,
     AdcImm = 0x69,
     RorAcc = 0x6a,
+    ArrImm = 0x6b,
     JmpInd = 0x6c,
     AdcAbs = 0x6d,
     RorAbs = 0x6e,
+    RraAbs = 0x6f,
 
     Bvs = 0x70,
     AdcIndY = 0x71,
+    RraIndY = 0x73,
+    NopZpX74 = 0x74,
     AdcZpX = 0x75,
     RorZpX = 0x76,
+    RraZpX = 0x77,
     Sei = 0x78,
     AdcAbsY = 0x79,
+    Nop7A = 0x7a,
+    RraAbsY = 0x7b,
+    NopAbsX7C = 0x7c,
     AdcAbsX = 0x7d,
     RorAbsX = 0x7e,
+    RraAbsX = 0x7f,
 
+    NopImm80 = 0x80,
     StaIndX = 0x81,
+    NopImm82 = 0x82,
+    SaxIndX = 0x83,
     StyZp = 0x84,
     StaZp = 0x85,
     StxZp = 0x86,
+    SaxZp = 0x87,
     Dey = 0x88,
+    NopImm89 = 0x89,
     Txa = 0x8a,
     StyAbs = 0x8c,
     StaAbs = 0x8d,
     StxAbs = 0x8e,
+    SaxAbs = 0x8f,
 
     Bcc = 0x90,
     StaIndY = 0x91,
     StyZpX = 0x94,
     StaZpX = 0x95,
     StxZpY = 0x96,
+    SaxZpY = 0x97,
     Tya = 0x98,
     StaAbsY = 0x99,
     Txs = 0x9a,
@@ -109,69 +164,99 @@ pub enum Opcode {
     LdyImm = 0xa0,
     LdaIndX = 0xa1,
     LdxImm = 0xa2,
+    LaxIndX = 0xa3,
     LdyZp = 0xa4,
     LdaZp = 0xa5,
     LdxZp = 0xa6,
+    LaxZp = 0xa7,
     Tay = 0xa8,
     LdaImm = 0xa9,
     Tax = 0xaa,
     LdyAbs = 0xac,
     LdaAbs = 0xad,
     LdxAbs = 0xae,
+    LaxAbs = 0xaf,
 
     Bcs = 0xb0,
     LdaIndY = 0xb1,
+    LaxIndY = 0xb3,
     LdyZpX = 0xb4,
     LdaZpX = 0xb5,
     LdxZpY = 0xb6,
+    LaxZpY = 0xb7,
     Clv = 0xb8,
     LdaAbsY = 0xb9,
     Tsx = 0xba,
     LdyAbsX = 0xbc,
     LdaAbsX = 0xbd,
     LdxAbsY = 0xbe,
+    LaxAbsY = 0xbf,
 
     CpyImm = 0xc0,
     CmpIndX = 0xc1,
+    NopImmC2 = 0xc2,
+    DcpIndX = 0xc3,
     CpyZp = 0xc4,
     CmpZp = 0xc5,
     DecZp = 0xc6,
+    DcpZp = 0xc7,
     Iny = 0xc8,
     CmpImm = 0xc9,
     Dex = 0xca,
+    AxsImm = 0xcb,
     CpyAbs = 0xcc,
     CmpAbs = 0xcd,
     DecAbs = 0xce,
+    DcpAbs = 0xcf,
 
     Bne = 0xd0,
     CmpIndY = 0xd1,
+    DcpIndY = 0xd3,
+    NopZpXD4 = 0xd4,
     CmpZpX = 0xd5,
     DecZpX = 0xd6,
+    DcpZpX = 0xd7,
     Cld = 0xd8,
     CmpAbsY = 0xd9,
+    NopDA = 0xda,
+    DcpAbsY = 0xdb,
+    NopAbsXDC = 0xdc,
     CmpAbsX = 0xdd,
     DecAbsX = 0xde,
+    DcpAbsX = 0xdf,
 
     CpxImm = 0xe0,
     SbcIndX = 0xe1,
+    NopImmE2 = 0xe2,
+    IscIndX = 0xe3,
     CpxZp = 0xe4,
     SbcZp = 0xe5,
     IncZp = 0xe6,
+    IscZp = 0xe7,
     Inx = 0xe8,
     SbcImm = 0xe9,
     Nop = 0xea,
+    SbcImmEB = 0xeb,
     CpxAbs = 0xec,
     SbcAbs = 0xed,
     IncAbs = 0xee,
+    IscAbs = 0xef,
 
     Beq = 0xf0,
     SbcIndY = 0xf1,
+    IscIndY = 0xf3,
+    NopZpXF4 = 0xf4,
     SbcZpX = 0xf5,
     IncZpX = 0xf6,
+    IscZpX = 0xf7,
     Sed = 0xf8,
     SbcAbsY = 0xf9,
+    NopFA = 0xfa,
+    IscAbsY = 0xfb,
+    NopAbsXFC = 0xfc,
     SbcAbsX = 0xfd,
     IncAbsX = 0xfe,
+    IscAbsX = 0xff,
 }
 
 impl Opcode {
@@ -179,99 +264,154 @@ impl Opcode {
         match self {
             Opcode::Brk => 7,
             Opcode::OraIndX => 6,
+            Opcode::SloIndX => 8,
+            Opcode::NopZp04 => 3,
             Opcode::OraZp => 3,
             Opcode::AslZp => 5,
+            Opcode::SloZp => 5,
             Opcode::Php => 3,
             Opcode::OraImm => 2,
             Opcode::AslAcc => 2,
+            Opcode::AncImm => 2,
+            Opcode::NopAbs0C => 4,
             Opcode::OraAbs => 4,
             Opcode::AslAbs => 6,
+            Opcode::SloAbs => 6,
 
             Opcode::Bpl => 2,
             Opcode::OraIndY => 5,
+            Opcode::SloIndY => 8,
+            Opcode::NopZpX14 => 4,
             Opcode::OraZpX => 4,
             Opcode::AslZpX => 6,
+            Opcode::SloZpX => 6,
             Opcode::Clc => 2,
             Opcode::OraAbsY => 4,
+            Opcode::Nop1A => 2,
+            Opcode::SloAbsY => 7,
+            Opcode::NopAbsX1C => 4,
             Opcode::OraAbsX => 4,
             Opcode::AslAbsX => 7,
+            Opcode::SloAbsX => 7,
 
             Opcode::JsrAbs => 6,
             Opcode::AndIndX => 6,
+            Opcode::RlaIndX => 8,
             Opcode::BitZp => 3,
             Opcode::AndZp => 3,
             Opcode::RolZp => 5,
+            Opcode::RlaZp => 5,
             Opcode::Plp => 4,
             Opcode::AndImm => 2,
             Opcode::RolAcc => 2,
+            Opcode::AncImm2 => 2,
             Opcode::BitAbs => 4,
             Opcode::AndAbs => 4,
             Opcode::RolAbs => 6,
+            Opcode::RlaAbs => 6,
 
             Opcode::Bmi => 2,
             Opcode::AndIndY => 5,
+            Opcode::RlaIndY => 8,
+            Opcode::NopZpX34 => 4,
             Opcode::AndZpX => 4,
             Opcode::RolZpX => 6,
+            Opcode::RlaZpX => 6,
             Opcode::Sec => 2,
             Opcode::AndAbsY => 4,
+            Opcode::Nop3A => 2,
+            Opcode::RlaAbsY => 7,
+            Opcode::NopAbsX3C => 4,
             Opcode::AndAbsX => 4,
             Opcode::RolAbsX => 7,
+            Opcode::RlaAbsX => 7,
 
             Opcode::Rti => 6,
             Opcode::EorIndX => 6,
+            Opcode::SreIndX => 8,
+            Opcode::NopZp44 => 3,
             Opcode::EorZp => 3,
             Opcode::LsrZp => 5,
+            Opcode::SreZp => 5,
             Opcode::Pha => 3,
             Opcode::EorImm => 2,
             Opcode::LsrAcc => 2,
+            Opcode::AlrImm => 2,
             Opcode::JmpAbs => 3,
             Opcode::EorAbs => 4,
             Opcode::LsrAbs => 6,
+            Opcode::SreAbs => 6,
 
             Opcode::Bvc => 2,
             Opcode::EorIndY => 5,
+            Opcode::SreIndY => 8,
+            Opcode::NopZpX54 => 4,
             Opcode::EorZpX => 4,
             Opcode::LsrZpX => 6,
+            Opcode::SreZpX => 6,
             Opcode::Cli => 2,
             Opcode::EorAbsY => 4,
+            Opcode::Nop5A => 2,
+            Opcode::SreAbsY => 7,
+            Opcode::NopAbsX5C => 4,
             Opcode::EorAbsX => 4,
             Opcode::LsrAbsX => 7,
+            Opcode::SreAbsX => 7,
 
             Opcode::Rts => 6,
             Opcode::AdcIndX => 6,
+            Opcode::RraIndX => 8,
+            Opcode::NopZp64 => 3,
             Opcode::AdcZp => 3,
             Opcode::RorZp => 5,
+            Opcode::RraZp => 5,
             Opcode::Pla => 4,
             Opcode::AdcImm => 2,
             Opcode::RorAcc => 2,
+            Opcode::ArrImm => 2,
             Opcode::JmpInd => 5,
             Opcode::AdcAbs => 4,
             Opcode::RorAbs => 6,
+            Opcode::RraAbs => 6,
 
             Opcode::Bvs => 2,
             Opcode::AdcIndY => 5,
+            Opcode::RraIndY => 8,
+            Opcode::NopZpX74 => 4,
             Opcode::AdcZpX => 4,
             Opcode::RorZpX => 6,
+            Opcode::RraZpX => 6,
             Opcode::Sei => 2,
             Opcode::AdcAbsY => 4,
+            Opcode::Nop7A => 2,
+            Opcode::RraAbsY => 7,
+            Opcode::NopAbsX7C => 4,
             Opcode::AdcAbsX => 4,
             Opcode::RorAbsX => 7,
+            Opcode::RraAbsX => 7,
 
+            Opcode::NopImm80 => 2,
             Opcode::StaIndX => 6,
+            Opcode::NopImm82 => 2,
+            Opcode::SaxIndX => 6,
             Opcode::StyZp => 3,
             Opcode::StaZp => 3,
             Opcode::StxZp => 3,
+            Opcode::SaxZp => 3,
             Opcode::Dey => 2,
+            Opcode::NopImm89 => 2,
             Opcode::Txa => 2,
             Opcode::StyAbs => 4,
             Opcode::StaAbs => 4,
             Opcode::StxAbs => 4,
+            Opcode::SaxAbs => 4,
 
             Opcode::Bcc => 2,
             Opcode::StaIndY => 6,
             Opcode::StyZpX => 4,
             Opcode::StaZpX => 4,
             Opcode::StxZpY => 4,
+            Opcode::SaxZpY => 4,
             Opcode::Tya => 2,
             Opcode::StaAbsY => 5,
             Opcode::Txs => 2,
@@ -280,69 +420,99 @@ impl Opcode {
             Opcode::LdyImm => 2,
             Opcode::LdaIndX => 6,
             Opcode::LdxImm => 2,
+            Opcode::LaxIndX => 6,
             Opcode::LdyZp => 3,
             Opcode::LdaZp => 3,
             Opcode::LdxZp => 3,
+            Opcode::LaxZp => 3,
             Opcode::Tay => 2,
             Opcode::LdaImm => 2,
             Opcode::Tax => 2,
             Opcode::LdyAbs => 4,
             Opcode::LdaAbs => 4,
             Opcode::LdxAbs => 4,
+            Opcode::LaxAbs => 4,
 
             Opcode::Bcs => 2,
             Opcode::LdaIndY => 5,
+            Opcode::LaxIndY => 5,
             Opcode::LdyZpX => 4,
             Opcode::LdaZpX => 4,
             Opcode::LdxZpY => 4,
+            Opcode::LaxZpY => 4,
             Opcode::Clv => 2,
             Opcode::LdaAbsY => 4,
             Opcode::Tsx => 2,
             Opcode::LdyAbsX => 4,
             Opcode::LdaAbsX => 4,
             Opcode::LdxAbsY => 4,
+            Opcode::LaxAbsY => 4,
 
             Opcode::CpyImm => 2,
             Opcode::CmpIndX => 6,
+            Opcode::NopImmC2 => 2,
+            Opcode::DcpIndX => 8,
             Opcode::CpyZp => 3,
             Opcode::CmpZp => 3,
             Opcode::DecZp => 5,
+            Opcode::DcpZp => 5,
             Opcode::Iny => 2,
             Opcode::CmpImm => 2,
             Opcode::Dex => 2,
+            Opcode::AxsImm => 2,
             Opcode::CpyAbs => 4,
             Opcode::CmpAbs => 4,
             Opcode::DecAbs => 6,
+            Opcode::DcpAbs => 6,
 
             Opcode::Bne => 2,
             Opcode::CmpIndY => 5,
+            Opcode::DcpIndY => 8,
+            Opcode::NopZpXD4 => 4,
             Opcode::CmpZpX => 4,
             Opcode::DecZpX => 6,
+            Opcode::DcpZpX => 6,
             Opcode::Cld => 2,
             Opcode::CmpAbsY => 4,
+            Opcode::NopDA => 2,
+            Opcode::DcpAbsY => 7,
+            Opcode::NopAbsXDC => 4,
             Opcode::CmpAbsX => 4,
             Opcode::DecAbsX => 7,
+            Opcode::DcpAbsX => 7,
 
             Opcode::CpxImm => 2,
             Opcode::SbcIndX => 6,
+            Opcode::NopImmE2 => 2,
+            Opcode::IscIndX => 8,
             Opcode::CpxZp => 3,
             Opcode::SbcZp => 3,
             Opcode::IncZp => 5,
+            Opcode::IscZp => 5,
             Opcode::Inx => 2,
             Opcode::SbcImm => 2,
             Opcode::Nop => 2,
+            Opcode::SbcImmEB => 2,
             Opcode::CpxAbs => 4,
             Opcode::SbcAbs => 4,
             Opcode::IncAbs => 6,
+            Opcode::IscAbs => 6,
 
             Opcode::Beq => 2,
             Opcode::SbcIndY => 5,
+            Opcode::IscIndY => 8,
+            Opcode::NopZpXF4 => 4,
             Opcode::SbcZpX => 4,
             Opcode::IncZpX => 6,
+            Opcode::IscZpX => 6,
             Opcode::Sed => 2,
             Opcode::SbcAbsY => 4,
+            Opcode::NopFA => 2,
+            Opcode::IscAbsY => 7,
+            Opcode::NopAbsXFC => 4,
             Opcode::SbcAbsX => 4,
             Opcode::IncAbsX => 7,
+            Opcode::IscAbsX => 7,
         }
     }
 
@@ -351,99 +521,154 @@ impl Opcode {
         match self {
             Opcode::Brk => AddressingMode::Implied,
             Opcode::OraIndX => AddressingMode::IndirectX,
+            Opcode::SloIndX => AddressingMode::IndirectX,
+            Opcode::NopZp04 => AddressingMode::ZeroPage,
             Opcode::OraZp => AddressingMode::ZeroPage,
             Opcode::AslZp => AddressingMode::ZeroPage,
+            Opcode::SloZp => AddressingMode::ZeroPage,
             Opcode::Php => AddressingMode::Implied,
             Opcode::OraImm => AddressingMode::Immediate,
             Opcode::AslAcc => AddressingMode::Accumulator,
+            Opcode::AncImm => AddressingMode::Immediate,
+            Opcode::NopAbs0C => AddressingMode::Absolute,
             Opcode::OraAbs => AddressingMode::Absolute,
             Opcode::AslAbs => AddressingMode::Absolute,
+            Opcode::SloAbs => AddressingMode::Absolute,
 
             Opcode::Bpl => AddressingMode::Relative,
             Opcode::OraIndY => AddressingMode::IndirectY,
+            Opcode::SloIndY => AddressingMode::IndirectY,
+            Opcode::NopZpX14 => AddressingMode::ZeroPageX,
             Opcode::OraZpX => AddressingMode::ZeroPageX,
             Opcode::AslZpX => AddressingMode::ZeroPageX,
+            Opcode::SloZpX => AddressingMode::ZeroPageX,
             Opcode::Clc => AddressingMode::Implied,
             Opcode::OraAbsY => AddressingMode::AbsoluteY,
+            Opcode::Nop1A => AddressingMode::Implied,
+            Opcode::SloAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopAbsX1C => AddressingMode::AbsoluteX,
             Opcode::OraAbsX => AddressingMode::AbsoluteX,
             Opcode::AslAbsX => AddressingMode::AbsoluteX,
+            Opcode::SloAbsX => AddressingMode::AbsoluteX,
 
             Opcode::JsrAbs => AddressingMode::Absolute,
             Opcode::AndIndX => AddressingMode::IndirectX,
+            Opcode::RlaIndX => AddressingMode::IndirectX,
             Opcode::BitZp => AddressingMode::ZeroPage,
             Opcode::AndZp => AddressingMode::ZeroPage,
             Opcode::RolZp => AddressingMode::ZeroPage,
+            Opcode::RlaZp => AddressingMode::ZeroPage,
             Opcode::Plp => AddressingMode::Implied,
             Opcode::AndImm => AddressingMode::Immediate,
             Opcode::RolAcc => AddressingMode::Accumulator,
+            Opcode::AncImm2 => AddressingMode::Immediate,
             Opcode::BitAbs => AddressingMode::Absolute,
             Opcode::AndAbs => AddressingMode::Absolute,
             Opcode::RolAbs => AddressingMode::Absolute,
+            Opcode::RlaAbs => AddressingMode::Absolute,
 
             Opcode::Bmi => AddressingMode::Relative,
             Opcode::AndIndY => AddressingMode::IndirectY,
+            Opcode::RlaIndY => AddressingMode::IndirectY,
+            Opcode::NopZpX34 => AddressingMode::ZeroPageX,
             Opcode::AndZpX => AddressingMode::ZeroPageX,
             Opcode::RolZpX => AddressingMode::ZeroPageX,
+            Opcode::RlaZpX => AddressingMode::ZeroPageX,
             Opcode::Sec => AddressingMode::Implied,
             Opcode::AndAbsY => AddressingMode::AbsoluteY,
+            Opcode::Nop3A => AddressingMode::Implied,
+            Opcode::RlaAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopAbsX3C => AddressingMode::AbsoluteX,
             Opcode::AndAbsX => AddressingMode::AbsoluteX,
             Opcode::RolAbsX => AddressingMode::AbsoluteX,
+            Opcode::RlaAbsX => AddressingMode::AbsoluteX,
 
             Opcode::Rti => AddressingMode::Implied,
             Opcode::EorIndX => AddressingMode::IndirectX,
+            Opcode::SreIndX => AddressingMode::IndirectX,
+            Opcode::NopZp44 => AddressingMode::ZeroPage,
             Opcode::EorZp => AddressingMode::ZeroPage,
             Opcode::LsrZp => AddressingMode::ZeroPage,
+            Opcode::SreZp => AddressingMode::ZeroPage,
             Opcode::Pha => AddressingMode::Implied,
             Opcode::EorImm => AddressingMode::Immediate,
             Opcode::LsrAcc => AddressingMode::Accumulator,
+            Opcode::AlrImm => AddressingMode::Immediate,
             Opcode::JmpAbs => AddressingMode::Absolute,
             Opcode::EorAbs => AddressingMode::Absolute,
             Opcode::LsrAbs => AddressingMode::Absolute,
+            Opcode::SreAbs => AddressingMode::Absolute,
 
             Opcode::Bvc => AddressingMode::Relative,
             Opcode::EorIndY => AddressingMode::IndirectY,
+            Opcode::SreIndY => AddressingMode::IndirectY,
+            Opcode::NopZpX54 => AddressingMode::ZeroPageX,
             Opcode::EorZpX => AddressingMode::ZeroPageX,
             Opcode::LsrZpX => AddressingMode::ZeroPageX,
+            Opcode::SreZpX => AddressingMode::ZeroPageX,
             Opcode::Cli => AddressingMode::Implied,
             Opcode::EorAbsY => AddressingMode::AbsoluteY,
+            Opcode::Nop5A => AddressingMode::Implied,
+            Opcode::SreAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopAbsX5C => AddressingMode::AbsoluteX,
             Opcode::EorAbsX => AddressingMode::AbsoluteX,
             Opcode::LsrAbsX => AddressingMode::AbsoluteX,
+            Opcode::SreAbsX => AddressingMode::AbsoluteX,
 
             Opcode::Rts => AddressingMode::Implied,
             Opcode::AdcIndX => AddressingMode::IndirectX,
+            Opcode::RraIndX => AddressingMode::IndirectX,
+            Opcode::NopZp64 => AddressingMode::ZeroPage,
             Opcode::AdcZp => AddressingMode::ZeroPage,
             Opcode::RorZp => AddressingMode::ZeroPage,
+            Opcode::RraZp => AddressingMode::ZeroPage,
             Opcode::Pla => AddressingMode::Implied,
             Opcode::AdcImm => AddressingMode::Immediate,
             Opcode::RorAcc => AddressingMode::Accumulator,
+            Opcode::ArrImm => AddressingMode::Immediate,
             Opcode::JmpInd => AddressingMode::Indirect,
             Opcode::AdcAbs => AddressingMode::Absolute,
             Opcode::RorAbs => AddressingMode::Absolute,
+            Opcode::RraAbs => AddressingMode::Absolute,
 
             Opcode::Bvs => AddressingMode::Relative,
             Opcode::AdcIndY => AddressingMode::IndirectY,
+            Opcode::RraIndY => AddressingMode::IndirectY,
+            Opcode::NopZpX74 => AddressingMode::ZeroPageX,
             Opcode::AdcZpX => AddressingMode::ZeroPageX,
             Opcode::RorZpX => AddressingMode::ZeroPageX,
+            Opcode::RraZpX => AddressingMode::ZeroPageX,
             Opcode::Sei => AddressingMode::Implied,
             Opcode::AdcAbsY => AddressingMode::AbsoluteY,
+            Opcode::Nop7A => AddressingMode::Implied,
+            Opcode::RraAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopAbsX7C => AddressingMode::AbsoluteX,
             Opcode::AdcAbsX => AddressingMode::AbsoluteX,
             Opcode::RorAbsX => AddressingMode::AbsoluteX,
+            Opcode::RraAbsX => AddressingMode::AbsoluteX,
 
+            Opcode::NopImm80 => AddressingMode::Immediate,
             Opcode::StaIndX => AddressingMode::IndirectX,
+            Opcode::NopImm82 => AddressingMode::Immediate,
+            Opcode::SaxIndX => AddressingMode::IndirectX,
             Opcode::StyZp => AddressingMode::ZeroPage,
             Opcode::StaZp => AddressingMode::ZeroPage,
             Opcode::StxZp => AddressingMode::ZeroPage,
+            Opcode::SaxZp => AddressingMode::ZeroPage,
             Opcode::Dey => AddressingMode::Implied,
+            Opcode::NopImm89 => AddressingMode::Immediate,
             Opcode::Txa => AddressingMode::Implied,
             Opcode::StyAbs => AddressingMode::Absolute,
             Opcode::StaAbs => AddressingMode::Absolute,
             Opcode::StxAbs => AddressingMode::Absolute,
+            Opcode::SaxAbs => AddressingMode::Absolute,
 
             Opcode::Bcc => AddressingMode::Relative,
             Opcode::StaIndY => AddressingMode::IndirectY,
             Opcode::StyZpX => AddressingMode::ZeroPageX,
             Opcode::StaZpX => AddressingMode::ZeroPageX,
             Opcode::StxZpY => AddressingMode::ZeroPageY,
+            Opcode::SaxZpY => AddressingMode::ZeroPageY,
             Opcode::Tya => AddressingMode::Implied,
             Opcode::StaAbsY => AddressingMode::AbsoluteY,
             Opcode::Txs => AddressingMode::Implied,
@@ -452,69 +677,99 @@ impl Opcode {
             Opcode::LdyImm => AddressingMode::Immediate,
             Opcode::LdaIndX => AddressingMode::IndirectX,
             Opcode::LdxImm => AddressingMode::Immediate,
+            Opcode::LaxIndX => AddressingMode::IndirectX,
             Opcode::LdyZp => AddressingMode::ZeroPage,
             Opcode::LdaZp => AddressingMode::ZeroPage,
             Opcode::LdxZp => AddressingMode::ZeroPage,
+            Opcode::LaxZp => AddressingMode::ZeroPage,
             Opcode::Tay => AddressingMode::Implied,
             Opcode::LdaImm => AddressingMode::Immediate,
             Opcode::Tax => AddressingMode::Implied,
             Opcode::LdyAbs => AddressingMode::Absolute,
             Opcode::LdaAbs => AddressingMode::Absolute,
             Opcode::LdxAbs => AddressingMode::Absolute,
+            Opcode::LaxAbs => AddressingMode::Absolute,
 
             Opcode::Bcs => AddressingMode::Relative,
             Opcode::LdaIndY => AddressingMode::IndirectY,
+            Opcode::LaxIndY => AddressingMode::IndirectY,
             Opcode::LdyZpX => AddressingMode::ZeroPageX,
             Opcode::LdaZpX => AddressingMode::ZeroPageX,
             Opcode::LdxZpY => AddressingMode::ZeroPageY,
+            Opcode::LaxZpY => AddressingMode::ZeroPageY,
             Opcode::Clv => AddressingMode::Implied,
             Opcode::LdaAbsY => AddressingMode::AbsoluteY,
             Opcode::Tsx => AddressingMode::Implied,
             Opcode::LdyAbsX => AddressingMode::AbsoluteX,
             Opcode::LdaAbsX => AddressingMode::AbsoluteX,
             Opcode::LdxAbsY => AddressingMode::AbsoluteY,
+            Opcode::LaxAbsY => AddressingMode::AbsoluteY,
 
             Opcode::CpyImm => AddressingMode::Immediate,
             Opcode::CmpIndX => AddressingMode::IndirectX,
+            Opcode::NopImmC2 => AddressingMode::Immediate,
+            Opcode::DcpIndX => AddressingMode::IndirectX,
             Opcode::CpyZp => AddressingMode::ZeroPage,
             Opcode::CmpZp => AddressingMode::ZeroPage,
             Opcode::DecZp => AddressingMode::ZeroPage,
+            Opcode::DcpZp => AddressingMode::ZeroPage,
             Opcode::Iny => AddressingMode::Implied,
             Opcode::CmpImm => AddressingMode::Immediate,
             Opcode::Dex => AddressingMode::Implied,
+            Opcode::AxsImm => AddressingMode::Immediate,
             Opcode::CpyAbs => AddressingMode::Absolute,
             Opcode::CmpAbs => AddressingMode::Absolute,
             Opcode::DecAbs => AddressingMode::Absolute,
+            Opcode::DcpAbs => AddressingMode::Absolute,
 
             Opcode::Bne => AddressingMode::Relative,
             Opcode::CmpIndY => AddressingMode::IndirectY,
+            Opcode::DcpIndY => AddressingMode::IndirectY,
+            Opcode::NopZpXD4 => AddressingMode::ZeroPageX,
             Opcode::CmpZpX => AddressingMode::ZeroPageX,
             Opcode::DecZpX => AddressingMode::ZeroPageX,
+            Opcode::DcpZpX => AddressingMode::ZeroPageX,
             Opcode::Cld => AddressingMode::Implied,
             Opcode::CmpAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopDA => AddressingMode::Implied,
+            Opcode::DcpAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopAbsXDC => AddressingMode::AbsoluteX,
             Opcode::CmpAbsX => AddressingMode::AbsoluteX,
             Opcode::DecAbsX => AddressingMode::AbsoluteX,
+            Opcode::DcpAbsX => AddressingMode::AbsoluteX,
 
             Opcode::CpxImm => AddressingMode::Immediate,
             Opcode::SbcIndX => AddressingMode::IndirectX,
+            Opcode::NopImmE2 => AddressingMode::Immediate,
+            Opcode::IscIndX => AddressingMode::IndirectX,
             Opcode::CpxZp => AddressingMode::ZeroPage,
             Opcode::SbcZp => AddressingMode::ZeroPage,
             Opcode::IncZp => AddressingMode::ZeroPage,
+            Opcode::IscZp => AddressingMode::ZeroPage,
             Opcode::Inx => AddressingMode::Implied,
             Opcode::SbcImm => AddressingMode::Immediate,
             Opcode::Nop => AddressingMode::Implied,
+            Opcode::SbcImmEB => AddressingMode::Immediate,
             Opcode::CpxAbs => AddressingMode::Absolute,
             Opcode::SbcAbs => AddressingMode::Absolute,
             Opcode::IncAbs => AddressingMode::Absolute,
+            Opcode::IscAbs => AddressingMode::Absolute,
 
             Opcode::Beq => AddressingMode::Relative,
             Opcode::SbcIndY => AddressingMode::IndirectY,
+            Opcode::IscIndY => AddressingMode::IndirectY,
+            Opcode::NopZpXF4 => AddressingMode::ZeroPageX,
             Opcode::SbcZpX => AddressingMode::ZeroPageX,
             Opcode::IncZpX => AddressingMode::ZeroPageX,
+            Opcode::IscZpX => AddressingMode::ZeroPageX,
             Opcode::Sed => AddressingMode::Implied,
             Opcode::SbcAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopFA => AddressingMode::Implied,
+            Opcode::IscAbsY => AddressingMode::AbsoluteY,
+            Opcode::NopAbsXFC => AddressingMode::AbsoluteX,
             Opcode::SbcAbsX => AddressingMode::AbsoluteX,
             Opcode::IncAbsX => AddressingMode::AbsoluteX,
+            Opcode::IscAbsX => AddressingMode::AbsoluteX,
         }
     }
 }
@@ -0,0 +1,331 @@
+// Minimal GDB remote serial protocol server.
+// The transport is left to the frontend: feed it the bytes received from GDB and send back what it returns.
+// https://sourceware.org/gdb/onlinedocs/gdb/Remote-Protocol.html
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+
+use crate::cpu::StatusRegister;
+use crate::Emulator;
+
+// SIGINT and SIGTRAP, as reported in the stop replies
+const SIGNAL_INTERRUPT: u8 = 2;
+const SIGNAL_TRAP: u8 = 5;
+
+/// GDB remote protocol server for the 6502.
+/// The registers are sent in this order: A, X, Y, P, S as 8 bits, then PC as 16 bits little-endian.
+#[derive(Default)]
+pub struct GdbStub {
+    breakpoints: Vec<u16>,
+    running: bool,
+    packet: Option<Vec<u8>>, // Packet currently being received
+}
+
+impl GdbStub {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Whether GDB resumed the execution. In that case, `poll` must be called to run the emulation.
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Handle bytes received from GDB, and return the bytes to send back
+    pub fn receive(&mut self, emulator: &mut Emulator, data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        for &byte in data {
+            match (&mut self.packet, byte) {
+                (None, b'$') => self.packet = Some(Vec::new()),
+                (None, 0x03) => {
+                    // Interrupt request
+                    if self.running {
+                        self.running = false;
+                        output.extend(stop_reply(SIGNAL_INTERRUPT));
+                    }
+                }
+                (None, _) => {} // Acknowledgments, which are not checked
+                (Some(packet), _) => {
+                    packet.push(byte);
+
+                    // The packet ends with '#' and a 2 digits checksum
+                    let len = packet.len();
+                    if len >= 3 && packet[len - 3] == b'#' {
+                        let packet = self.packet.take().unwrap();
+                        let (body, checksum) = packet.split_at(len - 3);
+
+                        if parse_hex(&checksum[1..]) == Some(u32::from(checksum_of(body))) {
+                            output.push(b'+');
+                            if let Some(response) = self.handle_packet(emulator, body) {
+                                output.extend(frame_packet(&response));
+                            }
+                        } else {
+                            output.push(b'-');
+                        }
+                    }
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Run the emulation while GDB resumed it, until a breakpoint is hit or a frame is ready.
+    /// Return the bytes to send to GDB.
+    pub fn poll(&mut self, emulator: &mut Emulator) -> Vec<u8> {
+        if !self.running {
+            return Vec::new();
+        }
+
+        loop {
+            if emulator.cpu().cycles == 0 && self.breakpoints.contains(&emulator.cpu().pc) {
+                self.running = false;
+                return stop_reply(SIGNAL_TRAP);
+            }
+
+            if emulator.clock().is_some() {
+                return Vec::new();
+            }
+        }
+    }
+
+    // Returns `None` when there is no response to send yet
+    fn handle_packet(&mut self, emulator: &mut Emulator, packet: &[u8]) -> Option<String> {
+        let (&command, args) = packet.split_first()?;
+        let args = core::str::from_utf8(args).unwrap_or_default();
+
+        let response = match command {
+            b'?' => signal_response(SIGNAL_TRAP),
+            b'g' => {
+                let cpu = emulator.cpu();
+                let mut response = String::new();
+                for byte in [cpu.a, cpu.x, cpu.y, cpu.status_register.bits(), cpu.st]
+                    .iter()
+                    .chain(cpu.pc.to_le_bytes().iter())
+                {
+                    let _ = write!(response, "{:02x}", byte);
+                }
+                response
+            }
+            b'G' => match decode_hex(args) {
+                Some(bytes) if bytes.len() == 7 => {
+                    let cpu = emulator.cpu_mut();
+                    cpu.a = bytes[0];
+                    cpu.x = bytes[1];
+                    cpu.y = bytes[2];
+                    cpu.status_register = StatusRegister::from_bits_truncate(bytes[3]);
+                    cpu.st = bytes[4];
+                    cpu.pc = u16::from_le_bytes([bytes[5], bytes[6]]);
+                    "OK".into()
+                }
+                _ => "E01".into(),
+            },
+            b'm' => match parse_range(args) {
+                Some((addr, len)) if len > 0 => {
+                    let end = addr.saturating_add(len - 1);
+                    let mut response = String::new();
+                    for byte in emulator.mem_dump(addr, end) {
+                        let _ = write!(response, "{:02x}", byte);
+                    }
+                    response
+                }
+                _ => "E01".into(),
+            },
+            b'M' => {
+                let mut parts = args.splitn(2, ':');
+                let range = parts.next().and_then(parse_range);
+                let data = parts.next().and_then(decode_hex);
+                match (range, data) {
+                    (Some((addr, len)), Some(data)) if data.len() == len as usize => {
+                        for (offset, byte) in data.into_iter().enumerate() {
+                            emulator.mem_write(addr.wrapping_add(offset as u16), byte);
+                        }
+                        "OK".into()
+                    }
+                    _ => "E01".into(),
+                }
+            }
+            b'Z' | b'z' => {
+                let mut parts = args.split(',');
+                let kind = parts.next();
+                let addr = parts.next().and_then(|a| parse_hex(a.as_bytes()));
+                match (kind, addr) {
+                    // Only software breakpoints are supported
+                    (Some("0"), Some(addr)) if addr <= 0xFFFF => {
+                        let addr = addr as u16;
+                        if command == b'Z' {
+                            if !self.breakpoints.contains(&addr) {
+                                self.breakpoints.push(addr);
+                            }
+                        } else {
+                            self.breakpoints.retain(|&b| b != addr);
+                        }
+                        "OK".into()
+                    }
+                    _ => String::new(),
+                }
+            }
+            b's' => {
+                emulator.step_instruction();
+                signal_response(SIGNAL_TRAP)
+            }
+            b'c' => {
+                // Don't stop on the breakpoint we're at
+                emulator.step_instruction();
+                self.running = true;
+                return None;
+            }
+            b'D' => {
+                self.breakpoints.clear();
+                self.running = true;
+                "OK".into()
+            }
+            b'H' => "OK".into(),
+            b'k' => return None,
+            b'q' if args.starts_with("Supported") => "PacketSize=1000".into(),
+            b'q' if args == "Attached" => "1".into(),
+            // Empty response for unsupported packets
+            _ => String::new(),
+        };
+
+        Some(response)
+    }
+}
+
+fn signal_response(signal: u8) -> String {
+    let mut response = String::new();
+    let _ = write!(response, "S{:02x}", signal);
+    response
+}
+
+fn stop_reply(signal: u8) -> Vec<u8> {
+    frame_packet(&signal_response(signal))
+}
+
+fn frame_packet(body: &str) -> Vec<u8> {
+    let mut packet = String::with_capacity(body.len() + 4);
+    let _ = write!(packet, "${}#{:02x}", body, checksum_of(body.as_bytes()));
+    packet.into_bytes()
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+fn parse_hex(data: &[u8]) -> Option<u32> {
+    if data.is_empty() || data.len() > 8 {
+        return None;
+    }
+
+    data.iter().try_fold(0u32, |value, &digit| {
+        Some((value << 4) | (digit as char).to_digit(16)?)
+    })
+}
+
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    let bytes = data.as_bytes().chunks_exact(2);
+    if !bytes.remainder().is_empty() {
+        return None;
+    }
+
+    bytes.map(|byte| parse_hex(byte).map(|b| b as u8)).collect()
+}
+
+// Parse an `addr,length` pair
+fn parse_range(data: &str) -> Option<(u16, u16)> {
+    let mut parts = data.split(',');
+    let addr = parse_hex(parts.next()?.as_bytes())?;
+    let len = parse_hex(parts.next()?.as_bytes())?;
+
+    // Keep the length within the packet size
+    if addr > 0xFFFF || len > 0x800 {
+        return None;
+    }
+    Some((addr as u16, len as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    // Program at $8000: LDA #$42, LDX #$07, JMP $8004
+    fn emulator() -> Emulator {
+        let mut rom = vec![0u8; 16 + 0x4000 + 0x2000];
+        rom[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+        rom[4] = 0x01;
+        rom[5] = 0x01;
+        rom[16..23].copy_from_slice(&[0xA9, 0x42, 0xA2, 0x07, 0x4C, 0x04, 0x80]);
+        rom[16 + 0x3FFC] = 0x00;
+        rom[16 + 0x3FFD] = 0x80;
+        Emulator::new(&rom, None).unwrap()
+    }
+
+    // Send a packet like GDB would, and return the response without its framing
+    fn request(stub: &mut GdbStub, emulator: &mut Emulator, body: &str) -> String {
+        let response = stub.receive(emulator, &frame_packet(body));
+        assert_eq!(response.first(), Some(&b'+'));
+
+        let response = core::str::from_utf8(&response[1..]).unwrap();
+        if response.is_empty() {
+            return String::new();
+        }
+        let (body, checksum) = response[1..].split_at(response.len() - 4);
+        assert_eq!(
+            checksum,
+            &alloc::format!("#{:02x}", checksum_of(body.as_bytes()))
+        );
+        body.into()
+    }
+
+    #[test]
+    fn read_registers() {
+        let mut emu = emulator();
+        let mut stub = GdbStub::new();
+
+        assert_eq!(request(&mut stub, &mut emu, "qAttached"), "1");
+        assert_eq!(request(&mut stub, &mut emu, "?"), "S05");
+
+        // Finish the reset sequence
+        while emu.cpu().cycles > 0 {
+            emu.clock();
+        }
+        assert_eq!(request(&mut stub, &mut emu, "g"), "00000024fd0080");
+
+        assert_eq!(request(&mut stub, &mut emu, "s"), "S05");
+        assert_eq!(request(&mut stub, &mut emu, "g"), "42000024fd0280");
+    }
+
+    #[test]
+    fn memory_and_breakpoints() {
+        let mut emu = emulator();
+        let mut stub = GdbStub::new();
+
+        assert_eq!(request(&mut stub, &mut emu, "M10,2:abcd"), "OK");
+        assert_eq!(request(&mut stub, &mut emu, "m10,2"), "abcd");
+        assert_eq!(request(&mut stub, &mut emu, "m8000,2"), "a942");
+
+        assert_eq!(request(&mut stub, &mut emu, "Z0,8004,1"), "OK");
+        assert_eq!(request(&mut stub, &mut emu, "c"), "");
+        assert!(stub.is_running());
+        assert_eq!(stub.poll(&mut emu), frame_packet("S05"));
+        assert_eq!(emu.cpu().pc, 0x8004);
+        assert_eq!(emu.cpu().x, 0x07);
+
+        // Continuing from the breakpoint goes around the loop back to it
+        request(&mut stub, &mut emu, "c");
+        assert_eq!(stub.poll(&mut emu), frame_packet("S05"));
+
+        // Without breakpoints, it runs until interrupted
+        assert_eq!(request(&mut stub, &mut emu, "z0,8004,1"), "OK");
+        request(&mut stub, &mut emu, "c");
+        assert!(stub.poll(&mut emu).is_empty());
+        assert_eq!(stub.receive(&mut emu, &[0x03]), frame_packet("S02"));
+        assert!(!stub.is_running());
+
+        // Bad checksum
+        assert_eq!(stub.receive(&mut emu, b"$g#00"), b"-");
+    }
+}
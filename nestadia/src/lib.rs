@@ -7,23 +7,94 @@ mod bus;
 
 mod apu;
 mod cartridge;
+mod controller;
 mod cpu;
+#[cfg(feature = "gdbstub")]
+mod gdb;
+mod movie;
 mod ppu;
 mod rgb_palette;
+#[cfg(feature = "serde")]
+mod state;
 
-pub use rgb_palette::RGB_PALETTE;
+pub use rgb_palette::{
+    palette_by_name, PaletteError, PALETTE_NAMES, RGB_PALETTE, RGB_PALETTE_2C03, RGB_PALETTE_2C07,
+    RGB_PALETTE_NOSTALGIA_FBX,
+};
 
-pub use apu::Apu;
-pub use cartridge::RomParserError;
+pub use apu::{Apu, ApuChannel, MixingModel};
+#[cfg(feature = "debugger")]
+pub use bus::MemoryRegion;
+#[cfg(feature = "debugger")]
+pub use cartridge::TraceEvent;
+pub use cartridge::{
+    is_mapper_supported, CartridgeHeader, HeaderFormat, Mirroring, PatchError, RomParserError,
+};
+pub use controller::{Buttons, ControllerPort, SocdPolicy};
 pub use cpu::Cpu;
+#[cfg(feature = "gdbstub")]
+pub use gdb::GdbStub;
+pub use movie::{Movie, MovieError};
 pub use ppu::registers::MaskReg;
-pub use ppu::Ppu;
+pub use ppu::sprites::SpriteOverflowMode;
+pub use ppu::{Ppu, PpuFrame};
+#[cfg(feature = "serde")]
+pub use state::{SaveStateManager, StateError};
 
 use crate::cartridge::Cartridge;
-use crate::ppu::PpuFrame;
+use crate::controller::Zapper;
 
 pub const RAM_SIZE: u16 = 0x0800;
 
+/// CPU clock of the NTSC NES, in Hz
+pub const NTSC_CPU_CLOCK: f64 = 1_789_773.0;
+
+/// Emulation progress since the last call to `Emulator::reset_performance_stats`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PerformanceStats {
+    pub cycles: u64,
+    pub frames: u64,
+}
+
+impl PerformanceStats {
+    /// Speed relative to a real NES, given the real time elapsed. 1.0 is full speed.
+    pub fn speed(&self, elapsed_seconds: f64) -> f64 {
+        self.cycles as f64 / NTSC_CPU_CLOCK / elapsed_seconds
+    }
+}
+
+/// Mapper numbers supported by the emulator
+pub fn supported_mappers() -> &'static [u16] {
+    cartridge::SUPPORTED_MAPPERS
+}
+
+/// Snapshot of the emulator state for the debugger, see `Emulator::debug_info`
+#[cfg(feature = "debugger")]
+#[derive(Clone, Debug)]
+pub struct DebugInfo {
+    pub cpu: Cpu,
+    pub scanline: i16,
+    pub cycle: u16,
+    pub scroll: (u16, u16),
+    pub mask: MaskReg,
+    pub nmi_pending: bool,
+    pub irq_pending: bool,
+    pub prg_banks: [Option<u8>; 4], // 16KB PRG ROM bank mapped in each 8KB window of $8000-$FFFF
+    pub chr_banks: [Option<u8>; 8], // 1KB CHR bank mapped in each 1KB window of $0000-$1FFF
+    pub frame_count: u64,
+    pub total_cycles: u64,
+}
+
+/// Outcome of `Emulator::step_instruction`
+#[cfg(feature = "debugger")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepResult {
+    pub opcode: Option<u8>, // `None` when no instruction started, like for an NMI or IRQ sequence
+    pub pc: u16,            // Address of the next instruction
+    pub cycles: u64,
+}
+
+#[derive(Clone)]
 pub struct Emulator {
     // == APU == //
     apu: Apu,
@@ -38,6 +109,7 @@ pub struct Emulator {
     controller_state: bool,
     controller1_snapshot: u8,
     controller2_snapshot: u8,
+    zapper: Option<Zapper>, // Plugged in the second port
     ram: [u8; RAM_SIZE as usize],
 
     // == PPU == //
@@ -46,14 +118,48 @@ pub struct Emulator {
 
     // Emulator internal state
     clock_count: u8,
+    total_cycles: u64,
+    frame_count: u64,
+    input_delay: u8,
+    run_ahead: u8,
+    run_ahead_state: Option<alloc::boxed::Box<Emulator>>,
+    media_inserted: bool,
+    input_queue: alloc::vec::Vec<(u64, ControllerPort, Buttons)>,
+    socd_policy: SocdPolicy,
+    raw_inputs: [Buttons; 2], // Controller states before the SOCD resolution
+    held_inputs: [Buttons; 2], // Controller states before the autofire buttons are released
+    turbo_masks: [Buttons; 2], // Buttons of each controller that autofire while held
+    turbo_period: u8,         // Frames the autofire buttons stay pressed, then released
+    requested_inputs: [Buttons; 2], // Controller states last set by the frontend, before the input delay
+    recording: Option<(u64, Movie)>, // Frame where the recording started, and the movie
+    performance_start: (u64, u64),  // Cycles and frames when the stats were reset
+    palette: [[u8; 3]; 64],
+    #[cfg(feature = "debugger")]
+    interrupt_started: bool, // An NMI or IRQ sequence started during `step_instruction`
 }
 
 impl Emulator {
     pub fn new(rom: &[u8], save_data: Option<&[u8]>) -> Result<Self, RomParserError> {
+        Ok(Self::with_cartridge(Cartridge::load(rom, save_data)?))
+    }
+
+    /// Same as `new`, but the IPS or BPS `patch` is applied to the ROM file first.
+    /// This is how ROM hacks and translations are distributed.
+    pub fn new_with_patch(
+        rom: &[u8],
+        patch: &[u8],
+        save_data: Option<&[u8]>,
+    ) -> Result<Self, RomParserError> {
+        Ok(Self::with_cartridge(Cartridge::load_with_patch(
+            rom, patch, save_data,
+        )?))
+    }
+
+    fn with_cartridge(cartridge: Cartridge) -> Self {
         let mut emulator = Self {
             apu: Default::default(),
 
-            cartridge: Cartridge::load(rom, save_data)?,
+            cartridge,
 
             cpu: Default::default(),
             controller1: 0,
@@ -61,64 +167,576 @@ impl Emulator {
             controller_state: false,
             controller1_snapshot: 0,
             controller2_snapshot: 0,
+            zapper: None,
             ram: [0u8; RAM_SIZE as usize],
 
             ppu: Ppu::new(),
             name_tables: [0u8; 1024 * 4],
 
             clock_count: 0,
+            total_cycles: 0,
+            frame_count: 0,
+            input_delay: 0,
+            run_ahead: 0,
+            run_ahead_state: None,
+            media_inserted: true,
+            input_queue: alloc::vec::Vec::new(),
+            socd_policy: Default::default(),
+            raw_inputs: [Buttons::empty(); 2],
+            held_inputs: [Buttons::empty(); 2],
+            turbo_masks: [Buttons::empty(); 2],
+            turbo_period: 1,
+            requested_inputs: [Buttons::empty(); 2],
+            recording: None,
+            performance_start: (0, 0),
+            palette: RGB_PALETTE,
+            #[cfg(feature = "debugger")]
+            interrupt_started: false,
         };
 
-        emulator.reset();
+        emulator.power_cycle();
+
+        emulator
+    }
 
+    /// Same as `new`, but run `frames` frames before returning.
+    /// This is useful to start tests from a stable state, past the boot screens of a game.
+    pub fn new_with_pre_run(
+        rom: &[u8],
+        save_data: Option<&[u8]>,
+        frames: u64,
+    ) -> Result<Self, RomParserError> {
+        let mut emulator = Self::new(rom, save_data)?;
+        emulator.run_frames(frames);
         Ok(emulator)
     }
 
     pub fn clock(&mut self) -> Option<&PpuFrame> {
+        // Nothing to run while the media is out
+        if !self.media_inserted {
+            return None;
+        }
+
         // Make PPU clock first
         let mut ppu_bus = borrow_ppu_bus!(self);
         self.ppu.clock(&mut ppu_bus);
+        if let Some(zapper) = &mut self.zapper {
+            zapper.clock(&self.ppu, &self.palette);
+        }
 
         // CPU clock is 3 times slower
         if self.clock_count % 3 == 0 {
             self.clock_count = 0;
+            self.total_cycles += 1;
 
             // TODO: Cleanup if current solution is working
             /*#[cfg(feature = "audio")]*/
             self.apu.clock();
 
-            if self.cpu.cycles == 0 && self.ppu.take_vblank_nmi_set_state() {
+            // The DMC samples are always read from the cartridge, at $8000-$FFFF
+            if let Some(addr) = self.apu.dmc_sample_address() {
+                let data = self.cartridge.read_prg_mem(addr);
+                self.apu.load_dmc_sample(data);
+            }
+
+            // Latch the interrupt lines so they can be inspected until they are serviced
+            if self.ppu.take_vblank_nmi_set_state() {
+                self.cpu.nmi_pending = true;
+            }
+            self.cpu.irq_pending = self.cartridge.irq_state() || self.apu.irq_state();
+
+            #[cfg(feature = "debugger")]
+            if self.cpu.cycles == 0
+                && (self.cpu.nmi_pending
+                    || (self.cpu.irq_pending
+                        && !self.cpu.status_register.contains(cpu::StatusRegister::I)))
+            {
+                self.interrupt_started = true;
+            }
+
+            let mut cpu_bus = borrow_cpu_bus!(self);
+            if self.cpu.cycles == 0 && self.cpu.nmi_pending {
                 // NMI interrupt
-                let mut cpu_bus = borrow_cpu_bus!(self);
                 self.cpu.nmi(&mut cpu_bus);
-                self.cpu.clock(&mut cpu_bus);
-            } else if self.cpu.cycles == 0 && self.cartridge.take_irq_set_state() {
+            } else if self.cpu.cycles == 0 && self.cpu.irq_pending {
                 // IRQ interrupt
-                let mut cpu_bus = borrow_cpu_bus!(self);
                 self.cpu.irq(&mut cpu_bus);
-                self.cpu.clock(&mut cpu_bus);
-            } else {
-                let mut cpu_bus = borrow_cpu_bus!(self);
-                self.cpu.clock(&mut cpu_bus);
+            }
+            self.cpu.clock(&mut cpu_bus);
+
+            // https://wiki.nesdev.com/w/index.php/PPU_registers#OAMDMA
+            // "The CPU is suspended during the transfer, which will take 513 or 514 cycles after the $4014 write tick.
+            // (1 wait state cycle while waiting for writes to complete, +1 if on an odd CPU cycle, then 256
+            // alternating read/write cycles.)"
+            // The instruction is run on its first cycle, but the $4014 write tick is its last one.
+            if cpu_bus.take_oam_dma() {
+                let write_cycle = self.total_cycles + u64::from(self.cpu.cycles);
+                self.cpu.cycles += if write_cycle % 2 == 1 { 514 } else { 513 };
+            }
+
+            // A $2002 read can cancel the NMI of the vblank that just started
+            if self.ppu.take_vblank_nmi_suppressed_state() {
+                self.cpu.nmi_pending = false;
             }
         }
 
         self.clock_count = self.clock_count.wrapping_add(1);
 
+        if self.ppu.ready_frame().is_some() {
+            self.frame_count += 1;
+            self.apply_queued_inputs();
+
+            if self.run_ahead > 0 {
+                return self.run_ahead_frame();
+            }
+        }
+
         // returns PPU frame if any
         self.ppu.ready_frame()
     }
 
+    /// Same as `clock`, but a ready frame is swapped with `frame` instead of being borrowed, so it isn't copied.
+    /// The buffer passed in becomes the one the next frame is rendered to. Returns whether a frame was ready.
+    pub fn clock_into(&mut self, frame: &mut alloc::boxed::Box<PpuFrame>) -> bool {
+        if self.clock().is_none() {
+            return false;
+        }
+
+        match &mut self.run_ahead_state {
+            Some(ahead) if self.run_ahead > 0 => ahead.ppu.swap_frame(frame),
+            _ => self.ppu.swap_frame(frame),
+        }
+        true
+    }
+
+    /// Display frames from the future to hide the input lag of games that react to inputs a few frames late.
+    /// Each time a frame is ready, the state is copied and run `frames` more frames with the current inputs, and the
+    /// last frame of the copy is returned instead. The copy is then discarded, so the emulation itself is unaffected.
+    /// This costs `frames` more frames of emulation for each displayed frame.
+    pub fn set_run_ahead(&mut self, frames: u8) {
+        self.run_ahead = frames;
+        self.run_ahead_state = None;
+    }
+
+    fn run_ahead_frame(&mut self) -> Option<&PpuFrame> {
+        // Drop the previous copy first, so it isn't cloned along
+        self.run_ahead_state = None;
+        let mut ahead = alloc::boxed::Box::new(self.clone());
+        ahead.run_ahead = 0;
+        ahead.recording = None;
+
+        for _ in 0..self.run_ahead {
+            while ahead.clock().is_none() {}
+        }
+
+        self.run_ahead_state.insert(ahead).ppu.ready_frame()
+    }
+
+    /// Advance the emulation by exactly `cycles` CPU cycles, which is 3 times as many PPU cycles.
+    /// Frames rendered in the meantime are not returned.
+    pub fn clock_n(&mut self, cycles: u64) {
+        if !self.media_inserted {
+            return;
+        }
+
+        let target = self.total_cycles + cycles;
+        while self.total_cycles < target {
+            self.clock();
+        }
+    }
+
+    /// Run until the next instruction boundary, finishing the instruction in progress if any.
+    /// The step runs the NMI or IRQ sequence instead of an instruction when one is serviced.
+    #[cfg(feature = "debugger")]
+    pub fn step_instruction(&mut self) -> StepResult {
+        let start = self.total_cycles;
+        if !self.media_inserted {
+            return StepResult {
+                opcode: None,
+                pc: self.cpu.pc,
+                cycles: 0,
+            };
+        }
+
+        // Fetch the opcode before it runs, in case the instruction overwrites it
+        let mut opcode = if self.cpu.cycles == 0 {
+            let pc = self.cpu.pc;
+            let mut cpu_bus = borrow_cpu_bus!(self);
+            Some(self.cpu.mem_dump(&mut cpu_bus, pc))
+        } else {
+            None
+        };
+
+        self.interrupt_started = false;
+        while {
+            self.clock();
+            self.total_cycles == start || self.cpu.cycles > 0
+        } {}
+
+        if self.interrupt_started {
+            opcode = None;
+        }
+
+        StepResult {
+            opcode,
+            pc: self.cpu.pc,
+            cycles: self.total_cycles - start,
+        }
+    }
+
+    /// Run the next instruction on the CPU alone, and return the number of cycles it took.
+    /// Unlike `step_instruction`, the rest of the console doesn't advance and no interrupt is serviced,
+    /// which makes it deterministic for test harnesses. An instruction in progress is finished first.
+    #[cfg(feature = "debugger")]
+    pub fn step_cpu_instruction(&mut self) -> u16 {
+        let mut cpu_bus = borrow_cpu_bus!(self);
+        if self.cpu.cycles > 0 {
+            self.cpu.step(&mut cpu_bus);
+        }
+
+        self.cpu.step(&mut cpu_bus)
+    }
+
+    /// Number of CPU cycles run since the emulator was created
+    pub fn total_cycles(&self) -> u64 {
+        self.total_cycles
+    }
+
+    /// Which opcodes the CPU executed since the emulator was created, indexed by opcode.
+    /// Running a library of games through this shows which unofficial opcodes are worth supporting.
+    #[cfg(feature = "opcode-coverage")]
+    pub fn executed_opcodes(&self) -> [bool; 256] {
+        self.cpu.executed_opcodes()
+    }
+
+    pub fn performance_stats(&self) -> PerformanceStats {
+        PerformanceStats {
+            cycles: self.total_cycles - self.performance_start.0,
+            frames: self.frame_count - self.performance_start.1,
+        }
+    }
+
+    /// Start counting the `performance_stats` from now
+    pub fn reset_performance_stats(&mut self) {
+        self.performance_start = (self.total_cycles, self.frame_count);
+    }
+
+    /// Run the emulation until `frames` frames are rendered, and return the last one.
+    /// Does nothing while the media is ejected.
+    pub fn run_frames(&mut self, frames: u64) -> &PpuFrame {
+        if self.media_inserted {
+            for _ in 0..frames {
+                self.run_frame();
+            }
+        }
+
+        self.current_frame()
+    }
+
+    /// Clock until the next frame is ready, and return it.
+    /// Nothing runs while the media is out, so the last frame is returned instead.
+    ///
+    /// ```
+    /// // NROM cartridge that loops forever at $8000
+    /// let mut rom = vec![0u8; 16 + 0x4000 + 0x2000];
+    /// rom[..6].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01]);
+    /// rom[16..19].copy_from_slice(&[0x4C, 0x00, 0x80]); // JMP $8000
+    /// rom[16 + 0x3FFD] = 0x80; // Reset vector
+    ///
+    /// let mut emulator = nestadia::Emulator::new(&rom, None).unwrap();
+    /// let frame = emulator.run_frame();
+    /// assert_eq!(frame.len(), 256 * 240);
+    /// assert_eq!(emulator.frame_count(), 1);
+    /// ```
+    pub fn run_frame(&mut self) -> &PpuFrame {
+        if self.media_inserted {
+            while self.clock().is_none() {}
+        }
+
+        self.current_frame()
+    }
+
+    /// FNV-1a hash of the last frame, to compare the output of a game in regression tests.
+    /// Only the palette indices are hashed, so it doesn't depend on the palette or the emphasis.
+    pub fn frame_hash(&self) -> u64 {
+        self.current_frame()
+            .iter()
+            .fold(0xCBF29CE484222325, |hash, &color| {
+                (hash ^ u64::from(color)).wrapping_mul(0x100000001B3)
+            })
+    }
+
+    fn current_frame(&self) -> &PpuFrame {
+        match &self.run_ahead_state {
+            Some(ahead) if self.run_ahead > 0 => ahead.ppu.frame(),
+            _ => self.ppu.frame(),
+        }
+    }
+
+    /// Number of frames rendered since the emulator was created
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Set the state of a controller once `frame_count` reaches `frame`.
+    /// Inputs queued for a frame that's already reached are applied immediately.
+    pub fn queue_input(&mut self, frame: u64, port: ControllerPort, buttons: Buttons) {
+        self.input_queue.push((frame, port, buttons));
+        self.apply_queued_inputs();
+    }
+
+    fn apply_queued_inputs(&mut self) {
+        let frame_count = self.frame_count;
+        let mut input_queue = core::mem::take(&mut self.input_queue);
+        input_queue.retain(|&(frame, port, buttons)| {
+            if frame > frame_count {
+                return true;
+            }
+
+            self.apply_input(port, buttons);
+            false
+        });
+        self.input_queue = input_queue;
+
+        // Pulse the autofire buttons that are still held
+        for &port in &[ControllerPort::One, ControllerPort::Two] {
+            let i = port as usize;
+            if self.held_inputs[i] - self.turbo_released(port) != self.raw_inputs[i] {
+                self.apply_input(port, self.held_inputs[i]);
+            }
+        }
+    }
+
+    // The autofire buttons are released before the input is resolved and recorded,
+    // so a movie replays the same without the turbo masks
+    fn apply_input(&mut self, port: ControllerPort, held: Buttons) {
+        let i = port as usize;
+        let buttons = held - self.turbo_released(port);
+        let controller = match port {
+            ControllerPort::One => &mut self.controller1,
+            ControllerPort::Two => &mut self.controller2,
+        };
+        let previous = Buttons::from_bits_truncate(*controller);
+        *controller = self
+            .socd_policy
+            .resolve(self.raw_inputs[i], previous, buttons)
+            .bits();
+        self.raw_inputs[i] = buttons;
+        self.held_inputs[i] = held;
+
+        if let Some((start_frame, movie)) = &mut self.recording {
+            movie.push(self.frame_count - *start_frame, port, buttons);
+        }
+    }
+
+    fn turbo_released(&self, port: ControllerPort) -> Buttons {
+        controller::turbo_released(
+            self.turbo_masks[port as usize],
+            self.frame_count,
+            self.turbo_period,
+        )
+    }
+
+    // Move what depends on the frame count to the timeline of a state that was just loaded
+    #[cfg(feature = "serde")]
+    fn rebase_timeline(&mut self, previous_frame_count: u64) {
+        let frame_count = self.frame_count;
+
+        // Delayed inputs stay as far in the future
+        for (frame, _, _) in self.input_queue.iter_mut() {
+            *frame = frame_count + frame.saturating_sub(previous_frame_count);
+        }
+        self.raw_inputs = [
+            Buttons::from_bits_truncate(self.controller1),
+            Buttons::from_bits_truncate(self.controller2),
+        ];
+        self.held_inputs = self.raw_inputs;
+        self.run_ahead_state = None;
+
+        // How much was emulated can't be told across a load, so the stats start over
+        self.performance_start = (self.total_cycles, frame_count);
+
+        // The recording goes back to the loaded frame, and the inputs after it are recorded again.
+        // Before the start of the recording, it starts over from there.
+        if matches!(self.recording, Some((start_frame, _)) if frame_count < start_frame) {
+            self.start_recording();
+        }
+        if let Some((start_frame, movie)) = &mut self.recording {
+            let frame = frame_count - *start_frame;
+            movie.truncate(frame);
+            movie.push(frame, ControllerPort::One, self.raw_inputs[0]);
+            movie.push(frame, ControllerPort::Two, self.raw_inputs[1]);
+        }
+    }
+
+    /// Record the controller inputs from now on.
+    /// The movie starts with a save state, so it replays from the same point. Without the `serde` feature, there's
+    /// no save state, so the recording has to start right after the emulator is created to replay deterministically.
+    /// The inputs must be set between frames.
+    pub fn start_recording(&mut self) {
+        let movie = Movie::new(self.rom_crc32());
+        #[cfg(feature = "serde")]
+        let movie = {
+            let mut movie = movie;
+            movie.set_initial_state(self.save_state());
+            movie
+        };
+        self.recording = Some((self.frame_count, movie));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Movie> {
+        self.recording.take().map(|(_, movie)| movie)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Load the save state the movie starts from, and queue its inputs from there.
+    /// Movies recorded without a save state start from the current frame.
+    pub fn play_movie(&mut self, movie: &Movie) -> Result<(), MovieError> {
+        if movie.rom_crc32() != self.rom_crc32() {
+            return Err(MovieError::RomMismatch);
+        }
+
+        #[cfg(feature = "serde")]
+        if let Some(state) = movie.initial_state() {
+            self.load_state(state)
+                .map_err(|_| MovieError::InvalidState)?;
+        }
+        #[cfg(not(feature = "serde"))]
+        if movie.initial_state().is_some() {
+            return Err(MovieError::InvalidState);
+        }
+
+        let start_frame = self.frame_count;
+        self.input_queue.extend(
+            movie
+                .inputs()
+                .iter()
+                .map(|&(frame, port, buttons)| (start_frame + frame, port, buttons)),
+        );
+        self.apply_queued_inputs();
+
+        Ok(())
+    }
+
+    pub fn is_vblank(&self) -> bool {
+        self.ppu.is_vblank()
+    }
+
+    pub fn is_rendering(&self) -> bool {
+        self.ppu.is_rendering()
+    }
+
     pub fn get_ppu_mask_reg(&mut self) -> MaskReg {
         self.ppu.mask_reg
     }
 
+    /// Palette to pass to the `frame_to_*` converters
+    pub fn palette(&self) -> &[[u8; 3]; 64] {
+        &self.palette
+    }
+
+    pub fn set_palette(&mut self, palette: [[u8; 3]; 64]) {
+        self.palette = palette;
+    }
+
+    /// Select one of the bundled palettes listed in `PALETTE_NAMES`
+    pub fn set_palette_by_name(&mut self, name: &str) -> Result<(), PaletteError> {
+        self.palette = palette_by_name(name)?;
+        Ok(())
+    }
+
+    /// Whether the sprite overflow flag reproduces the hardware bug. Defaults to `SpriteOverflowMode::Hardware`.
+    pub fn set_sprite_overflow_mode(&mut self, mode: SpriteOverflowMode) {
+        self.ppu.set_sprite_overflow_mode(mode);
+    }
+
+    /// Whether odd frames skip a PPU cycle while rendering. Defaults to `true`, like the hardware.
+    /// Turning it off helps comparing the timing against references that don't emulate it.
+    pub fn set_odd_frame_skip(&mut self, enabled: bool) {
+        self.ppu.set_odd_frame_skip(enabled);
+    }
+
+    /// Whether the first frame after a power cycle or a reset is an odd one, which is random on hardware.
+    /// Defaults to `false`, the most common power-on state. It also applies to the current frame,
+    /// so set it right after `new` to match a reference trace from the start.
+    pub fn set_initial_odd_frame(&mut self, odd: bool) {
+        self.ppu.set_initial_odd_frame(odd);
+    }
+
     pub fn set_controller1(&mut self, state: u8) {
-        self.controller1 = state;
+        self.set_controller(ControllerPort::One, Buttons::from_bits_truncate(state));
     }
 
     pub fn set_controller2(&mut self, state: u8) {
-        self.controller2 = state;
+        self.set_controller(ControllerPort::Two, Buttons::from_bits_truncate(state));
+    }
+
+    /// Press or release buttons of a controller, leaving its other buttons as they are
+    pub fn set_button(&mut self, port: ControllerPort, button: Buttons, pressed: bool) {
+        let mut buttons = self.controller(port);
+        buttons.set(button, pressed);
+        self.set_controller(port, buttons);
+    }
+
+    /// State of a controller, as last set by the frontend.
+    /// With an input delay, the game will only see it a few frames later.
+    pub fn controller(&self, port: ControllerPort) -> Buttons {
+        self.requested_inputs[port as usize]
+    }
+
+    fn set_controller(&mut self, port: ControllerPort, buttons: Buttons) {
+        self.requested_inputs[port as usize] = buttons;
+        self.queue_input(
+            self.frame_count + u64::from(self.input_delay),
+            port,
+            buttons,
+        );
+    }
+
+    /// Make buttons of the first controller autofire while they are held, like the turbo buttons of some
+    /// controllers. Set bits use the layout of `set_controller1`, and those buttons alternate between
+    /// pressed and released every `set_turbo_period` frames. A mask of 0 turns it off.
+    /// The released frames are recorded in movies like any other input.
+    pub fn set_controller1_turbo_mask(&mut self, mask: u8) {
+        self.turbo_masks[ControllerPort::One as usize] = Buttons::from_bits_truncate(mask);
+    }
+
+    /// Same as `set_controller1_turbo_mask`, for the second controller
+    pub fn set_controller2_turbo_mask(&mut self, mask: u8) {
+        self.turbo_masks[ControllerPort::Two as usize] = Buttons::from_bits_truncate(mask);
+    }
+
+    /// Number of frames the autofire buttons stay pressed, and then released. Defaults to 1, the fastest.
+    pub fn set_turbo_period(&mut self, frames: u8) {
+        self.turbo_period = frames.max(1);
+    }
+
+    /// Plug a zapper in the second port, or move the one already there, replacing the controller.
+    /// `x` and `y` are in NES pixels, from 0 to 255 and 0 to 239. Pointing outside of that range aims off-screen.
+    pub fn set_zapper(&mut self, x: u16, y: u16, trigger: bool) {
+        match &mut self.zapper {
+            Some(zapper) => zapper.aim(x, y, trigger),
+            None => self.zapper = Some(Zapper::new(x, y, trigger)),
+        }
+    }
+
+    /// Plug the controller back in the second port
+    pub fn disconnect_zapper(&mut self) {
+        self.zapper = None;
+    }
+
+    /// How opposing directions pressed at the same time are passed to the game. Defaults to `SocdPolicy::Neutral`.
+    pub fn set_socd_policy(&mut self, policy: SocdPolicy) {
+        self.socd_policy = policy;
+    }
+
+    /// Delay the controller states set through `set_controller1` and `set_controller2` by a number of frames.
+    /// This is used to keep the inputs of remote players in sync for netplay.
+    pub fn set_input_delay(&mut self, frames: u8) {
+        self.input_delay = frames;
     }
 
     pub fn reset(&mut self) {
@@ -129,28 +747,164 @@ impl Emulator {
         self.clock_count = 0;
     }
 
+    /// Turn the console off and on again.
+    /// Unlike `reset`, the RAM is cleared and the APU goes back to its power-up state.
+    /// The save data of the cartridge is kept, but its banks are switched back like at power-on.
+    pub fn power_cycle(&mut self) {
+        self.cartridge.power_on();
+        self.ram = [0u8; RAM_SIZE as usize];
+        self.name_tables = [0u8; 1024 * 4];
+        self.apu.power_on();
+        self.reset();
+    }
+
+    /// Battery-backed RAM of the cartridge, to be passed back to `new` on the next run.
+    /// `None` when the cartridge has no battery.
     pub fn get_save_data(&self) -> Option<&[u8]> {
         self.cartridge.get_save_data()
     }
 
-    #[cfg(feature = "audio")]
+    /// Whether the game wrote to its battery-backed RAM since the cartridge was loaded.
+    /// When it didn't, the save file doesn't need to be written again.
+    pub fn is_save_dirty(&self) -> bool {
+        self.cartridge.is_save_dirty()
+    }
+
+    /// Snapshot the whole machine, to be restored with `load_state`.
+    /// The ROMs aren't included, so the state can only be loaded with the same cartridge.
+    /// States are versioned: those made by an incompatible version of the emulator are rejected.
+    #[cfg(feature = "serde")]
+    pub fn save_state(&self) -> alloc::vec::Vec<u8> {
+        state::save(self)
+    }
+
+    /// Restore a snapshot taken by `save_state`. The settings of the frontend, like the sample rate,
+    /// are kept. The emulator is left untouched if the state can't be loaded.
+    #[cfg(feature = "serde")]
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        state::load(self, data)
+    }
+
+    /// Header of the ROM file, in the iNES or NES 2.0 format
+    pub fn cartridge_header(&self) -> &CartridgeHeader {
+        self.cartridge.header()
+    }
+
+    /// CRC32 of the ROM data without its header, as used by the game databases
+    pub fn rom_crc32(&self) -> u32 {
+        self.cartridge.rom_crc32()
+    }
+
+    /// SHA-1 of the ROM data without its header, as used by the game databases
+    pub fn rom_sha1(&self) -> [u8; 20] {
+        self.cartridge.rom_sha1()
+    }
+
+    /// Apply a list of `(address, value)` patches to the cartridge, like a trainer or a RAM hack.
+    /// Patches to the PRG RAM ($6000-$7FFF) are applied, while the ROM and everything outside
+    /// of the cartridge can't be written, so those are logged and ignored.
+    /// Returns the number of patches that were applied.
+    pub fn apply_patch(&mut self, patches: &[(u16, u8)]) -> usize {
+        patches
+            .iter()
+            .filter(|&&(addr, data)| self.cartridge.patch_prg_mem(addr, data))
+            .count()
+    }
+
+    /// Same as `apply_patch`, but the addresses are in the PPU pattern tables ($0000-$1FFF).
+    /// Patches are applied to the CHR RAM, while the CHR ROM and the rest of the PPU space
+    /// can't be written, so those are logged and ignored.
+    /// Returns the number of patches that were applied.
+    pub fn apply_chr_patch(&mut self, patches: &[(u16, u8)]) -> usize {
+        patches
+            .iter()
+            .filter(|&&(addr, data)| self.cartridge.patch_chr_mem(addr, data))
+            .count()
+    }
+
+    /// Remove the current media. The emulation is paused until new media is inserted.
+    pub fn eject_media(&mut self) {
+        self.media_inserted = false;
+    }
+
+    /// Insert new media, replacing the current one.
+    /// Swapping a cartridge requires a reset, but disk-based games prompting for a disk change
+    /// expect the CPU to keep running, which is what `keep_running` is for.
+    pub fn insert_media(
+        &mut self,
+        rom: &[u8],
+        save_data: Option<&[u8]>,
+        keep_running: bool,
+    ) -> Result<(), RomParserError> {
+        self.cartridge = Cartridge::load(rom, save_data)?;
+        self.media_inserted = true;
+
+        if !keep_running {
+            self.reset();
+        }
+
+        Ok(())
+    }
+
+    pub fn is_media_inserted(&self) -> bool {
+        self.media_inserted
+    }
+
+    // The audio methods are available without the `audio` feature so the frontends don't need to check for it.
+    // In that case, no samples are ever produced.
+
     pub fn set_sample_rate(&mut self, sample_rate: f32) {
         self.apu.set_sample_rate(sample_rate);
     }
 
-    #[cfg(feature = "audio")]
+    /// Whether the audio goes through the filters of the console, which soften the raw output. Defaults to `true`.
+    pub fn set_audio_filtering_enabled(&mut self, enabled: bool) {
+        self.apu.set_filtering_enabled(enabled);
+    }
+
+    /// Mute or unmute a sound channel. Muted channels keep running, they're only left out of the mix.
+    pub fn set_channel_enabled(&mut self, channel: ApuChannel, enabled: bool) {
+        self.apu.set_channel_enabled(channel, enabled);
+    }
+
+    pub fn is_channel_enabled(&self, channel: ApuChannel) -> bool {
+        self.apu.is_channel_enabled(channel)
+    }
+
+    /// Mute every sound channel but `channel`
+    pub fn solo_channel(&mut self, channel: ApuChannel) {
+        self.apu.solo_channel(channel);
+    }
+
+    /// Whether the triangle channel stops at ultrasonic periods instead of popping. Defaults to `true`.
+    pub fn set_ultrasonic_triangle_silenced(&mut self, silenced: bool) {
+        self.apu.set_ultrasonic_triangle_silenced(silenced);
+    }
+
+    /// Mix the channels like the 2A03 DAC, or with the cheaper linear approximation.
+    /// Defaults to `MixingModel::Nonlinear`.
+    pub fn set_mixing_model(&mut self, model: MixingModel) {
+        self.apu.set_mixing_model(model);
+    }
+
     pub fn take_audio_samples(&mut self) -> alloc::vec::Vec<i16> {
         self.apu.take_samples()
     }
 
+    pub fn take_audio_samples_f32(&mut self) -> alloc::vec::Vec<f32> {
+        self.apu.take_samples_f32()
+    }
+
+    /// Disassemble the CPU address space between `start` and `end`, inclusively, with the PRG bank of each
+    /// instruction. The memory is read without side effects, so the registers are skipped.
     #[cfg(feature = "debugger")]
-    #[allow(unused_variables)] // FIXME
     pub fn disassemble(
-        &self,
+        &mut self,
         start: u16,
         end: u16,
     ) -> alloc::vec::Vec<(Option<u8>, u16, alloc::string::String)> {
-        crate::cpu::disassembler::disassemble(&self.cartridge, 0x4020)
+        let mut cpu_bus = borrow_cpu_bus!(self);
+        crate::cpu::disassembler::disassemble(&mut cpu_bus, start, end)
     }
 
     #[cfg(feature = "debugger")]
@@ -165,110 +919,286 @@ impl Emulator {
         data
     }
 
+    /// Write to the CPU address space, with the same side effects as a write from the CPU
+    #[cfg(feature = "debugger")]
+    pub fn mem_write(&mut self, addr: u16, data: u8) {
+        let mut bus = borrow_cpu_bus!(self);
+        self.cpu.mem_write(&mut bus, addr, data);
+    }
+
     #[cfg(feature = "debugger")]
     pub fn cpu(&self) -> &Cpu {
         &self.cpu
     }
-}
-
-pub fn frame_to_rgb(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240 * 3]) {
-    let empasized_palette = &mut RGB_PALETTE.clone();
-    apply_emphasis(mask_reg, empasized_palette);
 
-    for i in 0..frame.len() {
-        let f = empasized_palette[(frame[i] & 0x3f) as usize];
-        output[i * 3] = f[0]; // R
-        output[i * 3 + 1] = f[1]; // G
-        output[i * 3 + 2] = f[2]; // B
+    #[cfg(feature = "debugger")]
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
     }
-}
 
-pub fn frame_to_rgba(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240 * 4]) {
-    let empasized_palette = &mut RGB_PALETTE.clone();
-    apply_emphasis(mask_reg, empasized_palette);
-
-    for i in 0..frame.len() {
-        let f = empasized_palette[(frame[i] & 0x3f) as usize];
-        output[i * 4] = f[0]; // R
-        output[i * 4 + 1] = f[1]; // G
-        output[i * 4 + 2] = f[2]; // B
+    #[cfg(feature = "debugger")]
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
 
-        // Alpha is always 0xff because it's opaque
-        output[i * 4 + 3] = 0xff; // A
+    /// Pattern tables as seen by the PPU, with the CHR banks currently selected by the mapper
+    #[cfg(feature = "debugger")]
+    pub fn chr_dump(&self) -> alloc::vec::Vec<u8> {
+        self.cartridge.chr_dump()
     }
-}
 
-pub fn frame_to_argb(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240 * 4]) {
-    let empasized_palette = &mut RGB_PALETTE.clone();
-    apply_emphasis(mask_reg, empasized_palette);
+    /// State shown by the debugger, gathered in a single call
+    #[cfg(feature = "debugger")]
+    pub fn debug_info(&self) -> DebugInfo {
+        let mut prg_banks = [None; 4];
+        for (i, bank) in prg_banks.iter_mut().enumerate() {
+            *bank = self.cartridge.get_prg_bank(0x8000 + i as u16 * 0x2000);
+        }
 
-    for i in 0..frame.len() {
-        let f = empasized_palette[(frame[i] & 0x3f) as usize];
-        output[i * 4] = f[2]; // B
-        output[i * 4 + 1] = f[1]; // G
-        output[i * 4 + 2] = f[0]; // R
+        let mut chr_banks = [None; 8];
+        for (i, bank) in chr_banks.iter_mut().enumerate() {
+            *bank = self.cartridge.get_chr_bank(i as u16 * 0x400);
+        }
 
-        // Alpha is always 0xff because it's opaque
-        output[i * 4 + 3] = 0xff; // A
+        DebugInfo {
+            cpu: self.cpu.clone(),
+            scanline: self.ppu.scanline(),
+            cycle: self.ppu.cycle(),
+            scroll: self.ppu.scroll(),
+            mask: self.ppu.mask_reg,
+            nmi_pending: self.cpu.nmi_pending,
+            irq_pending: self.cpu.irq_pending,
+            prg_banks,
+            chr_banks,
+            frame_count: self.frame_count,
+            total_cycles: self.total_cycles,
+        }
     }
-}
 
-pub fn apply_emphasis(mask_reg: MaskReg, new_palette: &mut [[u8; 3]; 64]) {
-    if !mask_reg.contains(MaskReg::EMPHASISE_RED)
-        && !mask_reg.contains(MaskReg::EMPHASISE_GREEN)
-        && !mask_reg.contains(MaskReg::EMPHASISE_BLUE)
-    {
-        return;
+    /// Current layout of the CPU address space
+    #[cfg(feature = "debugger")]
+    pub fn memory_map(&self) -> alloc::vec::Vec<MemoryRegion> {
+        bus::memory_map(&self.cartridge)
     }
 
-    if mask_reg.contains(MaskReg::EMPHASISE_RED)
-        && mask_reg.contains(MaskReg::EMPHASISE_GREEN)
-        && mask_reg.contains(MaskReg::EMPHASISE_BLUE)
-    {
-        for (i, colors) in new_palette.iter_mut().enumerate().take(0x3F) {
-            // 0x0F should not have any emphasis applied to it.
-            if i == 0x0F {
-                continue;
-            }
+    /// Diagnostic events raised since the last call
+    #[cfg(feature = "debugger")]
+    pub fn take_trace_events(&mut self) -> alloc::vec::Vec<TraceEvent> {
+        self.cartridge.take_trace_events()
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn force_sprite_zero_hit(&mut self, state: Option<bool>) {
+        self.ppu.force_sprite_zero_hit(state);
+    }
+
+    /// Whether the game holds the controllers strobe high through `$4016`, which reloads both shift registers
+    #[cfg(feature = "debugger")]
+    pub fn is_controller_strobe_held(&self) -> bool {
+        self.controller_state
+    }
+
+    /// Loopy `v` register of the PPU
+    #[cfg(feature = "debugger")]
+    pub fn vram_address(&self) -> u16 {
+        self.ppu.vram_address()
+    }
+
+    /// Loopy `t` register of the PPU
+    #[cfg(feature = "debugger")]
+    pub fn temp_vram_address(&self) -> u16 {
+        self.ppu.temp_vram_address()
+    }
+
+    /// Loopy `x` register of the PPU
+    #[cfg(feature = "debugger")]
+    pub fn fine_x(&self) -> u8 {
+        self.ppu.fine_x()
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn oam_bytes(&self) -> &[u8; 256] {
+        self.ppu.oam_bytes()
+    }
+
+    #[cfg(feature = "debugger")]
+    pub fn set_oam_bytes(&mut self, oam: &[u8; 256]) {
+        self.ppu.set_oam_bytes(oam);
+    }
+}
+
+/// Same as `frame_to_rgb_with_palette`, with the default 2C02 palette
+pub fn frame_to_rgb(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240 * 3]) {
+    frame_to_rgb_with_palette(&RGB_PALETTE, mask_reg, frame, output);
+}
+
+pub fn frame_to_rgb_with_palette(
+    palette: &[[u8; 3]; 64],
+    mask_reg: MaskReg,
+    frame: &PpuFrame,
+    output: &mut [u8; 256 * 240 * 3],
+) {
+    let mut empasized_palette = *palette;
+    apply_emphasis(mask_reg, &mut empasized_palette);
+
+    for i in 0..frame.len() {
+        let f = empasized_palette[(frame[i] & 0x3f) as usize];
+        output[i * 3] = f[0]; // R
+        output[i * 3 + 1] = f[1]; // G
+        output[i * 3 + 2] = f[2]; // B
+    }
+}
+
+/// Same as `frame_to_rgba_with_palette`, with the default 2C02 palette
+pub fn frame_to_rgba(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240 * 4]) {
+    frame_to_rgba_with_palette(&RGB_PALETTE, mask_reg, frame, output);
+}
+
+pub fn frame_to_rgba_with_palette(
+    palette: &[[u8; 3]; 64],
+    mask_reg: MaskReg,
+    frame: &PpuFrame,
+    output: &mut [u8; 256 * 240 * 4],
+) {
+    let mut empasized_palette = *palette;
+    apply_emphasis(mask_reg, &mut empasized_palette);
+
+    for i in 0..frame.len() {
+        let f = empasized_palette[(frame[i] & 0x3f) as usize];
+        output[i * 4] = f[0]; // R
+        output[i * 4 + 1] = f[1]; // G
+        output[i * 4 + 2] = f[2]; // B
 
-            colors[0] = deemphasize_color(colors[0]);
-            colors[1] = deemphasize_color(colors[1]);
-            colors[2] = deemphasize_color(colors[2]);
+        // Alpha is always 0xff because it's opaque
+        output[i * 4 + 3] = 0xff; // A
+    }
+}
+
+/// Same as `frame_to_argb_with_palette`, with the default 2C02 palette
+pub fn frame_to_argb(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240 * 4]) {
+    frame_to_argb_with_palette(&RGB_PALETTE, mask_reg, frame, output);
+}
+
+pub fn frame_to_argb_with_palette(
+    palette: &[[u8; 3]; 64],
+    mask_reg: MaskReg,
+    frame: &PpuFrame,
+    output: &mut [u8; 256 * 240 * 4],
+) {
+    let mut empasized_palette = *palette;
+    apply_emphasis(mask_reg, &mut empasized_palette);
+
+    for i in 0..frame.len() {
+        let f = empasized_palette[(frame[i] & 0x3f) as usize];
+        output[i * 4] = f[2]; // B
+        output[i * 4 + 1] = f[1]; // G
+        output[i * 4 + 2] = f[0]; // R
+
+        // Alpha is always 0xff because it's opaque
+        output[i * 4 + 3] = 0xff; // A
+    }
+}
+
+/// Same as `frame_to_gray_with_palette`, with the default 2C02 palette
+pub fn frame_to_gray(mask_reg: MaskReg, frame: &PpuFrame, output: &mut [u8; 256 * 240]) {
+    frame_to_gray_with_palette(&RGB_PALETTE, mask_reg, frame, output);
+}
+
+/// Convert the frame to one luminance byte per pixel, for grayscale displays or accessibility.
+/// Unlike the PPU greyscale bit, this keeps the brightness differences between the hues.
+pub fn frame_to_gray_with_palette(
+    palette: &[[u8; 3]; 64],
+    mask_reg: MaskReg,
+    frame: &PpuFrame,
+    output: &mut [u8; 256 * 240],
+) {
+    let mut empasized_palette = *palette;
+    apply_emphasis(mask_reg, &mut empasized_palette);
+
+    let mut gray_palette = [0u8; 64];
+    for (gray, color) in gray_palette.iter_mut().zip(empasized_palette.iter()) {
+        *gray = luminance(*color);
+    }
+
+    for i in 0..frame.len() {
+        output[i] = gray_palette[(frame[i] & 0x3f) as usize];
+    }
+}
+
+// Rec. 601 luma weights, scaled to 256
+fn luminance(color: [u8; 3]) -> u8 {
+    ((77 * color[0] as u16 + 150 * color[1] as u16 + 29 * color[2] as u16) >> 8) as u8
+}
+
+/// Same as `pixels_rgb_with_palette`, with the default 2C02 palette
+pub fn pixels_rgb(
+    mask_reg: MaskReg,
+    frame: &PpuFrame,
+) -> impl Iterator<Item = (u16, u16, [u8; 3])> + '_ {
+    pixels_rgb_with_palette(&RGB_PALETTE, mask_reg, frame)
+}
+
+/// Iterate over the pixels of a frame as `(x, y, rgb)`.
+/// This is slower than the `frame_to_*` converters, use it for one-off reads like tests.
+pub fn pixels_rgb_with_palette<'a>(
+    palette: &[[u8; 3]; 64],
+    mask_reg: MaskReg,
+    frame: &'a PpuFrame,
+) -> impl Iterator<Item = (u16, u16, [u8; 3])> + 'a {
+    let mut empasized_palette = *palette;
+    apply_emphasis(mask_reg, &mut empasized_palette);
+
+    frame.iter().enumerate().map(move |(i, &color)| {
+        (
+            (i % 256) as u16,
+            (i / 256) as u16,
+            empasized_palette[(color & 0x3f) as usize],
+        )
+    })
+}
+
+/// Apply the greyscale and color emphasis bits of the mask register to a palette.
+/// Greyscale keeps only the gray column of the palette, and each emphasis bit dims the other color channels.
+pub fn apply_emphasis(mask_reg: MaskReg, new_palette: &mut [[u8; 3]; 64]) {
+    if mask_reg.contains(MaskReg::GREYSCALE) {
+        let palette = *new_palette;
+        for (i, colors) in new_palette.iter_mut().enumerate() {
+            *colors = palette[i & 0x30];
         }
-    } else {
-        for (i, colors) in new_palette.iter_mut().enumerate().take(0x3F) {
-            // 0x0F should not have any emphasis applied to it.
-            if i == 0x0F {
-                continue;
-            }
+    }
 
-            colors[0] = if mask_reg.contains(MaskReg::EMPHASISE_RED) {
-                emphasize_color(colors[0])
-            } else {
-                deemphasize_color(colors[0])
-            };
+    let emphasis = [
+        MaskReg::EMPHASISE_RED,
+        MaskReg::EMPHASISE_GREEN,
+        MaskReg::EMPHASISE_BLUE,
+    ];
+    let all_emphasis = MaskReg::EMPHASISE_RED | MaskReg::EMPHASISE_GREEN | MaskReg::EMPHASISE_BLUE;
+    if !mask_reg.intersects(all_emphasis) {
+        return;
+    }
 
-            colors[1] = if mask_reg.contains(MaskReg::EMPHASISE_GREEN) {
-                emphasize_color(colors[1])
-            } else {
-                deemphasize_color(colors[1])
-            };
+    for (i, colors) in new_palette.iter_mut().enumerate().take(0x3F) {
+        // 0x0F should not have any emphasis applied to it.
+        if i == 0x0F {
+            continue;
+        }
 
-            colors[2] = if mask_reg.contains(MaskReg::EMPHASISE_BLUE) {
-                emphasize_color(colors[2])
-            } else {
-                deemphasize_color(colors[2])
-            };
+        // With all the bits set, every channel is dimmed
+        for (color, &bit) in colors.iter_mut().zip(emphasis.iter()) {
+            if mask_reg.contains(all_emphasis) || !mask_reg.contains(bit) {
+                *color = deemphasize_color(*color);
+            }
         }
     }
 }
 
 pub fn deemphasize_color(color: u8) -> u8 {
-    // The value (0.85) is hard coded but this isn't very ideal or authentic.
-    let emphasized_color = color as f32 * 0.85;
+    // The real attenuation depends on the color, but 0.75 is close to the average.
+    let emphasized_color = color as f32 * 0.75;
     emphasized_color as u8
 }
 
+#[deprecated(note = "Emphasis only attenuates the other channels, see `deemphasize_color`")]
 pub fn emphasize_color(color: u8) -> u8 {
     // The value (1.1) is hard coded but this isn't very ideal or authentic.
     let mut emphasized_color = color as f32 * 1.1;
@@ -279,3 +1209,1303 @@ pub fn emphasize_color(color: u8) -> u8 {
 
     emphasized_color as u8
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn rom_with_reset_vector(reset_vector: u16) -> Vec<u8> {
+        let mut rom = vec![0u8; 16 + 0x4000];
+        rom[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+        rom[4] = 0x01;
+
+        // The 16KB PRG bank is mirrored at 0xC000, so 0xFFFC lands at 0x3FFC
+        rom[16 + 0x3FFC] = (reset_vector & 0xFF) as u8;
+        rom[16 + 0x3FFD] = (reset_vector >> 8) as u8;
+        rom
+    }
+
+    fn rom_with_program(program: &[u8]) -> Vec<u8> {
+        let mut rom = rom_with_reset_vector(0x8000);
+        rom[16..16 + program.len()].copy_from_slice(program);
+        rom
+    }
+
+    #[test]
+    fn clock_into_swaps_buffers() {
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            // Set the backdrop color, then point v back to the nametables
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x16,       // LDA #$16
+            0x8D, 0x07, 0x20, // STA $2007
+            0xA9, 0x20,       // LDA #$20
+            0x8D, 0x06, 0x20, // STA $2006
+            0x8D, 0x06, 0x20, // STA $2006
+            0x4C, 0x19, 0x80, // JMP $8019
+        ]);
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        let mut frame = alloc::boxed::Box::new([0xFF; 256 * 240]);
+        let buffer = frame.as_ptr();
+
+        for _ in 0..2 {
+            while !emu.clock_into(&mut frame) {}
+            assert!(frame.iter().all(|&c| c == 0x16));
+        }
+
+        // The buffers were swapped back and forth instead of copied
+        assert_eq!(frame.as_ptr(), buffer);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn save_state_round_trip() {
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            0xE6, 0x00,       // INC $00
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA5, 0x00,       // LDA $00
+            0x29, 0x3F,       // AND #$3F
+            0x8D, 0x07, 0x20, // STA $2007, the backdrop color
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0x8D, 0x06, 0x20, // STA $2006
+            0x4C, 0x00, 0x80, // JMP $8000
+        ]);
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.run_frames(10);
+        let state = emu.save_state();
+
+        let expected: Vec<_> = (0..5).map(|_| next_frame(&mut emu)).collect();
+        assert!(expected[0] != expected[1]);
+
+        emu.load_state(&state).unwrap();
+        assert_eq!(emu.frame_count(), 10);
+        let frames: Vec<_> = (0..5).map(|_| next_frame(&mut emu)).collect();
+        assert!(frames == expected);
+
+        // A bad state doesn't change anything
+        let total_cycles = emu.total_cycles();
+        assert_eq!(
+            emu.load_state(&state[..state.len() - 1]),
+            Err(StateError::Truncated)
+        );
+        assert_eq!(emu.total_cycles(), total_cycles);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn save_slots() {
+        let mut emu = Emulator::new(&rom_with_lagging_input(), None).unwrap();
+        let mut slots = SaveStateManager::new(4);
+        assert_eq!(slots.slot_count(), 4);
+        assert_eq!(slots.load_slot(2, &mut emu), Err(StateError::EmptySlot));
+        assert_eq!(slots.load_slot(7, &mut emu), Err(StateError::EmptySlot));
+
+        emu.clock_n(100_000);
+        slots.save_slot(2, &emu);
+        let slot2_cycles = emu.total_cycles();
+
+        emu.clock_n(10_000);
+        slots.save_slot(0, &emu);
+        emu.clock_n(10_000);
+        slots.save_slot(0, &emu);
+        let slot0_cycles = emu.total_cycles();
+        assert!(slots.occupied_slots().eq([0, 2].iter().copied()));
+        assert!(!slots.slot_exists(1));
+
+        // Overwriting slot 0 left slot 2 as it was
+        emu.clock_n(10_000);
+        slots.load_slot(2, &mut emu).unwrap();
+        assert_eq!(emu.total_cycles(), slot2_cycles);
+        slots.load_slot(0, &mut emu).unwrap();
+        assert_eq!(emu.total_cycles(), slot0_cycles);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_state_resumes_emulation() {
+        let mut emu = Emulator::new(&rom_with_lagging_input(), None).unwrap();
+        emu.set_controller1(0x80);
+        emu.clock_n(100_000);
+        let state = emu.save_state();
+
+        emu.clock_n(10_000);
+        let continuation = emu.save_state();
+        emu.clock_n(10_000);
+
+        // The states include the frame being rendered, so the output matches too
+        emu.load_state(&state).unwrap();
+        emu.clock_n(10_000);
+        assert!(emu.save_state() == continuation);
+
+        // States from another version of the format are rejected
+        let mut old_state = state;
+        old_state[4] = old_state[4].wrapping_sub(1);
+        assert_eq!(emu.load_state(&old_state), Err(StateError::VersionMismatch));
+        assert_eq!(emu.load_state(b"NOPE000000"), Err(StateError::Corrupt));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_earlier_state_while_recording() {
+        let mut emu = Emulator::new(&rom_with_lagging_input(), None).unwrap();
+        emu.set_input_delay(2);
+        emu.run_frames(3);
+        emu.start_recording();
+        emu.run_frames(2);
+        let state = emu.save_state();
+
+        emu.run_frames(5);
+        emu.reset_performance_stats();
+        emu.set_controller1(Buttons::A.bits());
+        emu.load_state(&state).unwrap();
+        assert_eq!(emu.frame_count(), 5);
+        assert_eq!(emu.performance_stats().frames, 0);
+
+        // The delayed input is still 2 frames away
+        emu.run_frames(1);
+        assert_eq!(emu.controller1, 0);
+        emu.run_frames(1);
+        assert_eq!(emu.controller1, Buttons::A.bits());
+        assert_eq!(emu.performance_stats().frames, 2);
+
+        // The movie continues from the loaded frame
+        let movie = emu.stop_recording().unwrap();
+        assert_eq!(movie.last_frame(), 4);
+        assert!(movie.inputs().iter().all(|&(frame, _, _)| frame >= 2));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn load_state_errors() {
+        let mut emu = Emulator::new(&rom_with_lagging_input(), None).unwrap();
+        emu.clock_n(10_000);
+        let state = emu.save_state();
+        let total_cycles = emu.total_cycles();
+
+        assert_eq!(emu.load_state(&state[..8]), Err(StateError::Truncated));
+        assert_eq!(
+            emu.load_state(&state[..state.len() / 2]),
+            Err(StateError::Truncated)
+        );
+
+        // States from the first version of the format only had the magic and the version in their header
+        let mut old_state = b"NSTA".to_vec();
+        old_state.extend_from_slice(&1u16.to_le_bytes());
+        old_state.extend_from_slice(&state[10..]);
+        assert_eq!(emu.load_state(&old_state), Err(StateError::VersionMismatch));
+
+        let mut other_game = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        assert_eq!(other_game.load_state(&state), Err(StateError::WrongGame));
+        assert_eq!(emu.total_cycles(), total_cycles);
+    }
+
+    #[test]
+    fn patched_rom_boots() {
+        let rom = rom_with_reset_vector(0x8000);
+
+        // Replace the program with one that stores $42 in RAM
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x07]);
+        patch.extend_from_slice(&[0xA9, 0x42, 0x85, 0x10, 0x4C, 0x04, 0x80]);
+        patch.extend_from_slice(b"EOF");
+
+        let mut emu = Emulator::new_with_patch(&rom, &patch, None).unwrap();
+        emu.run_frame();
+        assert_eq!(emu.ram[0x10], 0x42);
+
+        assert_eq!(
+            Emulator::new_with_patch(&rom, b"PATCH", None).err(),
+            Some(RomParserError::InvalidPatch(PatchError::Truncated))
+        );
+    }
+
+    #[test]
+    fn apply_patch_writes_prg_ram() {
+        // MMC1 has PRG RAM
+        let mut rom = rom_with_reset_vector(0x8000);
+        rom[6] = 0x10;
+        let mut emu = Emulator::new(&rom, None).unwrap();
+
+        let patches = [
+            (0x6010, 0xAB),
+            (0x8000, 0xCD),
+            (0x0000, 0xEF),
+            (0x7FFF, 0x12),
+        ];
+        assert_eq!(emu.apply_patch(&patches), 2);
+        assert_eq!(emu.cartridge.read_prg_mem(0x6010), 0xAB);
+        assert_eq!(emu.cartridge.read_prg_mem(0x7FFF), 0x12);
+        assert_eq!(emu.cartridge.read_prg_mem(0x8000), 0x00);
+        assert_eq!(emu.ram[0], 0x00);
+
+        // NROM doesn't have any
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        assert_eq!(emu.apply_patch(&[(0x6010, 0xAB)]), 0);
+    }
+
+    #[test]
+    fn apply_chr_patch_writes_chr_ram() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        let patches = [(0x0010, 0xAB), (0x1FFF, 0x12), (0x2000, 0xCD)];
+        assert_eq!(emu.apply_chr_patch(&patches), 2);
+        assert_eq!(emu.cartridge.read_chr_mem(0x0010), 0xAB);
+        assert_eq!(emu.cartridge.read_chr_mem(0x1FFF), 0x12);
+
+        // CHR ROM can't be patched
+        let mut rom = rom_with_reset_vector(0x8000);
+        rom[5] = 0x01;
+        rom.extend_from_slice(&[0u8; 0x2000]);
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        assert_eq!(emu.apply_chr_patch(&[(0x0010, 0xAB)]), 0);
+        assert_eq!(emu.cartridge.read_chr_mem(0x0010), 0x00);
+    }
+
+    #[test]
+    fn battery_save_round_trip() {
+        // MMC1 with a battery
+        #[rustfmt::skip]
+        let mut rom = rom_with_program(&[
+            0xAD, 0x00, 0x60, // LDA $6000
+            0x18,             // CLC
+            0x69, 0x01,       // ADC #$01
+            0x8D, 0x00, 0x60, // STA $6000
+            0x4C, 0x09, 0x80, // JMP $8009
+        ]);
+        rom[6] = 0x12;
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        assert!(!emu.is_save_dirty());
+        emu.run_frame();
+        assert!(emu.is_save_dirty());
+        let save = emu.get_save_data().unwrap().to_vec();
+        assert_eq!(save.len(), 0x2000);
+        assert_eq!(save[0], 0x01);
+
+        let mut emu = Emulator::new(&rom, Some(&save)).unwrap();
+        assert!(!emu.is_save_dirty());
+        emu.run_frame();
+        assert_eq!(emu.get_save_data().unwrap()[0], 0x02);
+
+        // Without a battery, the RAM isn't saved
+        rom[6] = 0x10;
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.run_frame();
+        assert!(!emu.is_save_dirty());
+        assert_eq!(emu.get_save_data(), None);
+    }
+
+    #[test]
+    fn zapper_light_sense() {
+        // Make the backdrop white
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x30,       // LDA #$30
+            0x8D, 0x07, 0x20, // STA $2007
+            0x4C, 0x0F, 0x80, // JMP $800F
+        ]);
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.run_frame();
+        emu.set_zapper(100, 50, true);
+
+        fn read_at(emu: &mut Emulator, scanline: i16, cycle: u16) -> u8 {
+            while (emu.ppu.scanline(), emu.ppu.cycle()) != (scanline, cycle) {
+                emu.clock();
+            }
+            let mut bus = borrow_cpu_bus!(emu);
+            bus.read_controller2_snapshot()
+        }
+
+        // The trigger is in bit 4, and bit 3 is cleared while the sensor sees light
+        assert_eq!(read_at(&mut emu, 50, 100), 0x18);
+        assert_eq!(read_at(&mut emu, 50, 101), 0x10);
+        assert_eq!(read_at(&mut emu, 70, 0), 0x10);
+        assert_eq!(read_at(&mut emu, 76, 0), 0x18);
+
+        // Aiming off-screen never sees anything
+        emu.set_zapper(300, 50, false);
+        assert_eq!(read_at(&mut emu, 50, 200), 0x08);
+        assert_eq!(read_at(&mut emu, 60, 0), 0x08);
+        emu.set_zapper(100, 250, false);
+        assert_eq!(read_at(&mut emu, 250, 200), 0x08);
+        assert_eq!(read_at(&mut emu, 10, 0), 0x08);
+
+        emu.disconnect_zapper();
+        emu.set_controller2(Buttons::A.bits());
+        emu.run_frame();
+        let mut bus = borrow_cpu_bus!(emu);
+        bus.controller_write(1);
+        assert_eq!(bus.read_controller2_snapshot(), 1);
+    }
+
+    #[test]
+    fn controller_turbo() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        emu.set_controller1_turbo_mask(Buttons::A.bits());
+        emu.set_controller2_turbo_mask(Buttons::B.bits());
+        emu.set_turbo_period(2);
+        emu.start_recording();
+        emu.set_controller1((Buttons::A | Buttons::B).bits());
+        emu.set_controller2((Buttons::A | Buttons::B).bits());
+
+        // The autofire buttons are pressed for 2 frames, then released for 2 frames,
+        // while the other buttons stay held
+        let mut states = Vec::new();
+        for _ in 0..8 {
+            states.push((emu.controller1, emu.controller2));
+            emu.run_frame();
+        }
+        let on = (Buttons::A | Buttons::B).bits();
+        let phase = states.iter().position(|&(port1, _)| port1 == on).unwrap();
+        for (frame, &(port1, port2)) in states.iter().enumerate().skip(phase) {
+            let pressed = (frame - phase) % 4 < 2;
+            assert_eq!(port1, if pressed { on } else { Buttons::B.bits() });
+            assert_eq!(port2, if pressed { on } else { Buttons::A.bits() });
+        }
+
+        // The pulses are in the movie, so it replays the same without the masks
+        let movie = emu.stop_recording().unwrap();
+        let mut replay = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        replay.play_movie(&movie).unwrap();
+        for &state in &states {
+            assert_eq!((replay.controller1, replay.controller2), state);
+            replay.run_frame();
+        }
+
+        emu.set_controller1_turbo_mask(0);
+        emu.run_frame();
+        assert_eq!(emu.controller1, on);
+    }
+
+    #[test]
+    fn reset_vector_from_fixed_bank() {
+        // MMC1 with 4 PRG banks, each with its own reset vector
+        let mut rom = vec![0u8; 16 + 0x4000 * 4];
+        rom[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+        rom[4] = 0x04;
+        rom[6] = 0x10;
+        for (bank, vector) in [0x8000u16, 0x9000, 0xA000, 0xC000].iter().enumerate() {
+            let offset = 16 + bank * 0x4000 + 0x3FFC;
+            rom[offset..offset + 2].copy_from_slice(&vector.to_le_bytes());
+        }
+
+        // Switch to 32KB mode, which maps banks 0 and 1 at $8000
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x00, 0x80, // STA $8000
+            0x8D, 0x00, 0x80, // STA $8000
+            0x8D, 0x00, 0x80, // STA $8000
+            0x8D, 0x00, 0x80, // STA $8000
+            0x8D, 0x00, 0x80, // STA $8000
+            0x4C, 0x11, 0xC0, // JMP $C011
+        ];
+        let last_bank = 16 + 3 * 0x4000;
+        rom[last_bank..last_bank + program.len()].copy_from_slice(&program);
+        // Bank 1 is now at $C000, so the loop must be there too
+        rom[16 + 0x4000 + 0x11..16 + 0x4000 + 0x14].copy_from_slice(&[0x4C, 0x11, 0xC0]);
+
+        // At power-on, the last bank is fixed at $C000
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        assert_eq!(emu.cpu.pc, 0xC000);
+
+        // A reset doesn't reach the mapper, so the vector comes from the switched bank
+        emu.run_frame();
+        emu.reset();
+        assert_eq!(emu.cpu.pc, 0x9000);
+
+        emu.power_cycle();
+        assert_eq!(emu.cpu.pc, 0xC000);
+
+        // UxROM always has the last bank at $C000
+        rom[6] = 0x20;
+        let emu = Emulator::new(&rom, None).unwrap();
+        assert_eq!(emu.cpu.pc, 0xC000);
+    }
+
+    #[test]
+    fn swap_media() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        assert_eq!(emu.cpu.pc, 0x8000);
+
+        emu.eject_media();
+        assert!(!emu.is_media_inserted());
+        for _ in 0..100 {
+            assert!(emu.clock().is_none());
+        }
+        assert_eq!(emu.cpu.pc, 0x8000);
+
+        emu.insert_media(&rom_with_reset_vector(0x9123), None, false)
+            .unwrap();
+        assert!(emu.is_media_inserted());
+        assert_eq!(emu.cpu.pc, 0x9123);
+    }
+
+    #[test]
+    fn swap_media_keep_running() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+
+        emu.eject_media();
+        emu.insert_media(&rom_with_reset_vector(0x9123), None, true)
+            .unwrap();
+        assert_eq!(emu.cpu.pc, 0x8000);
+    }
+
+    #[test]
+    fn insert_invalid_media() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+
+        emu.eject_media();
+        assert!(emu.insert_media(&[0u8; 4], None, false).is_err());
+        assert!(!emu.is_media_inserted());
+    }
+
+    #[test]
+    fn queued_input_applied_at_frame() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+
+        emu.queue_input(3, ControllerPort::One, Buttons::START);
+        emu.queue_input(4, ControllerPort::Two, Buttons::A | Buttons::LEFT);
+        emu.queue_input(5, ControllerPort::One, Buttons::empty());
+
+        for _ in 0..2 {
+            emu.run_frame();
+            assert_eq!(emu.controller1, 0);
+        }
+
+        emu.run_frame();
+        assert_eq!(emu.frame_count(), 3);
+        assert_eq!(emu.controller1, Buttons::START.bits());
+        assert_eq!(emu.controller2, 0);
+
+        emu.run_frame();
+        assert_eq!(emu.controller1, Buttons::START.bits());
+        assert_eq!(emu.controller2, (Buttons::A | Buttons::LEFT).bits());
+
+        emu.run_frame();
+        assert_eq!(emu.controller1, 0);
+        assert!(emu.input_queue.is_empty());
+    }
+
+    #[test]
+    fn queued_input_in_the_past() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        emu.run_frame();
+
+        emu.queue_input(0, ControllerPort::One, Buttons::B);
+        assert_eq!(emu.controller1, Buttons::B.bits());
+    }
+
+    #[test]
+    fn input_delay() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+
+        // Without delay, the input is applied immediately
+        emu.set_controller1(Buttons::B.bits());
+        assert_eq!(emu.controller1, Buttons::B.bits());
+
+        emu.set_input_delay(3);
+        emu.run_frame();
+
+        emu.set_controller1(Buttons::A.bits());
+        for _ in 0..2 {
+            emu.run_frame();
+            assert_eq!(emu.controller1, Buttons::B.bits());
+        }
+
+        emu.run_frame();
+        assert_eq!(emu.controller1, Buttons::A.bits());
+    }
+
+    #[test]
+    fn set_button() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+
+        emu.set_button(ControllerPort::One, Buttons::A, true);
+        emu.set_button(ControllerPort::One, Buttons::UP, true);
+        emu.set_button(ControllerPort::Two, Buttons::START, true);
+        emu.set_button(ControllerPort::One, Buttons::A, false);
+        assert_eq!(emu.controller(ControllerPort::One), Buttons::UP);
+        assert_eq!(emu.controller(ControllerPort::Two), Buttons::START);
+        assert_eq!(emu.controller1, Buttons::UP.bits());
+        assert_eq!(emu.controller2, Buttons::START.bits());
+
+        // The delayed state is the one that's updated
+        emu.set_input_delay(1);
+        emu.set_button(ControllerPort::One, Buttons::B, true);
+        assert_eq!(
+            emu.controller(ControllerPort::One),
+            Buttons::UP | Buttons::B
+        );
+        assert_eq!(emu.controller1, Buttons::UP.bits());
+
+        emu.run_frame();
+        assert_eq!(emu.controller1, (Buttons::UP | Buttons::B).bits());
+
+        emu.set_controller2(0);
+        assert!(emu.controller(ControllerPort::Two).is_empty());
+    }
+
+    #[test]
+    fn nmi_pending_until_serviced() {
+        let mut rom = rom_with_reset_vector(0x8000);
+        // NMI handler at 0x9000
+        rom[16 + 0x3FFA] = 0x00;
+        rom[16 + 0x3FFB] = 0x90;
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.ppu.set_ctrl(ppu::registers::ControlReg::GENERATE_NMI);
+
+        while !emu.cpu.nmi_pending {
+            emu.clock();
+        }
+        assert!(emu.is_vblank());
+        assert_ne!(emu.cpu.pc, 0x9000);
+
+        while emu.cpu.nmi_pending {
+            emu.clock();
+        }
+        assert_eq!(emu.cpu.pc, 0x9000);
+    }
+
+    #[test]
+    fn apu_frame_irq() {
+        let frame_irq_rom = |frame_counter: u8| {
+            let mut rom = rom_with_reset_vector(0x8000);
+            #[rustfmt::skip]
+            let reset = [
+                0xA9, frame_counter, // LDA #frame_counter
+                0x8D, 0x17, 0x40,    // STA $4017
+                0x58,                // CLI
+                0x4C, 0x06, 0x80,    // JMP $8006
+            ];
+            rom[16..16 + reset.len()].copy_from_slice(&reset);
+
+            // Count the interrupts, and acknowledge them
+            #[rustfmt::skip]
+            let irq = [
+                0xE6, 0x00,       // INC $00
+                0xAD, 0x15, 0x40, // LDA $4015
+                0x40,             // RTI
+            ];
+            rom[16 + 0x1000..16 + 0x1000 + irq.len()].copy_from_slice(&irq);
+            rom[16 + 0x3FFE] = 0x00;
+            rom[16 + 0x3FFF] = 0x90;
+            rom
+        };
+
+        // In 4-step mode, the IRQ is raised every 29830 cycles
+        let mut emu = Emulator::new(&frame_irq_rom(0x00), None).unwrap();
+        emu.clock_n(29830 * 3 + 100);
+        assert_eq!(emu.ram[0], 3);
+        assert!(!emu.cpu.irq_pending);
+
+        // It's never raised when inhibited, or in 5-step mode
+        for &frame_counter in &[0x40, 0x80] {
+            let mut emu = Emulator::new(&frame_irq_rom(frame_counter), None).unwrap();
+            emu.clock_n(29830 * 3 + 100);
+            assert_eq!(emu.ram[0], 0);
+        }
+    }
+
+    #[cfg(feature = "opcode-coverage")]
+    #[test]
+    fn executed_opcodes() {
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            0xA2, 0x03,       // LDX #$03
+            0xCA,             // DEX
+            0xD0, 0xFD,       // BNE $8002
+            0x04, 0x00,       // NOP $00, unofficial
+            0x4C, 0x05, 0x80, // JMP $8005
+        ]);
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.clock_n(1000);
+
+        let executed = emu.executed_opcodes();
+        let expected = [0xA2, 0xCA, 0xD0, 0x04, 0x4C];
+        for (opcode, &executed) in executed.iter().enumerate() {
+            assert_eq!(
+                executed,
+                expected.contains(&opcode),
+                "opcode {:#04X}",
+                opcode
+            );
+        }
+
+        // It covers the whole run, so it survives a reset
+        emu.reset();
+        assert_eq!(emu.executed_opcodes(), executed);
+    }
+
+    #[test]
+    fn clock_n_cycles() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        let ppu_dot =
+            |emu: &Emulator| (emu.ppu.scanline() as i32 + 1) * 341 + emu.ppu.cycle() as i32;
+
+        emu.clock_n(1);
+        let cycles = emu.total_cycles();
+        let dot = ppu_dot(&emu);
+
+        emu.clock_n(1000);
+        assert_eq!(emu.total_cycles() - cycles, 1000);
+        assert_eq!(ppu_dot(&emu) - dot, 3000);
+
+        emu.clock_n(0);
+        assert_eq!(emu.total_cycles() - cycles, 1000);
+    }
+
+    /// Program that shows the A button state in the backdrop color one frame after it's read, like most games do
+    fn rom_with_lagging_input() -> Vec<u8> {
+        let mut rom = rom_with_reset_vector(0x8000);
+
+        // Enable NMI and loop forever
+        let reset = [0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0x80];
+        rom[16..16 + reset.len()].copy_from_slice(&reset);
+
+        #[rustfmt::skip]
+        let nmi = [
+            // Write the color read during the previous frame
+            0xA6, 0x00,       // LDX $00
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0x8E, 0x07, 0x20, // STX $2007
+            0xA9, 0x20,       // LDA #$20
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            // Read the A button
+            0xA9, 0x01,       // LDA #$01
+            0x8D, 0x16, 0x40, // STA $4016
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x16, 0x40, // STA $4016
+            0xAD, 0x16, 0x40, // LDA $4016
+            0x29, 0x01,       // AND #$01
+            0xF0, 0x05,       // BEQ +5
+            0xA9, 0x16,       // LDA #$16
+            0x85, 0x00,       // STA $00
+            0x40,             // RTI
+            0xA9, 0x0F,       // LDA #$0F
+            0x85, 0x00,       // STA $00
+            0x40,             // RTI
+        ];
+        rom[16 + 0x100..16 + 0x100 + nmi.len()].copy_from_slice(&nmi);
+        rom[16 + 0x3FFA] = 0x00;
+        rom[16 + 0x3FFB] = 0x81;
+
+        rom
+    }
+
+    fn next_frame(emu: &mut Emulator) -> ppu::PpuFrame {
+        loop {
+            if let Some(frame) = emu.clock() {
+                break *frame;
+            }
+        }
+    }
+
+    #[test]
+    fn run_ahead_hides_input_lag() {
+        let rom = rom_with_lagging_input();
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        let mut emu_ahead = Emulator::new(&rom, None).unwrap();
+        emu_ahead.set_run_ahead(1);
+
+        // Wait for the first inputs to be read
+        for _ in 0..5 {
+            next_frame(&mut emu);
+            next_frame(&mut emu_ahead);
+        }
+        assert!(next_frame(&mut emu).iter().all(|&c| c == 0x0F));
+        assert!(next_frame(&mut emu_ahead).iter().all(|&c| c == 0x0F));
+
+        emu.set_controller1(Buttons::A.bits());
+        emu_ahead.set_controller1(Buttons::A.bits());
+
+        let frames_until_pressed = |emu: &mut Emulator| {
+            (1..10)
+                .find(|_| next_frame(emu).iter().all(|&c| c == 0x16))
+                .unwrap()
+        };
+        assert_eq!(frames_until_pressed(&mut emu), 2);
+        assert_eq!(frames_until_pressed(&mut emu_ahead), 1);
+
+        // The emulation itself progresses the same way
+        next_frame(&mut emu_ahead);
+        assert_eq!(emu.frame_count(), emu_ahead.frame_count());
+        assert_eq!(emu.total_cycles(), emu_ahead.total_cycles());
+        assert_eq!(emu.ram, emu_ahead.ram);
+        assert_eq!(emu.cpu.pc, emu_ahead.cpu.pc);
+    }
+
+    #[test]
+    fn pixels_rgb_spot_check() {
+        let mut frame = [0x0Fu8; 256 * 240];
+        frame[10 * 256 + 20] = 0x16;
+
+        let pixels: Vec<_> = pixels_rgb(MaskReg::empty(), &frame).collect();
+        assert_eq!(pixels.len(), 256 * 240);
+        assert_eq!(pixels[10 * 256 + 20], (20, 10, RGB_PALETTE[0x16]));
+        assert_eq!(pixels[256 * 240 - 1], (255, 239, RGB_PALETTE[0x0F]));
+
+        let (_, _, color) = pixels_rgb_with_palette(&RGB_PALETTE_2C03, MaskReg::empty(), &frame)
+            .nth(10 * 256 + 20)
+            .unwrap();
+        assert_eq!(color, RGB_PALETTE_2C03[0x16]);
+
+        let color = RGB_PALETTE[0x16];
+        let expected = [
+            color[0],
+            deemphasize_color(color[1]),
+            deemphasize_color(color[2]),
+        ];
+        let (_, _, emphasized) = pixels_rgb(MaskReg::EMPHASISE_RED, &frame)
+            .find(|&(x, y, _)| x == 20 && y == 10)
+            .unwrap();
+        assert_eq!(emphasized, expected);
+    }
+
+    #[test]
+    fn frame_to_gray_luminance() {
+        let mut frame = [0x0Fu8; 256 * 240];
+        frame[0] = 0x16; // Red
+        frame[1] = 0x01; // Blue
+        frame[2] = 0x30; // White
+
+        let mut gray = [0u8; 256 * 240];
+        frame_to_gray(MaskReg::empty(), &frame, &mut gray);
+        assert_eq!(gray[0], 107);
+        assert_eq!(gray[1], 28);
+        assert_eq!(gray[2], 252);
+        assert_eq!(gray[3], 0);
+    }
+
+    #[test]
+    fn mask_in_converters() {
+        let mut frame = [0x16u8; 256 * 240];
+        frame[1] = 0x0F;
+        let mut output = [0u8; 256 * 240 * 3];
+
+        // Like the wide flashes of Just Breed, all the emphasis bits dim the whole screen
+        let mask_reg = MaskReg::EMPHASISE_RED | MaskReg::EMPHASISE_GREEN | MaskReg::EMPHASISE_BLUE;
+        frame_to_rgb(mask_reg, &frame, &mut output);
+        let color = RGB_PALETTE[0x16];
+        assert_eq!(output[..3], color.map(deemphasize_color));
+        assert!(luminance([output[0], output[1], output[2]]) < luminance(color));
+        assert_eq!(output[3..6], [0, 0, 0]);
+
+        // Greyscale takes the gray of the same brightness
+        frame_to_rgb(MaskReg::GREYSCALE, &frame, &mut output);
+        assert_eq!(output[..3], RGB_PALETTE[0x10]);
+        assert_eq!(output[3..6], RGB_PALETTE[0x00]);
+
+        let mut rgba = [0u8; 256 * 240 * 4];
+        frame_to_rgba(MaskReg::GREYSCALE, &frame, &mut rgba);
+        assert_eq!(
+            rgba[..4],
+            [
+                RGB_PALETTE[0x10][0],
+                RGB_PALETTE[0x10][1],
+                RGB_PALETTE[0x10][2],
+                0xFF
+            ]
+        );
+    }
+
+    #[test]
+    fn select_palette_by_name() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        assert_eq!(emu.palette(), &RGB_PALETTE);
+
+        emu.set_palette_by_name("2C03").unwrap();
+        assert_eq!(emu.palette()[0], [0x6D, 0x6D, 0x6D]);
+
+        assert_eq!(
+            emu.set_palette_by_name("2C01"),
+            Err(PaletteError::UnknownName)
+        );
+        assert_eq!(emu.palette(), &RGB_PALETTE_2C03);
+
+        emu.set_palette_by_name("2C07-PAL").unwrap();
+        assert_eq!(emu.palette(), &RGB_PALETTE_2C07);
+        emu.set_palette_by_name("FBX-NostalgiaFBX").unwrap();
+        assert_eq!(emu.palette()[0], [0x6A, 0x6D, 0x6A]);
+
+        for name in PALETTE_NAMES.iter() {
+            assert!(palette_by_name(name).is_ok());
+        }
+    }
+
+    #[test]
+    fn oam_dma_suspends_cpu() {
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            0xA9, 0x02,       // LDA #$02
+            0x8D, 0x14, 0x40, // STA $4014
+            0xEA,             // NOP
+            0x8D, 0x14, 0x40, // STA $4014
+            0x4C, 0x09, 0x80, // JMP $8009
+        ]);
+        let mut emu = Emulator::new(&rom, None).unwrap();
+
+        let mut instruction_cycles = || {
+            let start = emu.total_cycles();
+            emu.clock_n(1);
+            while emu.cpu.cycles > 0 {
+                emu.clock_n(1);
+            }
+            (start, emu.total_cycles() - start)
+        };
+
+        // Finish the reset
+        instruction_cycles();
+        assert_eq!(instruction_cycles().1, 2);
+
+        // The STA takes 4 cycles, then the DMA takes 513 cycles, or 514 if the write was on an odd cycle
+        let dma_cycles = |(start, cycles): (u64, u64)| {
+            let write_cycle = start + 4;
+            assert_eq!(cycles - 4, if write_cycle % 2 == 1 { 514 } else { 513 });
+            cycles
+        };
+        let first = dma_cycles(instruction_cycles());
+        assert_eq!(instruction_cycles().1, 2);
+        let second = dma_cycles(instruction_cycles());
+
+        // Both parities are covered
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn frame_hash() {
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            // Set the backdrop color, then point v back to the nametables
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x16,       // LDA #$16
+            0x8D, 0x07, 0x20, // STA $2007
+            0xA9, 0x20,       // LDA #$20
+            0x8D, 0x06, 0x20, // STA $2006
+            0x8D, 0x06, 0x20, // STA $2006
+            0x4C, 0x17, 0x80, // JMP $8017
+        ]);
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        let mut blank = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        assert!(emu.run_frames(3).iter().all(|&c| c == 0x16));
+        blank.run_frames(3);
+
+        // Pinned, so changes to the hash function are noticed
+        assert_eq!(emu.frame_hash(), 0xA7BADD43C829E325);
+        assert_ne!(emu.frame_hash(), blank.frame_hash());
+
+        emu.set_palette_by_name("2C03").unwrap();
+        assert_eq!(emu.frame_hash(), 0xA7BADD43C829E325);
+    }
+
+    #[test]
+    fn pre_run_frames() {
+        let rom = rom_with_reset_vector(0x8000);
+
+        let emu = Emulator::new_with_pre_run(&rom, None, 0).unwrap();
+        assert_eq!(emu.frame_count(), 0);
+
+        let emu = Emulator::new_with_pre_run(&rom, None, 5).unwrap();
+        assert_eq!(emu.frame_count(), 5);
+
+        let mut reference = Emulator::new(&rom, None).unwrap();
+        reference.run_frames(5);
+        assert_eq!(emu.total_cycles(), reference.total_cycles());
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn memory_map_regions() {
+        let emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        let map = emu.memory_map();
+
+        let region = |label| {
+            let region = map.iter().find(|r| r.label == label).unwrap();
+            (region.start, region.end)
+        };
+        assert_eq!(region("RAM"), (0x0000, 0x1FFF));
+        assert_eq!(region("PPU registers"), (0x2000, 0x3FFF));
+        assert_eq!(region("APU and I/O registers"), (0x4000, 0x4017));
+
+        // The only 16KB bank is mirrored in both halves
+        let prg: Vec<_> = map.iter().filter(|r| r.label == "PRG ROM").collect();
+        assert_eq!(prg.len(), 2);
+        assert_eq!(
+            (prg[0].start, prg[0].end, prg[0].prg_bank),
+            (0x8000, 0xBFFF, Some(0))
+        );
+        assert_eq!(
+            (prg[1].start, prg[1].end, prg[1].prg_bank),
+            (0xC000, 0xFFFF, Some(0))
+        );
+
+        // The regions cover the whole address space, in order
+        assert_eq!(map[0].start, 0);
+        assert_eq!(map.last().unwrap().end, 0xFFFF);
+        for pair in map.windows(2) {
+            assert_eq!(pair[0].end + 1, pair[1].start);
+        }
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn strobe_both_controllers() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        emu.set_controller1((Buttons::A | Buttons::START).bits());
+        emu.set_controller2((Buttons::B | Buttons::LEFT).bits());
+
+        emu.mem_write(0x4016, 0x01);
+        assert!(emu.is_controller_strobe_held());
+        emu.mem_write(0x4016, 0x00);
+        assert!(!emu.is_controller_strobe_held());
+
+        let mut port1 = Vec::new();
+        let mut port2 = Vec::new();
+        for _ in 0..8 {
+            port1.push(emu.mem_dump(0x4016, 0x4016)[0] & 0x01);
+            port2.push(emu.mem_dump(0x4017, 0x4017)[0] & 0x01);
+        }
+        assert_eq!(port1, [1, 0, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(port2, [0, 1, 0, 0, 0, 0, 1, 0]);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn chr_dump_follows_banks() {
+        // CNROM with 4 CHR banks, each filled with its number
+        let mut rom = rom_with_reset_vector(0x8000);
+        rom[6] = 0x30;
+        rom[5] = 0x04;
+        for bank in 0..4u8 {
+            rom.extend_from_slice(&[bank; 0x2000]);
+        }
+        rom[16 + 0x4000 + 0x10] = 0xAB;
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        let chr = emu.chr_dump();
+        assert_eq!(chr.len(), 0x2000);
+        assert_eq!(chr[0x10], 0xAB);
+        assert!(chr[0x11..].iter().all(|&b| b == 0));
+
+        emu.mem_write(0x8000, 0x02);
+        assert!(emu.chr_dump().iter().all(|&b| b == 2));
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn chr_dump_matches_rom_tiles() {
+        // NROM game with a single 8KB CHR ROM bank, right after its PRG ROM
+        const ROM: &[u8] = include_bytes!("../../default_roms/nestest.nes");
+        let chr_start = 16 + ROM[4] as usize * 0x4000;
+
+        let emu = Emulator::new(ROM, None).unwrap();
+        let chr = emu.chr_dump();
+        assert_eq!(chr[..], ROM[chr_start..chr_start + 0x2000]);
+
+        // The tiles aren't blank, so this isn't comparing two empty banks
+        assert!(chr.iter().any(|&b| b != 0));
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn disassemble_cpu_address_space() {
+        let mut rom = rom_with_reset_vector(0x8000);
+        rom[16..18].copy_from_slice(&[0xA2, 0x07]); // LDX #$07
+        let mut emu = Emulator::new(&rom, None).unwrap();
+
+        // LDA #$42, STA $0400 in RAM
+        for (i, &byte) in [0xA9, 0x42, 0x8D, 0x00, 0x04].iter().enumerate() {
+            emu.mem_write(0x0300 + i as u16, byte);
+        }
+
+        // The instruction at the end is decoded with its operands
+        let disassembly = emu.disassemble(0x0300, 0x0302);
+        let expected = [(None, 0x0300, "lda #0x42"), (None, 0x0302, "sta 0x400")];
+        assert_eq!(disassembly.len(), expected.len());
+        for (line, &(bank, addr, text)) in disassembly.iter().zip(expected.iter()) {
+            assert_eq!(*line, (bank, addr, text.into()));
+        }
+
+        // RAM mirrors and PRG ROM
+        assert_eq!(emu.disassemble(0x0B00, 0x0B00)[0].2, "lda #0x42");
+        assert_eq!(
+            emu.disassemble(0x8000, 0x8000),
+            [(Some(0), 0x8000, "ldx #0x7".into())]
+        );
+
+        // The registers are skipped, and reading them had no effect
+        emu.mem_write(0x2006, 0x3F);
+        emu.mem_write(0x2006, 0x00);
+        assert!(emu.disassemble(0x2000, 0x401F).is_empty());
+        assert_eq!(emu.ppu().vram_address(), 0x3F00);
+
+        assert!(emu.disassemble(0x0302, 0x0300).is_empty());
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn step_cpu_instruction() {
+        #[rustfmt::skip]
+        let rom = rom_with_program(&[
+            0xA9, 0x42,       // LDA #$42
+            0x8D, 0x00, 0x02, // STA $0200
+            0xBD, 0xFF, 0x01, // LDA $01FF,X
+        ]);
+
+        // The reset sequence in progress is finished first
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        assert_eq!(emu.step_cpu_instruction(), 2);
+        assert_eq!(emu.cpu().a, 0x42);
+
+        assert_eq!(emu.step_cpu_instruction(), 4);
+        emu.cpu_mut().x = 0x01;
+        assert_eq!(emu.step_cpu_instruction(), 5);
+        assert_eq!(emu.cpu().a, 0x42);
+        assert_eq!(emu.cpu().pc, 0x8008);
+
+        // Only the CPU ran
+        assert_eq!(emu.total_cycles(), 0);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn step_instruction_and_nmi() {
+        #[rustfmt::skip]
+        let mut rom = rom_with_program(&[
+            0xA9, 0x80,       // LDA #$80
+            0x8D, 0x00, 0x20, // STA $2000
+            0x4C, 0x05, 0x80, // JMP $8005
+        ]);
+        rom[16 + 0x1000] = 0x40; // RTI at $9000
+        rom[16 + 0x3FFA] = 0x00;
+        rom[16 + 0x3FFB] = 0x90;
+
+        let mut emu = Emulator::new(&rom, None).unwrap();
+
+        // The reset sequence is finished first
+        assert_eq!(emu.step_instruction().opcode, None);
+        assert_eq!(emu.cpu().pc, 0x8000);
+
+        let expected = [(0xA9, 0x8002, 2), (0x8D, 0x8005, 4), (0x4C, 0x8005, 3)];
+        for &(opcode, pc, cycles) in expected.iter() {
+            let step = emu.step_instruction();
+            assert_eq!(step.opcode, Some(opcode));
+            assert_eq!(step.pc, pc);
+            assert_eq!(step.cycles, cycles);
+            assert_eq!(emu.cpu().cycles, 0);
+        }
+
+        // Loop until the vblank NMI, which is stepped over as a whole
+        let step = loop {
+            let step = emu.step_instruction();
+            if step.opcode != Some(0x4C) {
+                break step;
+            }
+        };
+        assert_eq!(step.opcode, None);
+        assert_eq!(step.pc, 0x9000);
+        assert_eq!(step.cycles, 8);
+
+        let step = emu.step_instruction();
+        assert_eq!(step.opcode, Some(0x40));
+        assert_eq!(step.pc, 0x8005);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn debug_info_matches_accessors() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        emu.run_frames(3);
+        emu.clock_n(1234);
+        emu.mem_write(0x2005, 125);
+        emu.mem_write(0x2005, 94);
+
+        let info = emu.debug_info();
+        assert_eq!(info.cpu.pc, emu.cpu().pc);
+        assert_eq!(info.cpu.cycles, emu.cpu().cycles);
+        assert_eq!(info.scanline, emu.ppu().scanline());
+        assert_eq!(info.cycle, emu.ppu().cycle());
+        assert_eq!(info.scroll, (125, 94));
+        assert_eq!(info.mask, emu.ppu().mask_reg);
+        assert_eq!(info.nmi_pending, emu.cpu().nmi_pending);
+        assert_eq!(info.irq_pending, emu.cpu().irq_pending);
+        assert_eq!(info.prg_banks, [Some(0); 4]);
+        assert_eq!(info.chr_banks, [0, 1, 2, 3, 4, 5, 6, 7].map(Some));
+        assert_eq!(info.frame_count, emu.frame_count());
+        assert_eq!(info.total_cycles, emu.total_cycles());
+    }
+
+    #[cfg(not(feature = "audio"))]
+    #[test]
+    fn audio_stubs_without_audio() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        emu.set_sample_rate(44100.0);
+        emu.run_frame();
+
+        assert!(emu.take_audio_samples().is_empty());
+        assert!(emu.take_audio_samples_f32().is_empty());
+    }
+
+    #[test]
+    fn socd_policies() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        let press = |emu: &mut Emulator, buttons: Buttons| {
+            emu.queue_input(0, ControllerPort::One, buttons);
+            Buttons::from_bits_truncate(emu.controller1)
+        };
+        let both = Buttons::LEFT | Buttons::RIGHT | Buttons::A;
+
+        // Neutral by default
+        assert_eq!(press(&mut emu, both), Buttons::A);
+
+        emu.set_socd_policy(SocdPolicy::Allow);
+        assert_eq!(press(&mut emu, both), both);
+
+        emu.set_socd_policy(SocdPolicy::LastWins);
+        press(&mut emu, Buttons::empty());
+        assert_eq!(press(&mut emu, both), Buttons::A);
+        press(&mut emu, Buttons::LEFT);
+        assert_eq!(press(&mut emu, both), Buttons::RIGHT | Buttons::A);
+        assert_eq!(press(&mut emu, both), Buttons::RIGHT | Buttons::A);
+        press(&mut emu, Buttons::RIGHT);
+        assert_eq!(press(&mut emu, both), Buttons::LEFT | Buttons::A);
+
+        // Only the opposing axis is affected
+        assert_eq!(
+            press(&mut emu, Buttons::UP | Buttons::LEFT),
+            Buttons::UP | Buttons::LEFT
+        );
+    }
+
+    #[test]
+    fn movie_replays_to_same_frame() {
+        let rom = rom_with_lagging_input();
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.start_recording();
+
+        let mut last_frame = [0u8; 256 * 240];
+        for frame in 0..30 {
+            match frame {
+                10 => emu.set_controller1(Buttons::A.bits()),
+                15 => emu.set_controller1(0),
+                20 => emu.set_controller1(Buttons::A.bits()),
+                _ => {}
+            }
+            last_frame = next_frame(&mut emu);
+        }
+        assert!(last_frame.iter().all(|&c| c == 0x16));
+
+        let movie = emu.stop_recording().unwrap();
+        assert!(!emu.is_recording());
+        assert_eq!(movie.last_frame(), 20);
+
+        let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        let mut replay = Emulator::new(&rom, None).unwrap();
+        replay.play_movie(&movie).unwrap();
+
+        let mut replayed_frames = Vec::new();
+        for _ in 0..30 {
+            replayed_frames.push(next_frame(&mut replay));
+        }
+        assert!(replayed_frames[29] == last_frame);
+        assert!(replayed_frames[12].iter().all(|&c| c == 0x16));
+        assert!(replayed_frames[17].iter().all(|&c| c == 0x0F));
+        assert_eq!(emu.ram, replay.ram);
+
+        // Movies only play on the ROM they were recorded with
+        let mut other = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        assert_eq!(other.play_movie(&movie), Err(MovieError::RomMismatch));
+        assert_eq!(Movie::from_bytes(b"NMV1"), Err(MovieError::InvalidFormat));
+
+        // The input count is checked against the size of the data
+        let mut huge_count = b"NMV1".to_vec();
+        huge_count.extend_from_slice(&movie.rom_crc32().to_le_bytes());
+        huge_count.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            Movie::from_bytes(&huge_count),
+            Err(MovieError::InvalidFormat)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn movie_starts_from_save_state() {
+        let rom = rom_with_lagging_input();
+        let mut emu = Emulator::new(&rom, None).unwrap();
+        emu.set_controller1(Buttons::A.bits());
+        emu.run_frames(12);
+        emu.set_controller1(0);
+
+        emu.start_recording();
+        for frame in 0..10 {
+            if frame == 5 {
+                emu.set_controller1(Buttons::A.bits());
+            }
+            emu.run_frame();
+        }
+        let movie = Movie::from_bytes(&emu.stop_recording().unwrap().to_bytes()).unwrap();
+
+        // The replay starts where the recording did, not from power-on
+        let mut replay = Emulator::new(&rom, None).unwrap();
+        replay.play_movie(&movie).unwrap();
+        assert_eq!(replay.frame_count(), 12);
+        replay.run_frames(10);
+        assert_eq!(replay.total_cycles(), emu.total_cycles());
+        assert_eq!(replay.ram, emu.ram);
+        assert!(replay.current_frame()[..] == emu.current_frame()[..]);
+    }
+
+    #[test]
+    fn performance_stats() {
+        let mut emu = Emulator::new(&rom_with_reset_vector(0x8000), None).unwrap();
+        emu.run_frames(3);
+        assert_eq!(emu.performance_stats().frames, 3);
+        assert_eq!(emu.performance_stats().cycles, emu.total_cycles());
+
+        emu.reset_performance_stats();
+        assert_eq!(
+            emu.performance_stats(),
+            PerformanceStats {
+                cycles: 0,
+                frames: 0
+            }
+        );
+
+        let cycles_before = emu.total_cycles();
+        emu.run_frames(60);
+        let stats = emu.performance_stats();
+        assert_eq!(stats.frames, 60);
+        assert_eq!(stats.cycles, emu.total_cycles() - cycles_before);
+
+        // 60 NTSC frames are emulated in about a second
+        assert!((stats.speed(1.0) - 1.0).abs() < 0.01);
+        assert!((stats.speed(2.0) - 0.5).abs() < 0.01);
+    }
+}
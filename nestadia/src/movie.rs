@@ -0,0 +1,148 @@
+use alloc::vec::Vec;
+use core::convert::TryInto as _;
+
+use crate::controller::{Buttons, ControllerPort};
+
+const MAGIC: &[u8; 4] = b"NMV2";
+// Movies from before the initial save state, which start from power-on
+const MAGIC_V1: &[u8; 4] = b"NMV1";
+const HEADER_SIZE: usize = 16;
+const HEADER_SIZE_V1: usize = 12;
+const INPUT_SIZE: usize = 10;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MovieError {
+    InvalidFormat,
+    /// The movie was recorded with another ROM
+    RomMismatch,
+    /// The save state the movie starts from can't be loaded
+    InvalidState,
+}
+
+impl core::fmt::Display for MovieError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:?}", &self)
+    }
+}
+
+/// Controller inputs, recorded from a save state or from power-on, to be played back deterministically
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    rom_crc32: u32,
+    initial_state: Option<Vec<u8>>, // Save state the inputs start from, or `None` to start from power-on
+    inputs: Vec<(u64, ControllerPort, Buttons)>, // Frame relative to the start of the movie
+}
+
+impl Movie {
+    pub(crate) fn new(rom_crc32: u32) -> Self {
+        Self {
+            rom_crc32,
+            initial_state: None,
+            inputs: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_initial_state(&mut self, state: Vec<u8>) {
+        self.initial_state = Some(state);
+    }
+
+    pub(crate) fn initial_state(&self) -> Option<&[u8]> {
+        self.initial_state.as_deref()
+    }
+
+    pub(crate) fn rom_crc32(&self) -> u32 {
+        self.rom_crc32
+    }
+
+    pub(crate) fn inputs(&self) -> &[(u64, ControllerPort, Buttons)] {
+        &self.inputs
+    }
+
+    pub(crate) fn push(&mut self, frame: u64, port: ControllerPort, buttons: Buttons) {
+        self.inputs.push((frame, port, buttons));
+    }
+
+    /// Drop the inputs from `frame` on, to record them again
+    #[cfg(feature = "serde")]
+    pub(crate) fn truncate(&mut self, frame: u64) {
+        self.inputs
+            .retain(|&(input_frame, _, _)| input_frame < frame);
+    }
+
+    /// Frame of the last input, relative to the start of the movie
+    pub fn last_frame(&self) -> u64 {
+        self.inputs.last().map(|&(frame, _, _)| frame).unwrap_or(0)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let initial_state = self.initial_state.as_deref().unwrap_or_default();
+        let mut bytes =
+            Vec::with_capacity(HEADER_SIZE + initial_state.len() + self.inputs.len() * INPUT_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.rom_crc32.to_le_bytes());
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_le_bytes());
+        // An empty state means the movie starts from power-on
+        bytes.extend_from_slice(&(initial_state.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(initial_state);
+
+        for &(frame, port, buttons) in &self.inputs {
+            bytes.extend_from_slice(&frame.to_le_bytes());
+            bytes.push(port as u8);
+            bytes.push(buttons.bits());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        let header_size = match bytes.get(0..4) {
+            Some(magic) if magic == MAGIC => HEADER_SIZE,
+            Some(magic) if magic == MAGIC_V1 => HEADER_SIZE_V1,
+            _ => return Err(MovieError::InvalidFormat),
+        };
+        if bytes.len() < header_size {
+            return Err(MovieError::InvalidFormat);
+        }
+
+        let rom_crc32 = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        let count = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+
+        let mut data = &bytes[header_size..];
+        let mut initial_state = None;
+        if header_size == HEADER_SIZE {
+            let state_len = u32::from_le_bytes(bytes[12..16].try_into().unwrap()) as usize;
+            if data.len() < state_len {
+                return Err(MovieError::InvalidFormat);
+            }
+            let (state, inputs) = data.split_at(state_len);
+            if !state.is_empty() {
+                initial_state = Some(state.to_vec());
+            }
+            data = inputs;
+        }
+
+        // The count can't be trusted, and would overflow on 32-bit targets
+        if count.checked_mul(INPUT_SIZE) != Some(data.len()) {
+            return Err(MovieError::InvalidFormat);
+        }
+
+        let inputs = data
+            .chunks_exact(INPUT_SIZE)
+            .map(|input| {
+                let frame = u64::from_le_bytes(input[0..8].try_into().unwrap());
+                let port = match input[8] {
+                    0 => ControllerPort::One,
+                    1 => ControllerPort::Two,
+                    _ => return Err(MovieError::InvalidFormat),
+                };
+                Ok((frame, port, Buttons::from_bits_truncate(input[9])))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            rom_crc32,
+            initial_state,
+            inputs,
+        })
+    }
+}
@@ -1,19 +1,46 @@
+use alloc::boxed::Box;
+
 use crate::bus::PpuBus;
 
 /// Registers definitions
 pub mod registers;
 pub mod sprites;
-use sprites::{SpriteEvalutationState, SpriteXCounter, SpriteZeroHitState};
+use sprites::{SpriteEvalutationState, SpriteOverflowMode, SpriteXCounter, SpriteZeroHitState};
 
 pub const FRAME_WIDTH: usize = 256;
 pub const FRAME_HEIGHT: usize = 240;
 
 pub type PpuFrame = [u8; FRAME_WIDTH * FRAME_HEIGHT];
 
+/// Settings of the frontend, which are kept across resets and loaded states
+#[derive(Clone, Copy)]
+struct PpuSettings {
+    sprite_overflow_mode: SpriteOverflowMode,
+    odd_frame_skip: bool,
+    initial_odd_frame: bool,
+    #[cfg(feature = "debugger")]
+    sprite_zero_hit_override: Option<bool>,
+}
+
+impl Default for PpuSettings {
+    fn default() -> Self {
+        Self {
+            sprite_overflow_mode: Default::default(),
+            odd_frame_skip: true,
+            initial_odd_frame: false,
+            #[cfg(feature = "debugger")]
+            sprite_zero_hit_override: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ppu {
     // Internal memory
-    palette_table: [u8; 32],    // For color stuff
-    oam_data: [u8; 64 * 4],     // Object Attribute Memory, internal to PPU
+    palette_table: [u8; 32], // For color stuff
+    #[cfg_attr(feature = "serde", serde(with = "crate::state::byte_array"))]
+    oam_data: [u8; 64 * 4], // Object Attribute Memory, internal to PPU
     secondary_oam: [u8; 8 * 4], // Object Attribute Memory of sprites to render on the scanline.
 
     // Rendering pipeline memory
@@ -42,11 +69,20 @@ pub struct Ppu {
     // Emulation-specific internal stuff
     cycle_count: u16,
     scanline: i16,
-    frame: PpuFrame,
+    #[cfg_attr(feature = "serde", serde(with = "crate::state::boxed_byte_array"))]
+    frame: Box<PpuFrame>, // Boxed so it can be swapped with a buffer of the frontend
+    // Color the rest of the frame was filled with while rendering is disabled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    backdrop_fill: Option<u8>,
     vblank_nmi_set: bool,
+    // A $2002 read raced the start of the vblank: https://wiki.nesdev.com/w/index.php/PPU_frame_timing#VBL_Flag_Timing
+    vblank_suppressed: bool,
+    vblank_nmi_suppressed: bool,
     last_data_on_bus: u8,
     sprite_zero_hit_state: SpriteZeroHitState,
     is_odd_frame: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    settings: PpuSettings,
 
     // Buffers for cycle-accurate reads
     nt_buffer: u8,
@@ -91,11 +127,15 @@ impl Ppu {
 
             cycle_count: 0,
             scanline: -1,
-            frame: [0u8; 256 * 240],
+            frame: Box::new([0u8; 256 * 240]),
+            backdrop_fill: None,
             vblank_nmi_set: false,
+            vblank_suppressed: false,
+            vblank_nmi_suppressed: false,
             last_data_on_bus: 0,
             sprite_zero_hit_state: Default::default(),
             is_odd_frame: false,
+            settings: Default::default(),
 
             nt_buffer: 0,
             at_buffer: 0,
@@ -104,8 +144,47 @@ impl Ppu {
         }
     }
 
+    /// Reset the PPU, keeping the settings of the frontend
     pub fn reset(&mut self) {
-        *self = Default::default()
+        let settings = self.settings;
+        *self = Default::default();
+        self.settings = settings;
+        self.is_odd_frame = settings.initial_odd_frame;
+    }
+
+    /// Set the control register directly, bypassing the side effects of a `$2000` write.
+    #[cfg(test)]
+    pub(crate) fn set_ctrl(&mut self, ctrl: registers::ControlReg) {
+        self.ctrl_reg = ctrl;
+    }
+
+    /// Set the mask register directly, bypassing the side effects of a `$2001` write.
+    #[cfg(test)]
+    pub(crate) fn set_mask(&mut self, mask: registers::MaskReg) {
+        self.mask_reg = mask;
+    }
+
+    /// Force the sprite 0 hit flag reported to the game, or `None` to use the emulated value.
+    /// The rendering itself is not affected.
+    #[cfg(feature = "debugger")]
+    pub fn force_sprite_zero_hit(&mut self, state: Option<bool>) {
+        self.settings.sprite_zero_hit_override = state;
+    }
+
+    pub fn set_sprite_overflow_mode(&mut self, mode: SpriteOverflowMode) {
+        self.settings.sprite_overflow_mode = mode;
+    }
+
+    /// Whether the pre-render scanline of odd frames is one cycle shorter while rendering, like on hardware
+    pub fn set_odd_frame_skip(&mut self, enabled: bool) {
+        self.settings.odd_frame_skip = enabled;
+    }
+
+    /// Parity of the first frame after a reset, which is random on hardware.
+    /// It also applies to the current frame, so the first frame after `new` has it too.
+    pub fn set_initial_odd_frame(&mut self, odd: bool) {
+        self.settings.initial_odd_frame = odd;
+        self.is_odd_frame = odd;
     }
 
     pub fn take_vblank_nmi_set_state(&mut self) -> bool {
@@ -114,6 +193,14 @@ impl Ppu {
         state
     }
 
+    /// Whether a `$2002` read right as the vblank started cancelled its NMI.
+    /// The NMI may already have been latched by the CPU, which must then drop it.
+    pub fn take_vblank_nmi_suppressed_state(&mut self) -> bool {
+        let state = self.vblank_nmi_suppressed;
+        self.vblank_nmi_suppressed = false;
+        state
+    }
+
     pub fn write(&mut self, bus: &mut PpuBus<'_>, addr: u16, data: u8) {
         let addr = addr & 0x07; // mirror
 
@@ -175,6 +262,9 @@ impl Ppu {
                 if self.write_latch {
                     let value = (self.temp_vram_addr.get() & 0xff00) | (data as u16);
                     self.temp_vram_addr.set(value);
+
+                    // This is also done during rendering, which corrupts the scroll since the
+                    // background fetches use v directly. Some games rely on this for split-screen effects.
                     self.vram_addr = self.temp_vram_addr;
                 } else {
                     // For some reasons, bit 15 is cleared here
@@ -203,14 +293,7 @@ impl Ppu {
                     0x3000..=0x3EFF => log::warn!("address space 0x3000..0x3EFF is not expected to be used, but it was attempted to write at 0x{:#X}", write_addr),
 
                     // Palette table:
-                    0x3F00..=0x3FFF => {
-                        if write_addr & 0b11 == 0 {
-                            // Mirror to the universal background color
-                            self.palette_table[usize::from(write_addr & 0x0f)] = data;
-                        } else {
-                            self.palette_table[usize::from(write_addr & 0x1f)] = data;
-                        }
-                    },
+                    0x3F00..=0x3FFF => self.palette_table[palette_table_index(write_addr)] = data,
 
                     _ => unreachable!("unexpected write to mirrored space {:#X}", write_addr),
                 }
@@ -228,6 +311,99 @@ impl Ppu {
         }
     }
 
+    /// Whether the PPU is in the vertical blanking period. Unlike the status flag, this isn't cleared by reading $2002.
+    pub fn is_vblank(&self) -> bool {
+        (self.scanline == 241 && self.cycle_count >= 1) || self.scanline > 241
+    }
+
+    /// Whether the PPU is drawing the visible scanlines, with rendering enabled
+    pub fn is_rendering(&self) -> bool {
+        self.rendering_enabled() && (0..240).contains(&self.scanline)
+    }
+
+    /// Current scanline, from -1 (pre-render) to 260
+    pub fn scanline(&self) -> i16 {
+        self.scanline
+    }
+
+    /// Current cycle in the scanline, from 0 to 340
+    pub fn cycle(&self) -> u16 {
+        self.cycle_count
+    }
+
+    /// Nametable byte of the tile being fetched
+    #[cfg(feature = "debugger")]
+    pub fn nt_buffer(&self) -> u8 {
+        self.nt_buffer
+    }
+
+    /// Attribute byte of the tile being fetched
+    #[cfg(feature = "debugger")]
+    pub fn at_buffer(&self) -> u8 {
+        self.at_buffer
+    }
+
+    /// Low pattern byte of the tile being fetched
+    #[cfg(feature = "debugger")]
+    pub fn bg_lo_buffer(&self) -> u8 {
+        self.bg_lo_buffer
+    }
+
+    /// High pattern byte of the tile being fetched
+    #[cfg(feature = "debugger")]
+    pub fn bg_hi_buffer(&self) -> u8 {
+        self.bg_hi_buffer
+    }
+
+    /// Background pattern shift registers, low and high planes
+    #[cfg(feature = "debugger")]
+    pub fn pattern_shifters(&self) -> [u16; 2] {
+        self.pattern_pipeline
+    }
+
+    /// Background palette attribute shift registers, low and high bits
+    #[cfg(feature = "debugger")]
+    pub fn palette_shifters(&self) -> [u16; 2] {
+        self.palette_pipeline
+    }
+
+    /// Current VRAM address, the loopy `v` register
+    #[cfg(feature = "debugger")]
+    pub fn vram_address(&self) -> u16 {
+        self.vram_addr.get()
+    }
+
+    /// Temporary VRAM address, the loopy `t` register
+    #[cfg(feature = "debugger")]
+    pub fn temp_vram_address(&self) -> u16 {
+        self.temp_vram_addr.get()
+    }
+
+    /// Fine X scroll, the loopy `x` register
+    #[cfg(feature = "debugger")]
+    pub fn fine_x(&self) -> u8 {
+        self.fine_x
+    }
+
+    /// Scroll position written through `$2000` and `$2005`, in pixels across the four nametables
+    #[cfg(feature = "debugger")]
+    pub fn scroll(&self) -> (u16, u16) {
+        let t = &self.temp_vram_addr;
+        let x = (t.nametable() & 0b01) * 256 + t.coarse_x() * 8 + u16::from(self.fine_x);
+        let y = (t.nametable() >> 1) * 240 + t.coarse_y() * 8 + t.fine_y();
+        (x, y)
+    }
+
+    /// Raw content of the OAM
+    pub fn oam_bytes(&self) -> &[u8; 256] {
+        &self.oam_data
+    }
+
+    /// Overwrite the whole OAM, without touching OAMADDR
+    pub fn set_oam_bytes(&mut self, oam: &[u8; 256]) {
+        self.oam_data = *oam;
+    }
+
     pub fn read(&mut self, bus: &mut PpuBus<'_>, addr: u16) -> u8 {
         let addr = addr & 0x07; // mirror
 
@@ -250,9 +426,30 @@ impl Ppu {
                 // 3 top bits are the PPU status, least significant bits are noise from PPU bus.
                 let snapshot = self.status_reg.read() | self.last_data_on_bus & 0x1F;
 
+                #[cfg(feature = "debugger")]
+                let snapshot = match self.settings.sprite_zero_hit_override {
+                    Some(true) => snapshot | registers::StatusReg::SPRITE_ZERO_HIT.bits(),
+                    Some(false) => snapshot & !registers::StatusReg::SPRITE_ZERO_HIT.bits(),
+                    None => snapshot,
+                };
+
                 // Reading the Status register clear bit 7 and also the address latch used by PPUSCROLL and PPUADDR.
                 self.status_reg.remove(registers::StatusReg::VBLANK_STARTED);
 
+                // Reading one dot before the vblank starts reads the flag as clear and keeps it from being set,
+                // so there's no NMI for the whole frame. Reading on the same dot or the next one reads the flag
+                // as set, but still cancels the NMI.
+                if self.scanline == 241 {
+                    match self.cycle_count {
+                        0 => self.vblank_suppressed = true,
+                        1..=2 => {
+                            self.vblank_nmi_set = false;
+                            self.vblank_nmi_suppressed = true;
+                        }
+                        _ => {}
+                    }
+                }
+
                 self.write_latch = false;
 
                 snapshot
@@ -292,12 +489,10 @@ impl Ppu {
 
                     // Palette table:
                     0x3F00..=0x3FFF => {
-                        let color = if read_addr & 0b11 == 0 {
-                            // Mirror to the universal background color
-                            self.palette_table[usize::from(read_addr & 0x0f)]
-                        } else {
-                            self.palette_table[usize::from(read_addr & 0x1f)]
-                        };
+                        let color = self.palette_table[palette_table_index(read_addr)];
+
+                        // Palette reads are not buffered, but the buffer is still filled with the nametable byte "underneath"
+                        self.last_data_on_bus = bus.read_name_tables(read_addr - 0x1000);
 
                         // Apply greyscale to reads
                         if self.mask_reg.contains(registers::MaskReg::GREYSCALE) {
@@ -326,6 +521,24 @@ impl Ppu {
         }
     }
 
+    /// The frame being rendered, or the last one when it's ready
+    pub fn frame(&self) -> &PpuFrame {
+        &self.frame
+    }
+
+    /// Exchange the frame being rendered with `frame`, without copying it
+    pub fn swap_frame(&mut self, frame: &mut Box<PpuFrame>) {
+        core::mem::swap(&mut self.frame, frame);
+        self.backdrop_fill = None;
+    }
+
+    /// Replace the state with a snapshot, keeping the settings of the frontend
+    #[cfg(feature = "serde")]
+    pub(crate) fn load_state(&mut self, mut state: Ppu) {
+        state.settings = self.settings;
+        *self = state;
+    }
+
     /// Returns frame when it's ready
     pub fn clock(&mut self, bus: &mut PpuBus) {
         self.cycle_count += 1;
@@ -334,16 +547,15 @@ impl Ppu {
             self.cycle_count = 0;
             self.scanline += 1;
 
-            if self.scanline >= 261 {
+            if self.scanline == 0 {
+                // The last cycle of the pre-render scanline is skipped on odd frames, only if rendering is enabled
+                if self.settings.odd_frame_skip && self.is_odd_frame && self.rendering_enabled() {
+                    self.cycle_count += 1
+                };
+            } else if self.scanline >= 261 {
                 // http://wiki.nesdev.com/w/index.php/PPU_rendering#Pre-render_scanline_.28-1_or_261.29
                 // scanline = -1 is the dummy scanline
                 self.scanline = -1;
-
-                // Skips a cycle on odd frame, only if rendering is enabled
-                if self.is_odd_frame && self.rendering_enabled() {
-                    self.cycle_count += 1
-                };
-
                 self.is_odd_frame = !self.is_odd_frame;
             }
 
@@ -376,30 +588,38 @@ impl Ppu {
         };
 
         // Pre-render scanline
-        if self.scanline == -1 {
-            if self.cycle_count == 1 {
-                // Reset sprite 0 hit
-                self.status_reg
-                    .remove(registers::StatusReg::SPRITE_ZERO_HIT);
+        if self.scanline == -1 && self.cycle_count == 1 {
+            // Reset sprite 0 hit
+            self.status_reg
+                .remove(registers::StatusReg::SPRITE_ZERO_HIT);
 
-                // Reset sprite overflow flag
-                self.status_reg
-                    .remove(registers::StatusReg::SPRITE_OVERFLOW);
+            // Reset sprite overflow flag
+            self.status_reg
+                .remove(registers::StatusReg::SPRITE_OVERFLOW);
 
-                // VBLANK is done
-                self.status_reg.remove(registers::StatusReg::VBLANK_STARTED);
-            } else if self.cycle_count >= 280 && self.cycle_count <= 304 && self.rendering_enabled()
-            {
-                self.vram_addr.reset_y(&self.temp_vram_addr);
-            }
-        };
+            // VBLANK is done
+            self.status_reg.remove(registers::StatusReg::VBLANK_STARTED);
 
-        self.render_pixel();
+            self.backdrop_fill = None;
+        };
 
         // This condition is there to ensure that the VRAM address does not get updated if rendering is turned off
         if self.rendering_enabled() {
+            self.backdrop_fill = None;
+
+            if self.scanline == -1 && self.cycle_count >= 280 && self.cycle_count <= 304 {
+                self.vram_addr.reset_y(&self.temp_vram_addr);
+            }
+
+            self.render_pixel();
+
             // Visible + pre-render scanline
             if self.scanline < 240 {
+                // OAMADDR is reset while the sprite tiles are loaded
+                if self.cycle_count >= 257 && self.cycle_count <= 320 {
+                    self.oam_addr_reg = 0;
+                }
+
                 if self.scanline == -1 {
                     // Sprites are not loaded during the pre-render scanline
                     self.sprites_x_counter = Default::default();
@@ -422,13 +642,30 @@ impl Ppu {
                     self.vram_addr.reset_x(&self.temp_vram_addr);
                 };
             }
+        } else if self.scanline >= 0
+            && self.scanline <= 239
+            && self.cycle_count >= 1
+            && self.cycle_count <= 256
+        {
+            // Fast path: nothing is fetched or evaluated while rendering is disabled, so only the backdrop color is output.
+            // The rest of the frame is filled at once, and filled again only if the backdrop changes mid-frame.
+            let color = self.output_color(self.backdrop_color());
+            if self.backdrop_fill != Some(color) {
+                let start = self.scanline as usize * FRAME_WIDTH + (self.cycle_count - 1) as usize;
+                self.frame[start..].fill(color);
+                self.backdrop_fill = Some(color);
+            }
         }
 
         if self.scanline == 241 && self.cycle_count == 1 {
             // This is the exact cycle the VBLANK starts
-            self.status_reg.insert(registers::StatusReg::VBLANK_STARTED);
-            if self.ctrl_reg.contains(registers::ControlReg::GENERATE_NMI) {
-                self.vblank_nmi_set = true;
+            if self.vblank_suppressed {
+                self.vblank_suppressed = false;
+            } else {
+                self.status_reg.insert(registers::StatusReg::VBLANK_STARTED);
+                if self.ctrl_reg.contains(registers::ControlReg::GENERATE_NMI) {
+                    self.vblank_nmi_set = true;
+                }
             }
         };
     }
@@ -457,7 +694,7 @@ impl Ppu {
                 self.get_background_pixel()
             } else {
                 // Transparent with default background color
-                (true, self.palette_table[0])
+                (true, self.backdrop_color())
             };
 
         let sprite_pixel = if self.mask_reg.contains(registers::MaskReg::SHOW_SPRITES) {
@@ -501,13 +738,29 @@ impl Ppu {
         }
     }
 
-    fn set_pixel(&mut self, x: u16, y: u16, color: u8) {
-        let color = if self.mask_reg.contains(registers::MaskReg::GREYSCALE) {
+    /// Color used when no opaque pixel is drawn.
+    /// During forced blanking, if the VRAM address points to the palette, the PPU outputs the color at
+    /// that address instead of the universal background color. Some games use this to draw full-screen colors.
+    fn backdrop_color(&self) -> u8 {
+        let addr = self.vram_addr.get() & 0x3fff;
+
+        if !self.rendering_enabled() && addr >= 0x3F00 {
+            self.palette_table[palette_table_index(addr)]
+        } else {
+            self.palette_table[0]
+        }
+    }
+
+    fn output_color(&self, color: u8) -> u8 {
+        if self.mask_reg.contains(registers::MaskReg::GREYSCALE) {
             color & 0x30
         } else {
             color
-        };
+        }
+    }
 
+    fn set_pixel(&mut self, x: u16, y: u16, color: u8) {
+        let color = self.output_color(color);
         let idx = y as usize * FRAME_WIDTH + x as usize;
         if idx < self.frame.len() {
             self.frame[idx] = color;
@@ -670,7 +923,9 @@ impl Ppu {
             0 => {
                 // Initialization
                 self.sprite_evaluation_state = SpriteEvalutationState::CheckY;
-                self.oam_pointer = 0;
+
+                // Evaluation starts at OAMADDR, which is usually 0 since it's reset during the previous scanline
+                self.oam_pointer = self.oam_addr_reg >> 2;
                 self.secondary_oam_pointer = 0;
             }
             1..=64 => {
@@ -698,8 +953,8 @@ impl Ppu {
                                 // Sprite is in scanline
                                 self.sprite_evaluation_state = SpriteEvalutationState::CopyOam(1);
 
-                                if self.oam_pointer == 0 {
-                                    // This is sprite 0
+                                if self.cycle_count == 66 {
+                                    // This is sprite 0, which is the first one evaluated
 
                                     match self.sprite_zero_hit_state {
                                         SpriteZeroHitState::Idle => {
@@ -755,8 +1010,7 @@ impl Ppu {
                         };
                     }
                     SpriteEvalutationState::EvaluateOverflow(m) => {
-                        // Buggy implementation to check if there is a sprite overflow
-                        // This does NOT need to be perfect as barely any official games ever used it.
+                        // Check if there is a sprite overflow, with the hardware bug unless the exact mode is selected
                         if self.cycle_count & 1 == 1 {
                             // On odd cycle, read value
                             self.oam_latch = self.oam_data[((self.oam_pointer << 2) | m) as usize];
@@ -773,8 +1027,13 @@ impl Ppu {
                                 //  emulate the remaining reads(maybe for bus conflict emulation?), so we just Idle
                                 self.sprite_evaluation_state = SpriteEvalutationState::Idle;
                             } else {
-                                // Sprite does no hit, so n and m are (wrongly) incremented
+                                // Sprite does no hit, so n and m are (wrongly) incremented.
+                                // m wraps around without carrying into n.
                                 self.oam_pointer += 1;
+                                let m = match self.settings.sprite_overflow_mode {
+                                    SpriteOverflowMode::Hardware => (m + 1) & 0x03,
+                                    SpriteOverflowMode::Exact => 0,
+                                };
 
                                 if self.oam_pointer == 64 {
                                     // All sprites have been evaluated, idle
@@ -782,7 +1041,7 @@ impl Ppu {
                                 } else {
                                     // There are still sprite to evaluate
                                     self.sprite_evaluation_state =
-                                        SpriteEvalutationState::EvaluateOverflow(m + 1);
+                                        SpriteEvalutationState::EvaluateOverflow(m);
                                 }
                             }
                         }
@@ -961,11 +1220,23 @@ impl Ppu {
     }
 }
 
+/// Maps an address in the $3F00-$3FFF range to an index in the palette table
+fn palette_table_index(addr: u16) -> usize {
+    if addr & 0b11 == 0 {
+        // Mirror to the universal background color
+        usize::from(addr & 0x0f)
+    } else {
+        usize::from(addr & 0x1f)
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
     use crate::cartridge::Mirroring;
     use crate::Cartridge;
+    use alloc::vec;
+    use alloc::vec::Vec;
 
     const ROM_HORIZONTAL: &'static [u8] =
         include_bytes!("../../../default_roms/1.Branch_Basics.nes");
@@ -993,6 +1264,79 @@ pub mod test {
         mock_emu(ROM_VERTICAL)
     }
 
+    /// NROM cartridge with 16KB of empty PRG and CHR RAM, for tests that don't need real ROM data
+    fn dummy_rom() -> Vec<u8> {
+        let mut rom = vec![0u8; 16 + 0x4000];
+        rom[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+        rom[4] = 0x01;
+        rom
+    }
+
+    fn run_frame(emu: &mut MockEmulator) -> PpuFrame {
+        loop {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+            if let Some(frame) = emu.ppu.ready_frame() {
+                break *frame;
+            }
+        }
+    }
+
+    #[test]
+    fn odd_frame_skip() {
+        let frame_lengths = |odd_frame_skip: bool| {
+            let mut emu = mock_emu(&dummy_rom());
+            emu.ppu.set_odd_frame_skip(odd_frame_skip);
+            emu.ppu.set_mask(registers::MaskReg::SHOW_BACKGROUND);
+            run_frame(&mut emu);
+
+            let mut lengths = [0u32; 4];
+            for length in lengths.iter_mut() {
+                loop {
+                    let mut bus = borrow_ppu_bus!(emu);
+                    emu.ppu.clock(&mut bus);
+                    *length += 1;
+                    if emu.ppu.ready_frame().is_some() {
+                        break;
+                    }
+                }
+            }
+            lengths
+        };
+
+        // Every other frame is one cycle shorter
+        let lengths = frame_lengths(true);
+        assert_eq!(lengths[0] + lengths[1], 2 * 341 * 262 - 1);
+        assert_eq!(lengths[0..2], lengths[2..4]);
+        assert_ne!(lengths[0], lengths[1]);
+
+        assert_eq!(frame_lengths(false), [341 * 262; 4]);
+    }
+
+    #[test]
+    fn initial_frame_parity() {
+        // The power-on state starts on the pre-render scanline, so the first frame skips a cycle when it's odd
+        let first_frame_length = |odd: bool| {
+            let mut emu = mock_emu(&dummy_rom());
+            emu.ppu.set_initial_odd_frame(odd);
+            emu.ppu.set_mask(registers::MaskReg::SHOW_BACKGROUND);
+
+            let mut length = 0u32;
+            loop {
+                let mut bus = borrow_ppu_bus!(emu);
+                emu.ppu.clock(&mut bus);
+                length += 1;
+                if emu.ppu.ready_frame().is_some() {
+                    break length;
+                }
+            }
+        };
+
+        // Until the first frame is ready, at cycle 256 of scanline 239
+        assert_eq!(first_frame_length(false), 340 + 239 * 341 + 257);
+        assert_eq!(first_frame_length(true), 340 + 239 * 341 + 257 - 1);
+    }
+
     #[test]
     fn name_tables_writes() {
         let mut emu = mock_emu_horizontal();
@@ -1057,6 +1401,56 @@ pub mod test {
         assert_eq!(emu.ppu.read(&mut bus, 0x2007), 0x88);
     }
 
+    #[test]
+    fn name_tables_writes_step_32() {
+        let mut emu = mock_emu(&dummy_rom());
+        let mut bus = borrow_ppu_bus!(emu);
+
+        emu.ppu.write(&mut bus, 0x2000, 0b100);
+
+        emu.ppu.write(&mut bus, 0x2006, 0x21);
+        emu.ppu.write(&mut bus, 0x2006, 0x05);
+        emu.ppu.write(&mut bus, 0x2007, 0x66);
+        emu.ppu.write(&mut bus, 0x2007, 0x77);
+        emu.ppu.write(&mut bus, 0x2007, 0x88);
+        assert_eq!(emu.ppu.vram_addr.get(), 0x2165);
+
+        assert_eq!(emu.name_tables[0x0105], 0x66);
+        assert_eq!(emu.name_tables[0x0125], 0x77);
+        assert_eq!(emu.name_tables[0x0145], 0x88);
+        assert_eq!(emu.name_tables[0x0106], 0x00);
+    }
+
+    #[test]
+    fn vram_addr_increment_switch() {
+        let mut emu = mock_emu(&dummy_rom());
+        emu.name_tables[0x0021] = 0x33;
+        let mut bus = borrow_ppu_bus!(emu);
+
+        emu.ppu.write(&mut bus, 0x2000, 0b0);
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+        emu.ppu.write(&mut bus, 0x2007, 0x11);
+        assert_eq!(emu.ppu.vram_addr.get(), 0x2001);
+
+        // The new step applies to the very next access
+        emu.ppu.write(&mut bus, 0x2000, 0b100);
+        emu.ppu.write(&mut bus, 0x2007, 0x22);
+        assert_eq!(emu.ppu.vram_addr.get(), 0x2021);
+
+        // Same for reads, with the buffered value coming from the previous address
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x01);
+        emu.ppu.read(&mut bus, 0x2007);
+        assert_eq!(emu.ppu.vram_addr.get(), 0x2021);
+
+        emu.ppu.write(&mut bus, 0x2000, 0b0);
+        assert_eq!(emu.ppu.read(&mut bus, 0x2007), 0x22);
+        assert_eq!(emu.ppu.vram_addr.get(), 0x2022);
+        assert_eq!(emu.ppu.read(&mut bus, 0x2007), 0x33);
+        assert_eq!(emu.ppu.vram_addr.get(), 0x2023);
+    }
+
     // Horizontal
     // [0x2000 A ] [0x2400 a ]
     // [0x2800 B ] [0x2C00 b ]
@@ -1136,6 +1530,37 @@ pub mod test {
         assert_eq!(emu.ppu.read(&mut bus, 0x2007), 0x66);
     }
 
+    #[test]
+    fn axrom_single_screen() {
+        // AxROM with 2 32KB PRG banks, each starting with its number
+        let mut rom = vec![0u8; 16 + 0x4000 * 4];
+        rom[0..4].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A]);
+        rom[4] = 0x04;
+        rom[6] = 0x70;
+        rom[16 + 0x8000] = 1;
+
+        let mut emu = mock_emu(&rom);
+        assert_eq!(emu.cartridge.read_prg_mem(0x8000), 0);
+
+        let write_name_table = |emu: &mut MockEmulator, addr: u16, data: u8| {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.write(&mut bus, 0x2006, (addr >> 8) as u8);
+            emu.ppu.write(&mut bus, 0x2006, addr as u8);
+            emu.ppu.write(&mut bus, 0x2007, data);
+        };
+
+        // Every nametable goes to the first screen
+        write_name_table(&mut emu, 0x2C05, 0x11);
+        assert_eq!(emu.name_tables[0x0005], 0x11);
+
+        // Switch to the second bank and the second screen
+        emu.cartridge.write_prg_mem(0x8000, 0x11);
+        assert_eq!(emu.cartridge.read_prg_mem(0x8000), 1);
+        write_name_table(&mut emu, 0x2005, 0x22);
+        assert_eq!(emu.name_tables[0x0405], 0x22);
+        assert_eq!(emu.name_tables[0x0005], 0x11);
+    }
+
     #[test]
     fn read_status_resets_latch() {
         let mut emu = mock_emu_vertical();
@@ -1202,4 +1627,500 @@ pub mod test {
         emu.ppu.write(&mut bus, 0x2003, 0x0F); // "wrap around"
         assert_eq!(emu.ppu.read(&mut bus, 0x2004), 0x88);
     }
+
+    #[test]
+    fn forced_blanking_renders_palette_at_vram_addr() {
+        let mut emu = mock_emu(&dummy_rom());
+        let mut bus = borrow_ppu_bus!(emu);
+
+        // Backdrop color
+        emu.ppu.write(&mut bus, 0x2006, 0x3F);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+        emu.ppu.write(&mut bus, 0x2007, 0x0F);
+
+        // Color that should fill the screen
+        emu.ppu.write(&mut bus, 0x2006, 0x3F);
+        emu.ppu.write(&mut bus, 0x2006, 0x05);
+        emu.ppu.write(&mut bus, 0x2007, 0x16);
+
+        // Point the VRAM address back to it, with rendering disabled
+        emu.ppu.write(&mut bus, 0x2001, 0x00);
+        emu.ppu.write(&mut bus, 0x2006, 0x3F);
+        emu.ppu.write(&mut bus, 0x2006, 0x05);
+
+        let frame = run_frame(&mut emu);
+        assert!(frame.iter().all(|&c| c == 0x16));
+
+        // Outside of the palette, the backdrop color is used
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+
+        let frame = run_frame(&mut emu);
+        assert!(frame.iter().all(|&c| c == 0x0F));
+    }
+
+    /// Fill the palette with a single color and move the VRAM address out of the palette
+    fn set_backdrop_color(emu: &mut MockEmulator, color: u8) {
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2006, 0x3F);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+        emu.ppu.write(&mut bus, 0x2007, color);
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+    }
+
+    #[test]
+    fn vram_addr_increment_from_ctrl() {
+        let mut emu = mock_emu(&dummy_rom());
+        emu.ppu.set_ctrl(registers::ControlReg::VRAM_ADDR_INCREMENT);
+
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+        emu.ppu.write(&mut bus, 0x2007, 0x11);
+        emu.ppu.write(&mut bus, 0x2007, 0x22);
+
+        assert_eq!(emu.name_tables[0x00], 0x11);
+        assert_eq!(emu.name_tables[0x20], 0x22);
+    }
+
+    #[test]
+    fn greyscale_frame() {
+        let mut emu = mock_emu(&dummy_rom());
+        set_backdrop_color(&mut emu, 0x16);
+
+        let frame = run_frame(&mut emu);
+        assert!(frame.iter().all(|&c| c == 0x16));
+
+        emu.ppu.set_mask(registers::MaskReg::GREYSCALE);
+
+        let frame = run_frame(&mut emu);
+        assert!(frame.iter().all(|&c| c == 0x10));
+    }
+
+    #[test]
+    fn emphasis_frame() {
+        let mut emu = mock_emu(&dummy_rom());
+        set_backdrop_color(&mut emu, 0x16);
+        emu.ppu.set_mask(registers::MaskReg::EMPHASISE_RED);
+
+        let frame = run_frame(&mut emu);
+        let mut output = [0u8; 256 * 240 * 3];
+        crate::frame_to_rgb(emu.ppu.mask_reg, &frame, &mut output);
+
+        let color = crate::RGB_PALETTE[0x16];
+        let expected = [
+            color[0],
+            crate::deemphasize_color(color[1]),
+            crate::deemphasize_color(color[2]),
+        ];
+        assert!(output.chunks(3).all(|c| c == expected));
+    }
+
+    fn clock_until(emu: &mut MockEmulator, scanline: i16, cycle: u16) {
+        while emu.ppu.scanline != scanline || emu.ppu.cycle_count != cycle {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+    }
+
+    #[test]
+    fn ppuctrl_nametable_copied_to_vram_addr() {
+        let mut emu = mock_emu(&dummy_rom());
+        emu.ppu.set_mask(registers::MaskReg::SHOW_BACKGROUND);
+        run_frame(&mut emu);
+
+        clock_until(&mut emu, 100, 200);
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2000, 0x01);
+        emu.ppu.write(&mut bus, 0x2005, 0x10);
+        emu.ppu.write(&mut bus, 0x2005, 0x00);
+
+        // The scroll writes don't touch the nametable bits
+        assert_eq!(emu.ppu.temp_vram_addr.nametable(), 0b01);
+        assert_eq!(emu.ppu.temp_vram_addr.coarse_x(), 2);
+
+        // Horizontal bits are copied at dot 257
+        clock_until(&mut emu, 100, 256);
+        let vertical = emu.ppu.vram_addr.nametable() & 0b10;
+        clock_until(&mut emu, 100, 257);
+        assert_eq!(emu.ppu.vram_addr.nametable(), vertical | 0b01);
+        assert_eq!(emu.ppu.vram_addr.coarse_x(), 2);
+
+        // Vertical bits are copied during dots 280-304 of the pre-render scanline.
+        // Before that, the vertical nametable was flipped when coarse Y wrapped at the bottom of the screen.
+        clock_until(&mut emu, -1, 279);
+        assert_eq!(emu.ppu.vram_addr.nametable(), 0b11);
+        clock_until(&mut emu, -1, 280);
+        assert_eq!(emu.ppu.vram_addr.nametable(), 0b01);
+        clock_until(&mut emu, -1, 305);
+        assert_eq!(emu.ppu.vram_addr.nametable(), 0b01);
+
+        // A $2006 write overrides the nametable bits set through $2000
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2006, 0x28);
+        assert_eq!(emu.ppu.temp_vram_addr.nametable(), 0b10);
+        emu.ppu.write(&mut bus, 0x2000, 0x03);
+        assert_eq!(emu.ppu.temp_vram_addr.nametable(), 0b11);
+    }
+
+    #[test]
+    fn ppuaddr_write_during_rendering() {
+        let mut emu = mock_emu(&dummy_rom());
+        let mut bus = borrow_ppu_bus!(emu);
+
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x42);
+        emu.ppu.write(&mut bus, 0x2007, 0x55);
+        emu.ppu.write(&mut bus, 0x2007, 0x66);
+
+        emu.ppu.set_mask(registers::MaskReg::SHOW_BACKGROUND);
+
+        // Stop right after the coarse X increment of a tile fetch
+        while emu.ppu.scanline != 10 || emu.ppu.cycle_count != 104 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+
+        // Mid-scanline write directly changes the address used by the tile fetches
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x42);
+
+        let mut fetched_tiles = Vec::new();
+        while fetched_tiles.len() < 2 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+
+            if (emu.ppu.cycle_count - 1) & 0x7 == 1 {
+                fetched_tiles.push(emu.ppu.nt_buffer);
+            }
+        }
+
+        assert_eq!(fetched_tiles, [0x55, 0x66]);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn nt_buffer_holds_fetched_tile() {
+        let mut emu = mock_emu(&dummy_rom());
+        emu.name_tables[0] = 0x33;
+        emu.name_tables[2] = 0x55;
+        emu.ppu.set_mask(registers::MaskReg::SHOW_BACKGROUND);
+
+        // The first two tiles of a scanline are fetched at the end of the previous one
+        while emu.ppu.scanline() != -1 || emu.ppu.cycle() != 322 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+        assert_eq!(emu.ppu.nt_buffer(), 0x33);
+
+        while emu.ppu.scanline() != 0 || emu.ppu.cycle() != 2 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+        assert_eq!(emu.ppu.nt_buffer(), 0x55);
+    }
+
+    #[test]
+    fn oam_bytes_read_back() {
+        let mut emu = mock_emu(&dummy_rom());
+
+        let mut oam = [0u8; 256];
+        for (i, b) in oam.iter_mut().enumerate() {
+            *b = (i as u8).wrapping_mul(7);
+        }
+        emu.ppu.set_oam_bytes(&oam);
+        assert_eq!(emu.ppu.oam_bytes(), &oam);
+
+        let mut bus = borrow_ppu_bus!(emu);
+        for (i, &b) in oam.iter().enumerate() {
+            emu.ppu.write(&mut bus, 0x2003, i as u8);
+            assert_eq!(emu.ppu.read(&mut bus, 0x2004), b);
+        }
+    }
+
+    #[test]
+    fn sprite_evaluation_starts_at_oamaddr() {
+        let mut emu = mock_emu(&dummy_rom());
+
+        let mut oam = [0xFFu8; 256];
+        oam[0..8].copy_from_slice(&[10, 0xAA, 0, 0, 10, 0xBB, 0, 8]);
+        emu.ppu.set_oam_bytes(&oam);
+        emu.ppu.set_mask(registers::MaskReg::SHOW_SPRITES);
+
+        while emu.ppu.scanline != 9 || emu.ppu.cycle_count != 330 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.write(&mut bus, 0x2003, 4);
+
+        while emu.ppu.scanline != 10 || emu.ppu.cycle_count != 256 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+
+        // Sprite 0 is skipped since the evaluation started at sprite 1
+        assert_eq!(emu.ppu.secondary_oam[0..4], [10, 0xBB, 0, 8]);
+        assert_eq!(emu.ppu.secondary_oam[4], 0xFF);
+
+        // OAMADDR is reset afterwards
+        while emu.ppu.cycle_count != 320 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+        assert_eq!(emu.ppu.oam_addr_reg, 0);
+    }
+
+    // Evaluate the sprites of scanline 10 and return the sprite overflow flag
+    fn sprite_overflow(oam: &[u8; 256], mode: SpriteOverflowMode) -> bool {
+        let mut emu = mock_emu(&dummy_rom());
+        emu.ppu.set_oam_bytes(oam);
+        emu.ppu.set_mask(registers::MaskReg::SHOW_SPRITES);
+        emu.ppu.set_sprite_overflow_mode(mode);
+
+        while emu.ppu.scanline != 10 || emu.ppu.cycle_count != 257 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        }
+        emu.ppu
+            .status_reg
+            .contains(registers::StatusReg::SPRITE_OVERFLOW)
+    }
+
+    #[test]
+    fn sprite_overflow_modes() {
+        // 8 sprites on scanline 10, the others are out of range
+        let mut oam = [0xF0u8; 256];
+        for sprite in oam[..32].chunks_exact_mut(4) {
+            sprite[0] = 10;
+        }
+
+        // A 9th sprite right after
+        let mut nine_sprites = oam;
+        nine_sprites[32] = 10;
+
+        // After a miss, the tile byte of the next sprite is checked instead of its Y
+        let mut false_negative = oam;
+        false_negative[36] = 10;
+        let mut false_positive = oam;
+        false_positive[37] = 10;
+
+        // m wraps around after 4 misses, and checks the Y of the 13th sprite again
+        let mut m_wraps = oam;
+        m_wraps[48] = 10;
+
+        let expected = [
+            (nine_sprites, true, true),
+            (false_negative, false, true),
+            (false_positive, true, false),
+            (m_wraps, true, true),
+            (oam, false, false),
+        ];
+        for (i, (oam, hardware, exact)) in expected.iter().enumerate() {
+            assert_eq!(
+                sprite_overflow(oam, SpriteOverflowMode::Hardware),
+                *hardware,
+                "case {}",
+                i
+            );
+            assert_eq!(
+                sprite_overflow(oam, SpriteOverflowMode::Exact),
+                *exact,
+                "case {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn vblank_and_rendering_status() {
+        let mut emu = mock_emu(&dummy_rom());
+        assert!(!emu.ppu.is_vblank());
+
+        let clock = |emu: &mut MockEmulator| {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+        };
+
+        while !emu.ppu.is_vblank() {
+            clock(&mut emu);
+        }
+        assert_eq!(emu.ppu.scanline, 241);
+        assert_eq!(emu.ppu.cycle_count, 1);
+
+        // Reading the status doesn't end the vblank
+        let mut bus = borrow_ppu_bus!(emu);
+        emu.ppu.read(&mut bus, 0x2002);
+        assert!(emu.ppu.is_vblank());
+
+        // Rendering is disabled
+        assert!(!emu.ppu.is_rendering());
+        while emu.ppu.is_vblank() {
+            clock(&mut emu);
+        }
+        assert_eq!(emu.ppu.scanline, -1);
+
+        emu.ppu.set_mask(registers::MaskReg::SHOW_BACKGROUND);
+        assert!(!emu.ppu.is_rendering());
+        while emu.ppu.scanline != 0 {
+            clock(&mut emu);
+        }
+        assert!(emu.ppu.is_rendering());
+    }
+
+    #[test]
+    fn disabled_rendering_frame() {
+        let mut emu = mock_emu(&dummy_rom());
+        set_backdrop_color(&mut emu, 0x21);
+        emu.ppu.set_ctrl(registers::ControlReg::GENERATE_NMI);
+
+        let frame = run_frame(&mut emu);
+        assert!(frame.iter().all(|&c| c == 0x21));
+
+        // NMI is raised at the start of the vblank, and there is no skipped dot on odd frames
+        let mut nmi_dots = Vec::new();
+        let mut dot = 0u32;
+        while nmi_dots.len() < 3 {
+            let mut bus = borrow_ppu_bus!(emu);
+            emu.ppu.clock(&mut bus);
+            dot += 1;
+
+            if emu.ppu.take_vblank_nmi_set_state() {
+                assert_eq!((emu.ppu.scanline, emu.ppu.cycle_count), (241, 1));
+                nmi_dots.push(dot);
+            }
+        }
+
+        assert_eq!(nmi_dots[1] - nmi_dots[0], 341 * 262);
+        assert_eq!(nmi_dots[2] - nmi_dots[1], 341 * 262);
+
+        // The rest of the frame is filled again when the backdrop changes mid-frame
+        clock_until(&mut emu, 100, 50);
+        set_backdrop_color(&mut emu, 0x16);
+        let frame = run_frame(&mut emu);
+        let split = 100 * 256 + 50;
+        assert!(frame[..split].iter().all(|&c| c == 0x21));
+        assert!(frame[split..].iter().all(|&c| c == 0x16));
+    }
+
+    #[test]
+    fn vblank_read_race() {
+        // Read $2002 at a dot around the start of the vblank, and see what the next dots do
+        let race = |scanline: i16, dot: u16| {
+            let mut emu = mock_emu(&dummy_rom());
+            emu.ppu.set_ctrl(registers::ControlReg::GENERATE_NMI);
+            while (emu.ppu.scanline, emu.ppu.cycle_count) != (scanline, dot) {
+                let mut bus = borrow_ppu_bus!(emu);
+                emu.ppu.clock(&mut bus);
+            }
+
+            let mut bus = borrow_ppu_bus!(emu);
+            let status = emu.ppu.read(&mut bus, 0x2002) & 0x80;
+            let suppressed = emu.ppu.take_vblank_nmi_suppressed_state();
+            let mut nmi_count = emu.ppu.take_vblank_nmi_set_state() as u32;
+            for _ in 0..10 {
+                emu.ppu.clock(&mut bus);
+                nmi_count += emu.ppu.take_vblank_nmi_set_state() as u32;
+            }
+            let vblank_flag = emu
+                .ppu
+                .status_reg
+                .contains(registers::StatusReg::VBLANK_STARTED);
+
+            // Only this frame is affected
+            for _ in 0..341 * 262 {
+                emu.ppu.clock(&mut bus);
+                nmi_count += emu.ppu.take_vblank_nmi_set_state() as u32;
+            }
+
+            (status, nmi_count, suppressed, vblank_flag)
+        };
+
+        // Two dots before, the flag and the NMI come normally
+        assert_eq!(race(240, 340), (0, 2, false, true));
+        // One dot before, the flag is read as clear and never set for this frame
+        assert_eq!(race(241, 0), (0, 1, false, false));
+        // On the same dot or the next one, the flag is read as set and the NMI is cancelled
+        assert_eq!(race(241, 1), (0x80, 1, true, false));
+        assert_eq!(race(241, 2), (0x80, 1, true, false));
+        // After that, the NMI was already raised
+        assert_eq!(race(241, 3), (0x80, 2, false, false));
+    }
+
+    #[test]
+    fn palette_read_fills_buffer_with_nametable() {
+        let mut emu = mock_emu(&dummy_rom());
+        let mut bus = borrow_ppu_bus!(emu);
+
+        emu.ppu.write(&mut bus, 0x2006, 0x2F);
+        emu.ppu.write(&mut bus, 0x2006, 0x05);
+        emu.ppu.write(&mut bus, 0x2007, 0x77);
+
+        emu.ppu.write(&mut bus, 0x2006, 0x3F);
+        emu.ppu.write(&mut bus, 0x2006, 0x05);
+        emu.ppu.write(&mut bus, 0x2007, 0x12);
+
+        // Palette data is returned immediately
+        emu.ppu.write(&mut bus, 0x2006, 0x3F);
+        emu.ppu.write(&mut bus, 0x2006, 0x05);
+        assert_eq!(emu.ppu.read(&mut bus, 0x2007), 0x12);
+
+        // The next buffered read returns the nametable byte at $2F05
+        emu.ppu.write(&mut bus, 0x2006, 0x20);
+        emu.ppu.write(&mut bus, 0x2006, 0x00);
+        assert_eq!(emu.ppu.read(&mut bus, 0x2007), 0x77);
+    }
+
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn forced_sprite_zero_hit() {
+        let mut emu = mock_emu(&dummy_rom());
+        let mut bus = borrow_ppu_bus!(emu);
+
+        emu.ppu.force_sprite_zero_hit(Some(true));
+        assert_eq!(emu.ppu.read(&mut bus, 0x2002) & 0x40, 0x40);
+
+        emu.ppu
+            .status_reg
+            .insert(registers::StatusReg::SPRITE_ZERO_HIT);
+        emu.ppu.force_sprite_zero_hit(Some(false));
+        assert_eq!(emu.ppu.read(&mut bus, 0x2002) & 0x40, 0);
+
+        emu.ppu.force_sprite_zero_hit(None);
+        assert_eq!(emu.ppu.read(&mut bus, 0x2002) & 0x40, 0x40);
+
+        // The override is a setting of the frontend, so it survives a reset
+        emu.ppu.force_sprite_zero_hit(Some(true));
+        emu.ppu.reset();
+        assert_eq!(emu.ppu.read(&mut bus, 0x2002) & 0x40, 0x40);
+    }
+
+    // Example from https://wiki.nesdev.com/w/index.php/PPU_scrolling#Summary
+    #[cfg(feature = "debugger")]
+    #[test]
+    fn loopy_registers() {
+        let mut emu = mock_emu(&dummy_rom());
+        let mut bus = borrow_ppu_bus!(emu);
+
+        emu.ppu.write(&mut bus, 0x2000, 0x00);
+        emu.ppu.write(&mut bus, 0x2005, 0x7D);
+        assert_eq!(emu.ppu.temp_vram_address(), 0x000F);
+        assert_eq!(emu.ppu.fine_x(), 0x05);
+
+        emu.ppu.write(&mut bus, 0x2005, 0x5E);
+        assert_eq!(emu.ppu.temp_vram_address(), 0x616F);
+
+        emu.ppu.write(&mut bus, 0x2006, 0x3D);
+        assert_eq!(emu.ppu.temp_vram_address(), 0x3D6F);
+        assert_eq!(emu.ppu.vram_address(), 0x0000);
+
+        emu.ppu.write(&mut bus, 0x2006, 0xF0);
+        assert_eq!(emu.ppu.temp_vram_address(), 0x3DF0);
+        assert_eq!(emu.ppu.vram_address(), 0x3DF0);
+        assert_eq!(emu.ppu.fine_x(), 0x05);
+    }
 }
@@ -4,6 +4,7 @@ use bitflags::bitflags;
 bitfield! {
     /// A Vram address. Used to read and write on the PPU bus and during rendering
     #[derive(Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct VramAddr(u16);
 
     /// The X coordinate of the tile index
@@ -88,6 +89,7 @@ impl VramAddr {
 
 bitflags! {
     /// http://wiki.nesdev.com/w/index.php/PPU_registers#PPUCTRL
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ControlReg: u8 {
         /// N: Base nametable address lower bit
         const NAMETABLE_ADDR_LO = 0b00000001;
@@ -181,6 +183,7 @@ impl ControlReg {
 
 bitflags! {
     /// http://wiki.nesdev.com/w/index.php/PPU_registers#PPUSTATUS
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct StatusReg: u8 {
         /// O: Sprite overflow. The intent was for this flag to be set
         /// whenever more than eight sprites appear on a scanline, but a
@@ -220,6 +223,7 @@ impl StatusReg {
 
 bitflags! {
     /// http://wiki.nesdev.com/w/index.php/PPU_registers#PPUMASK
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct MaskReg: u8 {
         /// G: Greyscale (0: normal color, 1: produce a greyscale display)
         const GREYSCALE = 0b00000001;
@@ -1,5 +1,6 @@
 /// State machine for the sprite evaluation phase.
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SpriteEvalutationState {
     /// Idle until the end of the evaluation when all 64 sprites have been evaluated
     Idle,
@@ -22,8 +23,25 @@ impl Default for SpriteEvalutationState {
     }
 }
 
+/// How the sprite overflow flag is evaluated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpriteOverflowMode {
+    /// Reproduce the hardware bug: once 8 sprites are found, the byte checked against the scanline moves
+    /// diagonally through the OAM, which gives false positives and false negatives
+    Hardware,
+    /// Set the flag exactly when more than 8 sprites are on the scanline
+    Exact,
+}
+
+impl Default for SpriteOverflowMode {
+    fn default() -> Self {
+        Self::Hardware
+    }
+}
+
 /// State of a sprite on the current scanline
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SpriteXCounter {
     /// The sprite is not on the scanline at all. Happens when < 8 sprites are on the scanline
     WontRender,
@@ -46,6 +64,7 @@ impl Default for SpriteXCounter {
 
 /// State of the sprite 0 hit
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SpriteZeroHitState {
     /// The state machine is simply idle
     Idle,
@@ -1,3 +1,4 @@
+/// Palette used by default, for the 2C02 PPU of the NTSC NES
 pub const RGB_PALETTE: [[u8; 3]; 64] = [
     [0x7C, 0x7C, 0x7C],
     [0x00, 0x00, 0xFC],
@@ -64,3 +65,233 @@ pub const RGB_PALETTE: [[u8; 3]; 64] = [
     [0x00, 0x00, 0x00],
     [0x00, 0x00, 0x00],
 ];
+
+/// Palette of the 2C03 RGB PPU, used by the PlayChoice-10 and some Vs. System boards.
+/// Each component has 8 levels.
+pub const RGB_PALETTE_2C03: [[u8; 3]; 64] = [
+    [0x6D, 0x6D, 0x6D],
+    [0x00, 0x24, 0x92],
+    [0x00, 0x00, 0xDB],
+    [0x6D, 0x49, 0xDB],
+    [0x92, 0x00, 0x6D],
+    [0xB6, 0x00, 0x6D],
+    [0xB6, 0x24, 0x00],
+    [0x92, 0x49, 0x00],
+    [0x6D, 0x49, 0x00],
+    [0x24, 0x49, 0x00],
+    [0x00, 0x6D, 0x24],
+    [0x00, 0x92, 0x00],
+    [0x00, 0x49, 0x49],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xB6, 0xB6, 0xB6],
+    [0x00, 0x6D, 0xDB],
+    [0x00, 0x49, 0xFF],
+    [0x92, 0x00, 0xFF],
+    [0xB6, 0x00, 0xFF],
+    [0xFF, 0x00, 0x92],
+    [0xFF, 0x00, 0x00],
+    [0xDB, 0x6D, 0x00],
+    [0x92, 0x6D, 0x00],
+    [0x24, 0x92, 0x00],
+    [0x00, 0x92, 0x00],
+    [0x00, 0xB6, 0x6D],
+    [0x00, 0x92, 0x92],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0x6D, 0xB6, 0xFF],
+    [0x92, 0x92, 0xFF],
+    [0xDB, 0x6D, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0xFF, 0x6D, 0xFF],
+    [0xFF, 0x92, 0x00],
+    [0xFF, 0xB6, 0x00],
+    [0xDB, 0xDB, 0x00],
+    [0x6D, 0xDB, 0x00],
+    [0x00, 0xFF, 0x00],
+    [0x49, 0xFF, 0xDB],
+    [0x00, 0xFF, 0xFF],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0xB6, 0xDB, 0xFF],
+    [0xDB, 0xB6, 0xFF],
+    [0xFF, 0xB6, 0xFF],
+    [0xFF, 0x92, 0xFF],
+    [0xFF, 0xB6, 0xB6],
+    [0xFF, 0xDB, 0x92],
+    [0xFF, 0xFF, 0x49],
+    [0xFF, 0xFF, 0x6D],
+    [0xB6, 0xFF, 0x49],
+    [0x92, 0xFF, 0x6D],
+    [0x49, 0xFF, 0xDB],
+    [0x92, 0xDB, 0xFF],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+];
+
+/// Palette of the 2C07 PPU of the PAL NES, decoded from its composite signal levels.
+/// Its color burst phase is shifted from the 2C02, so the hues are rotated.
+pub const RGB_PALETTE_2C07: [[u8; 3]; 64] = [
+    [0x6F, 0x6F, 0x6F],
+    [0x00, 0x46, 0x82],
+    [0x00, 0x26, 0xBE],
+    [0x2B, 0x04, 0xD4],
+    [0x5D, 0x00, 0xBE],
+    [0x80, 0x00, 0x82],
+    [0x8D, 0x00, 0x2B],
+    [0x80, 0x0E, 0x00],
+    [0x5D, 0x30, 0x00],
+    [0x2B, 0x4E, 0x00],
+    [0x00, 0x62, 0x00],
+    [0x00, 0x68, 0x00],
+    [0x00, 0x5E, 0x2B],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xB5, 0xB5, 0xB5],
+    [0x00, 0x82, 0xCE],
+    [0x1D, 0x5A, 0xFF],
+    [0x60, 0x32, 0xFF],
+    [0x9F, 0x15, 0xFF],
+    [0xCB, 0x0C, 0xCE],
+    [0xDB, 0x1B, 0x60],
+    [0xCB, 0x3D, 0x00],
+    [0x9F, 0x66, 0x00],
+    [0x60, 0x8C, 0x00],
+    [0x1D, 0xA5, 0x00],
+    [0x00, 0xAD, 0x00],
+    [0x00, 0xA0, 0x60],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0x3F, 0xCE, 0xFF],
+    [0x6F, 0xA8, 0xFF],
+    [0xAD, 0x82, 0xFF],
+    [0xEA, 0x68, 0xFF],
+    [0xFF, 0x60, 0xFF],
+    [0xFF, 0x6E, 0xAD],
+    [0xFF, 0x8C, 0x3B],
+    [0xEA, 0xB3, 0x00],
+    [0xAD, 0xD7, 0x00],
+    [0x6F, 0xF0, 0x00],
+    [0x3F, 0xF7, 0x3B],
+    [0x2C, 0xEB, 0xAD],
+    [0x57, 0x57, 0x57],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0xB3, 0xEB, 0xFF],
+    [0xC5, 0xDB, 0xFF],
+    [0xDE, 0xCD, 0xFF],
+    [0xF6, 0xC2, 0xFF],
+    [0xFF, 0xBF, 0xFF],
+    [0xFF, 0xC4, 0xDE],
+    [0xFF, 0xD0, 0xB1],
+    [0xF6, 0xE0, 0x90],
+    [0xDE, 0xEF, 0x84],
+    [0xC5, 0xF9, 0x90],
+    [0xB3, 0xFC, 0xB1],
+    [0xAC, 0xF7, 0xDE],
+    [0xBC, 0xBC, 0xBC],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+];
+
+/// FirebrandX's Nostalgia-FBX palette, which mimics the NES composite output on a consumer CRT
+pub const RGB_PALETTE_NOSTALGIA_FBX: [[u8; 3]; 64] = [
+    [0x6A, 0x6D, 0x6A],
+    [0x00, 0x13, 0x80],
+    [0x1E, 0x00, 0x8A],
+    [0x39, 0x00, 0x7A],
+    [0x55, 0x00, 0x56],
+    [0x5A, 0x00, 0x18],
+    [0x4F, 0x10, 0x00],
+    [0x3D, 0x1C, 0x00],
+    [0x25, 0x32, 0x00],
+    [0x00, 0x3D, 0x00],
+    [0x00, 0x40, 0x00],
+    [0x00, 0x39, 0x24],
+    [0x00, 0x2E, 0x55],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xB9, 0xBC, 0xB9],
+    [0x18, 0x50, 0xC7],
+    [0x4B, 0x30, 0xE3],
+    [0x73, 0x22, 0xD6],
+    [0x95, 0x1F, 0xA9],
+    [0x9D, 0x28, 0x5C],
+    [0x98, 0x37, 0x00],
+    [0x7F, 0x4C, 0x00],
+    [0x5E, 0x64, 0x00],
+    [0x22, 0x77, 0x00],
+    [0x02, 0x7E, 0x02],
+    [0x00, 0x76, 0x45],
+    [0x00, 0x6E, 0x8A],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0x68, 0xA6, 0xFF],
+    [0x8C, 0x9C, 0xFF],
+    [0xB5, 0x86, 0xFF],
+    [0xD9, 0x75, 0xFD],
+    [0xE3, 0x77, 0xB9],
+    [0xE5, 0x8D, 0x68],
+    [0xD4, 0x9D, 0x29],
+    [0xB3, 0xAF, 0x0C],
+    [0x7B, 0xC2, 0x11],
+    [0x55, 0xCA, 0x47],
+    [0x46, 0xCB, 0x81],
+    [0x47, 0xC1, 0xC5],
+    [0x4A, 0x4D, 0x4A],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+    [0xFF, 0xFF, 0xFF],
+    [0xCC, 0xEA, 0xFF],
+    [0xDD, 0xDE, 0xFF],
+    [0xEC, 0xDA, 0xFF],
+    [0xF8, 0xD7, 0xFE],
+    [0xFC, 0xD6, 0xF5],
+    [0xFD, 0xDB, 0xCF],
+    [0xF9, 0xE7, 0xB5],
+    [0xF1, 0xF0, 0xAA],
+    [0xDA, 0xFA, 0xA9],
+    [0xC9, 0xFF, 0xBC],
+    [0xC3, 0xFB, 0xD7],
+    [0xC4, 0xF6, 0xF6],
+    [0xBE, 0xC1, 0xBE],
+    [0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x00],
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PaletteError {
+    UnknownName,
+}
+
+impl core::fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:?}", &self)
+    }
+}
+
+/// Names of the bundled palettes, for `palette_by_name`
+pub const PALETTE_NAMES: [&str; 4] = ["2C02", "2C03", "2C07-PAL", "FBX-NostalgiaFBX"];
+
+pub fn palette_by_name(name: &str) -> Result<[[u8; 3]; 64], PaletteError> {
+    match name {
+        "2C02" => Ok(RGB_PALETTE),
+        "2C03" => Ok(RGB_PALETTE_2C03),
+        "2C07-PAL" => Ok(RGB_PALETTE_2C07),
+        "FBX-NostalgiaFBX" => Ok(RGB_PALETTE_NOSTALGIA_FBX),
+        _ => Err(PaletteError::UnknownName),
+    }
+}
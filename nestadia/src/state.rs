@@ -0,0 +1,295 @@
+//! Save states, to snapshot the whole machine and restore it later.
+//! The states are encoded with bincode, and only contain the emulated hardware: the settings of the frontend,
+//! like the sample rate or the palette, are kept when a state is loaded.
+//! The encoded state starts with a header, so states from another version of the format or another game are rejected.
+
+use alloc::vec::Vec;
+
+use bincode::error::DecodeError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::apu::Apu;
+use crate::cartridge::CartridgeState;
+use crate::cpu::Cpu;
+use crate::ppu::Ppu;
+use crate::{Emulator, RAM_SIZE};
+
+/// Identifies the data as a save state
+const MAGIC: &[u8; 4] = b"NSTA";
+/// Bump this when the content of `EmulatorState` changes
+const VERSION: u16 = 4;
+const HEADER_SIZE: usize = MAGIC.len() + 2 + 4; // Followed by the CRC32 of the ROM
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    /// The state was made by another version of the format
+    VersionMismatch,
+    /// The state was made with another game
+    WrongGame,
+    /// The state ends before all of the machine was read
+    Truncated,
+    /// The state doesn't describe this machine
+    Corrupt,
+    /// There's no state saved in this slot
+    EmptySlot,
+}
+
+impl core::fmt::Display for StateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            StateError::VersionMismatch => {
+                write!(
+                    f,
+                    "the save state was made by another version of the emulator"
+                )
+            }
+            StateError::WrongGame => write!(f, "the save state was made with another game"),
+            StateError::Truncated => write!(f, "the save state is truncated"),
+            StateError::Corrupt => write!(f, "the save state is corrupted"),
+            StateError::EmptySlot => write!(f, "there's no save state in this slot"),
+        }
+    }
+}
+
+/// Numbered save slots, so the frontends share the same behavior.
+/// The states are kept in memory: frontends can persist them through `slot` and `set_slot`.
+#[derive(Clone, Debug, Default)]
+pub struct SaveStateManager {
+    slots: Vec<Option<Vec<u8>>>,
+}
+
+impl SaveStateManager {
+    pub fn new(slot_count: usize) -> Self {
+        Self {
+            slots: alloc::vec![None; slot_count],
+        }
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Save the state of the emulator in a slot, replacing the previous one.
+    /// Panics if the slot is out of range.
+    pub fn save_slot(&mut self, slot: usize, emulator: &Emulator) {
+        self.slots[slot] = Some(emulator.save_state());
+    }
+
+    pub fn load_slot(&self, slot: usize, emulator: &mut Emulator) -> Result<(), StateError> {
+        emulator.load_state(self.slot(slot).ok_or(StateError::EmptySlot)?)
+    }
+
+    pub fn slot_exists(&self, slot: usize) -> bool {
+        self.slot(slot).is_some()
+    }
+
+    /// Slots with a state saved in them, in order
+    pub fn occupied_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, slot)| slot.as_ref().map(|_| i))
+    }
+
+    /// Raw state saved in a slot
+    pub fn slot(&self, slot: usize) -> Option<&[u8]> {
+        self.slots.get(slot)?.as_deref()
+    }
+
+    /// Put a state back in a slot, like one read from a file. It's only validated when loaded.
+    /// Panics if the slot is out of range.
+    pub fn set_slot(&mut self, slot: usize, state: Option<Vec<u8>>) {
+        self.slots[slot] = state;
+    }
+}
+
+pub(crate) fn save(emulator: &Emulator) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&VERSION.to_le_bytes());
+    data.extend_from_slice(&emulator.rom_crc32().to_le_bytes());
+    data.extend_from_slice(&encode(&EmulatorState::save(emulator)));
+    data
+}
+
+pub(crate) fn load(emulator: &mut Emulator, data: &[u8]) -> Result<(), StateError> {
+    if data.len() < HEADER_SIZE {
+        return Err(StateError::Truncated);
+    }
+
+    let (header, state) = data.split_at(HEADER_SIZE);
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(StateError::Corrupt);
+    }
+
+    let (version, rom_crc32) = header[MAGIC.len()..].split_at(2);
+    if version != VERSION.to_le_bytes() {
+        return Err(StateError::VersionMismatch);
+    }
+
+    if rom_crc32 != emulator.rom_crc32().to_le_bytes() {
+        return Err(StateError::WrongGame);
+    }
+
+    decode::<EmulatorState>(state)?.load(emulator)
+}
+
+pub(crate) fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    bincode::serde::encode_to_vec(value, bincode::config::standard())
+        .expect("encoding to a Vec can't fail")
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, StateError> {
+    match bincode::serde::decode_from_slice(data, bincode::config::standard()) {
+        Ok((value, len)) if len == data.len() => Ok(value),
+        Ok(_) => Err(StateError::Corrupt), // Trailing bytes
+        Err(DecodeError::UnexpectedEnd { .. }) => Err(StateError::Truncated),
+        Err(_) => Err(StateError::Corrupt),
+    }
+}
+
+/// Serde only supports arrays of up to 32 elements, so the larger memories are encoded as bytes
+pub(crate) mod byte_array {
+    use core::convert::TryInto;
+    use core::fmt;
+
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        data: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        deserializer.deserialize_bytes(ByteArrayVisitor::<N>)
+    }
+
+    struct ByteArrayVisitor<const N: usize>;
+
+    impl<'de, const N: usize> Visitor<'de> for ByteArrayVisitor<N> {
+        type Value = [u8; N];
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{} bytes", N)
+        }
+
+        fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut data = [0u8; N];
+            for (i, byte) in data.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            Ok(data)
+        }
+    }
+}
+
+pub(crate) mod boxed_byte_array {
+    use alloc::boxed::Box;
+
+    use serde::{Deserializer, Serializer};
+
+    #[allow(clippy::borrowed_box)] // That's the type of the field
+    pub fn serialize<S: Serializer, const N: usize>(
+        data: &Box<[u8; N]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        super::byte_array::serialize(data, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<Box<[u8; N]>, D::Error> {
+        super::byte_array::deserialize(deserializer).map(Box::new)
+    }
+}
+
+/// Everything that changes while the machine runs
+#[derive(Serialize, Deserialize)]
+struct EmulatorState {
+    apu: Apu,
+    cartridge: CartridgeState,
+
+    cpu: Cpu,
+    controller1: u8,
+    controller2: u8,
+    controller_state: bool,
+    controller1_snapshot: u8,
+    controller2_snapshot: u8,
+    #[serde(with = "byte_array")]
+    ram: [u8; RAM_SIZE as usize],
+
+    ppu: Ppu,
+    #[serde(with = "byte_array")]
+    name_tables: [u8; 1024 * 4],
+
+    clock_count: u8,
+    total_cycles: u64,
+    frame_count: u64,
+}
+
+impl EmulatorState {
+    fn save(emulator: &Emulator) -> Self {
+        Self {
+            apu: emulator.apu.clone(),
+            cartridge: emulator.cartridge.save_state(),
+
+            cpu: emulator.cpu.clone(),
+            controller1: emulator.controller1,
+            controller2: emulator.controller2,
+            controller_state: emulator.controller_state,
+            controller1_snapshot: emulator.controller1_snapshot,
+            controller2_snapshot: emulator.controller2_snapshot,
+            ram: emulator.ram,
+
+            ppu: emulator.ppu.clone(),
+            name_tables: emulator.name_tables,
+
+            clock_count: emulator.clock_count,
+            total_cycles: emulator.total_cycles,
+            frame_count: emulator.frame_count,
+        }
+    }
+
+    fn load(self, emulator: &mut Emulator) -> Result<(), StateError> {
+        // The cartridge is the only part that can be rejected, so it's loaded first to leave the emulator untouched
+        emulator.cartridge.load_state(self.cartridge)?;
+        emulator.apu.load_state(self.apu);
+
+        // The opcode coverage is a diagnostic of the whole run, so it's kept
+        #[cfg(feature = "opcode-coverage")]
+        let executed_opcodes = emulator.cpu.executed_opcodes;
+        emulator.cpu = self.cpu;
+        #[cfg(feature = "opcode-coverage")]
+        {
+            emulator.cpu.executed_opcodes = executed_opcodes;
+        }
+        emulator.controller1 = self.controller1;
+        emulator.controller2 = self.controller2;
+        emulator.controller_state = self.controller_state;
+        emulator.controller1_snapshot = self.controller1_snapshot;
+        emulator.controller2_snapshot = self.controller2_snapshot;
+        emulator.ram = self.ram;
+
+        emulator.ppu.load_state(self.ppu);
+        emulator.name_tables = self.name_tables;
+
+        let previous_frame_count = emulator.frame_count;
+        emulator.clock_count = self.clock_count;
+        emulator.total_cycles = self.total_cycles;
+        emulator.frame_count = self.frame_count;
+        emulator.rebase_timeline(previous_frame_count);
+
+        Ok(())
+    }
+}